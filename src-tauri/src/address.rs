@@ -0,0 +1,211 @@
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// NAS・検査機器のアドレス（ホスト名/IPv4/IPv6 + 任意のポート番号）
+#[derive(Debug, Clone, PartialEq)]
+pub struct HostAddress {
+    pub host: String,
+    pub port: Option<u16>,
+}
+
+impl HostAddress {
+    /// ホストがIPv6アドレスかどうか（ゾーンID付きも含む）
+    pub fn is_ipv6(&self) -> bool {
+        parse_ipv6(&self.host).is_some()
+    }
+}
+
+/// アドレス文字列を解析する
+/// 対応形式: "192.168.0.10", "192.168.0.10:1445", "nas01", "nas01.local:445",
+///           "fe80::1", "fe80::1%eth0", "[fe80::1]", "[fe80::1]:445"
+pub fn parse_host_address(address: &str) -> Result<HostAddress, String> {
+    let address = address.trim();
+    if address.is_empty() {
+        return Err("アドレスが空です".to_string());
+    }
+    if address.starts_with(['\\', '/']) {
+        return Err(format!("UNCパスではなくIPアドレスまたはホスト名を指定してください: {}", address));
+    }
+
+    // [IPv6]:port 形式
+    if let Some(rest) = address.strip_prefix('[') {
+        let (host, after) = rest
+            .split_once(']')
+            .ok_or(format!("IPv6アドレスの括弧が閉じていません: {}", address))?;
+        if parse_ipv6(host).is_none() {
+            return Err(format!("IPv6アドレスが不正です: {}", host));
+        }
+        let port = if after.is_empty() {
+            None
+        } else {
+            let port = after
+                .strip_prefix(':')
+                .ok_or(format!("IPv6アドレスの後ろの形式が不正です: {}", address))?;
+            Some(parse_port(port)?)
+        };
+        return Ok(HostAddress { host: host.to_string(), port });
+    }
+
+    // 括弧なしのIPv6（ポート指定不可）
+    if parse_ipv6(address).is_some() {
+        return Ok(HostAddress { host: address.to_string(), port: None });
+    }
+
+    // IPv4 / ホスト名（:port 付きも可）
+    let (host, port) = match address.rsplit_once(':') {
+        Some((host, port)) => (host, Some(parse_port(port)?)),
+        None => (address, None),
+    };
+
+    if host.parse::<Ipv4Addr>().is_ok() || is_valid_hostname(host) {
+        Ok(HostAddress { host: host.to_string(), port })
+    } else {
+        Err(format!("IPアドレスまたはホスト名が不正です: {}", host))
+    }
+}
+
+/// アドレスを名前解決して接続先の一覧を取得
+/// ポート未指定の場合はdefault_portを使用
+pub fn resolve_socket_addrs(address: &str, default_port: u16) -> Result<Vec<SocketAddr>, String> {
+    let host_address = parse_host_address(address)?;
+    let port = host_address.port.unwrap_or(default_port);
+
+    let addrs: Vec<SocketAddr> = (host_address.host.as_str(), port)
+        .to_socket_addrs()
+        .map_err(|e| format!("名前解決に失敗しました {}: {}", host_address.host, e))?
+        .collect();
+
+    if addrs.is_empty() {
+        return Err(format!("名前解決の結果がありません: {}", host_address.host));
+    }
+
+    Ok(addrs)
+}
+
+/// アドレスへのTCP接続を試行（解決されたアドレスのいずれかに接続できればtrue）
+pub fn check_tcp_connection(address: &str, default_port: u16, timeout: Duration) -> bool {
+    let addrs = match resolve_socket_addrs(address, default_port) {
+        Ok(addrs) => addrs,
+        Err(e) => {
+            log::warn!("{}", e);
+            return false;
+        }
+    };
+
+    addrs
+        .iter()
+        .any(|addr| TcpStream::connect_timeout(addr, timeout).is_ok())
+}
+
+/// UNCパス（\\host\share）に使うホスト名を取得
+/// UNCパスにはポートを指定できないため除外し、IPv6はipv6-literal.net形式に変換する
+pub fn unc_host(address: &str) -> String {
+    match parse_host_address(address) {
+        Ok(host_address) if host_address.is_ipv6() => {
+            format!("{}.ipv6-literal.net", host_address.host.replace(':', "-").replace('%', "s"))
+        }
+        Ok(host_address) => host_address.host,
+        Err(_) => address.to_string(),
+    }
+}
+
+/// ゾーンID（%eth0など）を除いてIPv6アドレスとして解析
+fn parse_ipv6(host: &str) -> Option<Ipv6Addr> {
+    let addr = host.split_once('%').map(|(addr, _)| addr).unwrap_or(host);
+    addr.parse::<Ipv6Addr>().ok()
+}
+
+/// ポート番号を解析
+fn parse_port(port: &str) -> Result<u16, String> {
+    match port.parse::<u16>() {
+        Ok(port) if port != 0 => Ok(port),
+        _ => Err(format!("ポート番号が不正です: {}", port)),
+    }
+}
+
+/// ホスト名として有効か判定（RFC 1123）
+fn is_valid_hostname(host: &str) -> bool {
+    let host = host.strip_suffix('.').unwrap_or(host);
+    if host.is_empty() || host.len() > 253 {
+        return false;
+    }
+
+    let labels: Vec<&str> = host.split('.').collect();
+
+    // 数字だけのラベルのみで構成される場合は誤ったIPv4アドレスとみなす
+    if labels.iter().all(|label| label.chars().all(|c| c.is_ascii_digit())) {
+        return false;
+    }
+
+    labels.iter().all(|label| {
+        !label.is_empty()
+            && label.len() <= 63
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn host(host: &str, port: Option<u16>) -> HostAddress {
+        HostAddress { host: host.to_string(), port }
+    }
+
+    #[test]
+    fn parses_valid_addresses() {
+        let cases = [
+            ("192.168.0.10", host("192.168.0.10", None)),
+            (" 192.168.0.10:1445 ", host("192.168.0.10", Some(1445))),
+            ("nas01", host("nas01", None)),
+            ("nas01.local:445", host("nas01.local", Some(445))),
+            ("nas-01.example.com.", host("nas-01.example.com.", None)),
+            ("fe80::1", host("fe80::1", None)),
+            ("::1", host("::1", None)),
+            ("fe80::1%eth0", host("fe80::1%eth0", None)),
+            ("[fe80::1]", host("fe80::1", None)),
+            ("[fe80::1]:445", host("fe80::1", Some(445))),
+            ("[fe80::1%12]:22", host("fe80::1%12", Some(22))),
+        ];
+        for (address, expected) in cases {
+            assert_eq!(parse_host_address(address), Ok(expected), "{}", address);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_addresses() {
+        let cases = [
+            "",
+            "   ",
+            "\\\\192.168.0.10",
+            "\\\\nas01\\share",
+            "//nas01/share",
+            "192.168.0.256",
+            "192.168.0",
+            "192.168.0.10:0",
+            "192.168.0.10:65536",
+            "192.168.0.10:abc",
+            "192.168.0.10:",
+            "nas_01",
+            "-nas01",
+            "nas..local",
+            "[fe80::1",
+            "[fe80::1]445",
+            "[nas01]:445",
+            "fe80::zz",
+        ];
+        for address in cases {
+            assert!(parse_host_address(address).is_err(), "{}", address);
+        }
+    }
+
+    #[test]
+    fn unc_host_converts_ipv6() {
+        assert_eq!(unc_host("192.168.0.10:1445"), "192.168.0.10");
+        assert_eq!(unc_host("nas01"), "nas01");
+        assert_eq!(unc_host("[fe80::1]:445"), "fe80--1.ipv6-literal.net");
+        assert_eq!(unc_host("fe80::1%12"), "fe80--1s12.ipv6-literal.net");
+    }
+}
@@ -1,10 +1,55 @@
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, MutexGuard};
 use tokio::sync::RwLock;
-use tokio::time::{interval, Duration};
+use tokio::time::{sleep, timeout_at, Duration, Instant};
 use tauri::{AppHandle, Emitter};
-use std::net::TcpStream;
 use std::time::Duration as StdDuration;
-use crate::types::{NasConfig, InspConfig,InspInfo,NasInfo};
+use chrono::{Local, NaiveDateTime};
+#[cfg(not(windows))]
+use sysinfo::Disks;
+use crate::types::{NasConfig, InspConfig,InspInfo,NasInfo,DeviceBackupResult,CategoryBackupStatus,BackupHistoryEntry,StorageBackendConfig,SourceAccessConfig};
+use crate::settings_monitor::SettingsMonitor;
+use crate::address::{check_tcp_connection, parse_host_address};
+use crate::drive_mapping::{dest_root_path, verify_drive_mapping};
+use crate::capacity_history::CapacityHistory;
+use crate::storage_backend::open_backend;
+use crate::source_access::{open_source, source_port, REMOTE_TIMEOUT};
+
+/// SMBの既定ポート
+const SMB_PORT: u16 = 445;
+
+/// 1台分の接続・容量チェックの制限時間
+/// SFTP・FTPの接続とログイン・一覧取得がそれぞれREMOTE_TIMEOUTまでかかるため、その合計に合わせる
+const PROBE_TIMEOUT: Duration = Duration::from_secs(REMOTE_TIMEOUT.as_secs() * 2);
+
+/// SFTP・FTPのコピー元パスを確認する間隔（確認のたびにログインするため、接続チェックより間隔を空ける）
+const REMOTE_SOURCE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// チェック中の機器の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum ProbeTarget {
+    Nas,
+    Insp,
+}
+
+/// 監視の周期をまたいで保持する状態
+#[derive(Default)]
+struct ProbeState {
+    in_flight: HashSet<(ProbeTarget, u32)>,     // チェックが終わっていない機器（制限時間を過ぎても処理中のものを含む）
+    source_checked: HashMap<u32, Instant>,      // SFTP・FTPのコピー元パスを最後に確認した時刻
+}
+
+/// チェック中の印（チェックの処理が終わった時に、制限時間を過ぎていても外す）
+struct InFlightGuard {
+    state: Arc<Mutex<ProbeState>>,
+    key: (ProbeTarget, u32),
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).in_flight.remove(&self.key);
+    }
+}
 
 /// アプリケーション全体の状態を管理する構造体
 /// NASと検査機器の両方の状態を一元管理
@@ -12,6 +57,7 @@ use crate::types::{NasConfig, InspConfig,InspInfo,NasInfo};
 pub struct AppMonitor {
     pub nas_configs: Arc<RwLock<Vec<NasConfig>>>,
     pub insp_configs: Arc<RwLock<Vec<InspConfig>>>,
+    probe_state: Arc<Mutex<ProbeState>>,
 }
 
 impl AppMonitor {
@@ -20,54 +66,209 @@ impl AppMonitor {
         Self {
             nas_configs: Arc::new(RwLock::new(nas_configs)),
             insp_configs: Arc::new(RwLock::new(insp_configs)),
+            probe_state: Arc::new(Mutex::new(ProbeState::default())),
         }
     }
 
-    /// 監視スレッドを開始（10秒ごとにNASと検査機器の状態をチェック）
-    pub fn start_monitoring(self, app_handle: AppHandle) {
-        tauri::async_runtime::spawn(async move {
-            let mut interval = interval(Duration::from_secs(10));
+    fn lock_probe_state(&self) -> MutexGuard<'_, ProbeState> {
+        self.probe_state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// チェックを開始する（前回のチェックが終わっていない場合はNone）
+    fn begin_probe(&self, target: ProbeTarget, id: u32) -> Option<InFlightGuard> {
+        let key = (target, id);
+        if !self.lock_probe_state().in_flight.insert(key) {
+            return None;
+        }
+        Some(InFlightGuard { state: self.probe_state.clone(), key })
+    }
 
+    /// 監視スレッドを開始（設定された間隔ごとにNASと検査機器の状態をチェック）
+    pub fn start_monitoring(
+        self,
+        app_handle: AppHandle,
+        settings_monitor: SettingsMonitor,
+        capacity_history: CapacityHistory,
+    ) {
+        tauri::async_runtime::spawn(async move {
             loop {
-                interval.tick().await;
+                // NASと検査機器の状態を並行して更新
+                let (nas_result, insp_result) = tokio::join!(
+                    self.update_nas_status(),
+                    self.update_insp_status()
+                );
 
-                // NAS状態を更新
-                if let Err(e) = self.update_nas_status().await {
+                if let Err(e) = nas_result {
                     log::error!("Failed to update NAS status: {}", e);
                 }
+                if let Err(e) = insp_result {
+                    log::error!("Failed to update inspection device status: {}", e);
+                }
 
                 // フロントエンドに更新を通知
-                let nas_configs = self.nas_configs.read().await;
-                if let Err(e) = app_handle.emit("nas-status-updated", nas_configs.clone()) {
+                let nas_configs = self.get_nas_configs().await;
+
+                // 容量の推移を記録（予測用）
+                capacity_history.record(&nas_configs).await;
+
+                if let Err(e) = app_handle.emit("nas-status-updated", nas_configs) {
                     log::error!("Failed to emit nas-status-updated event: {}", e);
                 }
 
+                let insp_configs = self.get_insp_configs().await;
+                if let Err(e) = app_handle.emit("insp-status-updated", insp_configs) {
+                    log::error!("Failed to emit insp-status-updated event: {}", e);
+                }
+
+                // 次の監視まで待機（間隔は設定から毎回取得）
+                let interval_secs = settings_monitor.get_settings().await.monitor_interval_secs.max(1);
+                sleep(Duration::from_secs(interval_secs)).await;
             }
         });
     }
 
     /// すべてのNASの状態を更新
+    /// 接続・容量チェックはブロッキング処理のため別スレッドで並行実行し、
+    /// 結果の反映時のみ短時間書き込みロックを取得する
+    /// 前回のチェックが制限時間を過ぎても終わっていないNASは、スレッドが溜まらないよう終わるまでチェックしない
     async fn update_nas_status(&self) -> Result<(), String> {
-        let mut configs = self.nas_configs.write().await;
-
-        for config in configs.iter_mut() {
-            // 接続チェック
-            config.is_connected = check_nas_connection(&config.nas_ip);
+        let targets: Vec<NasConfig> = self.get_nas_configs().await;
+
+        let probes: Vec<_> = targets
+            .into_iter()
+            .filter_map(|config| {
+                let Some(guard) = self.begin_probe(ProbeTarget::Nas, config.id) else {
+                    log::debug!("NAS probe still in progress, skipped (id: {})", config.id);
+                    return None;
+                };
+                Some((config.id, tokio::task::spawn_blocking(move || {
+                    let _guard = guard;
+                    probe_nas(&config)
+                })))
+            })
+            .collect();
+
+        // すべてのプローブは同時に開始しているので、共通の期限で待つ
+        let deadline = Instant::now() + PROBE_TIMEOUT;
+        let mut results = Vec::with_capacity(probes.len());
+        for (id, probe) in probes {
+            match timeout_at(deadline, probe).await {
+                Ok(Ok(result)) => results.push((id, result)),
+                Ok(Err(e)) => {
+                    log::error!("NAS probe task failed (id: {}): {}", id, e);
+                    results.push((id, NasProbeResult::disconnected()));
+                }
+                Err(_) => {
+                    log::warn!("NAS probe timed out (id: {})", id);
+                    results.push((id, NasProbeResult::disconnected()));
+                }
+            }
+        }
 
-            // 接続できている場合は容量情報を取得
-            if config.is_connected {
-                if let Ok(space_info) = get_drive_space_info(&config.drive) {
+        let mut configs = self.nas_configs.write().await;
+        for (id, result) in results {
+            // プローブ中に削除されたNASは無視
+            if let Some(config) = configs.iter_mut().find(|c| c.id == id) {
+                config.is_connected = result.is_connected;
+                if let Some(mapping_error) = &result.mapping_error {
+                    if config.mapping_error.as_ref() != Some(mapping_error) {
+                        log::error!("NAS {}: {}", config.name, mapping_error);
+                    }
+                }
+                config.mapping_error = result.mapping_error;
+                if let Some(connection_error) = &result.connection_error {
+                    if config.connection_error.as_ref() != Some(connection_error) {
+                        log::error!("NAS {}: {}", config.name, connection_error);
+                    }
+                }
+                config.connection_error = result.connection_error;
+                if let Some(space_info) = result.space_info {
                     config.total_space = space_info.total;
                     config.used_space = space_info.used;
                     config.free_space = space_info.free;
-                } else {
-                    log::warn!("Could not get space info for drive {}", config.drive);
+                } else if !result.is_connected {
+                    // 接続できていない場合は容量を0にリセット
+                    config.total_space = 0;
+                    config.used_space = 0;
+                    config.free_space = 0;
                 }
-            } else {
-                // 接続できていない場合は容量を0にリセット
-                config.total_space = 0;
-                config.used_space = 0;
-                config.free_space = 0;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// すべての検査機器の状態を更新
+    /// NASと同様に別スレッドで並行実行し、結果の反映時のみ書き込みロックを取得する
+    /// SFTP・FTPのコピー元パスはREMOTE_SOURCE_CHECK_INTERVALごと（切断から戻った時はすぐ）に確認し、それ以外は前回の結果を使う
+    async fn update_insp_status(&self) -> Result<(), String> {
+        let targets: Vec<InspConfig> = self.get_insp_configs().await;
+
+        let probes: Vec<_> = targets
+            .into_iter()
+            .filter_map(|config| {
+                let Some(guard) = self.begin_probe(ProbeTarget::Insp, config.id) else {
+                    log::debug!("Inspection device probe still in progress, skipped (id: {})", config.id);
+                    return None;
+                };
+                let is_remote = matches!(config.source, SourceAccessConfig::Sftp { .. } | SourceAccessConfig::Ftp { .. });
+                let check_paths = !is_remote
+                    || !config.is_connected
+                    || self
+                        .lock_probe_state()
+                        .source_checked
+                        .get(&config.id)
+                        .is_none_or(|checked| checked.elapsed() >= REMOTE_SOURCE_CHECK_INTERVAL);
+                let probe_state = self.probe_state.clone();
+                Some((config.id, tokio::task::spawn_blocking(move || {
+                    let _guard = guard;
+                    let result = probe_insp(&config, check_paths);
+                    if is_remote && check_paths && result.is_connected {
+                        probe_state.lock().unwrap_or_else(|e| e.into_inner()).source_checked.insert(config.id, Instant::now());
+                    }
+                    result
+                })))
+            })
+            .collect();
+
+        let deadline = Instant::now() + PROBE_TIMEOUT;
+        let mut results = Vec::with_capacity(probes.len());
+        for (id, probe) in probes {
+            match timeout_at(deadline, probe).await {
+                Ok(Ok(result)) => results.push((id, result)),
+                Ok(Err(e)) => {
+                    log::error!("Inspection device probe task failed (id: {}): {}", id, e);
+                    results.push((id, InspProbeResult::disconnected()));
+                }
+                Err(_) => {
+                    log::warn!("Inspection device probe timed out (id: {})", id);
+                    results.push((id, InspProbeResult::disconnected()));
+                }
+            }
+        }
+
+        let mut configs = self.insp_configs.write().await;
+        for (id, result) in results {
+            if let Some(config) = configs.iter_mut().find(|c| c.id == id) {
+                config.is_connected = result.is_connected;
+                if let Some(connection_error) = &result.connection_error {
+                    if config.connection_error.as_ref() != Some(connection_error) {
+                        log::error!("Inspection device {}: {}", config.name, connection_error);
+                    }
+                }
+                config.connection_error = result.connection_error;
+                // コピー元パスを確認しなかった場合は前回の結果のまま
+                let Some(unreadable_paths) = result.unreadable_paths else {
+                    continue;
+                };
+                if !unreadable_paths.is_empty() {
+                    log::warn!(
+                        "Inspection device {} has unreadable source paths: {:?}",
+                        config.name,
+                        unreadable_paths
+                    );
+                }
+                config.unreadable_paths = unreadable_paths;
             }
         }
 
@@ -95,8 +296,15 @@ impl AppMonitor {
                 insp_config.back_image_path=new_insp_info.back_image_path.clone();
                 insp_config.surface_result_path=new_insp_info.surface_result_path.clone();
                 insp_config.back_result_path=new_insp_info.back_result_path.clone();
+                insp_config.bandwidth_limit_mbps=new_insp_info.bandwidth_limit_mbps;
+                insp_config.file_filter=new_insp_info.file_filter.clone();
+                // 画面には認証情報を送らないため、送り返された設定に現在のパスワードを補う
+                insp_config.source=new_insp_info.source.clone().with_secrets_from(&insp_config.source);
+                insp_config.is_continuous=new_insp_info.is_continuous;
             }
         }
+        // 変更したコピー元パスを次の監視ですぐに確認する
+        self.lock_probe_state().source_checked.remove(&new_insp_info.id);
     }
 
     /// NAS設定を更新(編集)
@@ -107,6 +315,8 @@ impl AppMonitor {
                 nas_config.name=new_nas_info.name.clone();
                 nas_config.nas_ip=new_nas_info.nas_ip.clone();
                 nas_config.drive=new_nas_info.drive.clone();
+                // 画面には認証情報を送らないため、送り返された設定に現在のパスワード・シークレットキーを補う
+                nas_config.backend=new_nas_info.backend.clone().with_secrets_from(&nas_config.backend);
             }
         }
     }
@@ -121,6 +331,16 @@ impl AppMonitor {
         }
     }
 
+    ///検査機器の連続バックアップ設定を切り替え
+    pub async fn switch_insp_continuous_settings(&self,insp_id:u32){
+        let mut configs = self.insp_configs.write().await;
+        for insp_config in configs.iter_mut(){
+            if insp_config.id==insp_id{
+                insp_config.is_continuous=!insp_config.is_continuous;
+            }
+        }
+    }
+
     ///メモリ上に検査機器を追加
     pub async fn add_insp(&self,name:String,insp_ip:String,surface_image_path:String,back_image_path:String,surface_result_path:String,back_result_path:String)->u32{
         let mut configs = self.insp_configs.write().await;
@@ -138,7 +358,17 @@ impl AppMonitor {
             back_image_path, 
             surface_result_path, 
             back_result_path, 
-            is_backup:true
+            is_backup:true,
+            last_successful_backup:None,
+            category_backups:vec![],
+            is_backup_stale:false,
+            is_connected:false,
+            connection_error:None,
+            unreadable_paths:vec![],
+            source:Default::default(),
+            is_continuous:false,
+            bandwidth_limit_mbps:0,
+            file_filter:Default::default(),
         });
 
         new_id
@@ -162,7 +392,10 @@ impl AppMonitor {
             is_connected:false,
             total_space:0,
             used_space:0,
-            free_space:0
+            free_space:0,
+            mapping_error:None,
+            connection_error:None,
+            backend:Default::default(),
         });
 
         new_id
@@ -184,6 +417,10 @@ impl AppMonitor {
                 surface_result_path: config.surface_result_path.clone(),
                 back_result_path: config.back_result_path.clone(),
                 is_backup: config.is_backup,
+                source: config.source.clone(),
+                is_continuous: config.is_continuous,
+                bandwidth_limit_mbps: config.bandwidth_limit_mbps,
+                file_filter: config.file_filter.clone(),
             });
 
         // 要素を削除
@@ -192,6 +429,80 @@ impl AppMonitor {
         deleted_info
     }
 
+    /// バックアップ結果から検査機器・カテゴリごとの最終成功日時を更新
+    pub async fn record_backup_results(&self, device_results: &[DeviceBackupResult], end_time: &str) {
+        let mut configs = self.insp_configs.write().await;
+
+        for device_result in device_results {
+            let Some(insp_config) = configs.iter_mut().find(|c| c.name == device_result.device_name) else {
+                continue;
+            };
+
+            // カテゴリ単位: スキップされずエラーなしで完了したもの
+            for category_result in &device_result.categories {
+                if category_result.skipped_reason.is_some()
+                    || category_result.failed_files > 0
+                    || !category_result.errors.is_empty()
+                {
+                    continue;
+                }
+
+                match insp_config.category_backups.iter_mut().find(|c| c.category == category_result.category) {
+                    Some(status) => status.last_successful_backup = Some(end_time.to_string()),
+                    None => insp_config.category_backups.push(CategoryBackupStatus {
+                        category: category_result.category.clone(),
+                        last_successful_backup: Some(end_time.to_string()),
+                    }),
+                }
+            }
+
+            // 検査機器単位: 全カテゴリがエラーなしで完了した場合のみ
+            if device_result.success {
+                insp_config.last_successful_backup = Some(end_time.to_string());
+                insp_config.is_backup_stale = false;
+            }
+        }
+    }
+
+    /// 保存済みのバックアップ履歴から最終成功日時を復元（起動時に使用）
+    pub async fn restore_backup_results(&self, history_entries: &[BackupHistoryEntry]) {
+        for entry in history_entries {
+            if let Some(result) = &entry.result {
+                self.record_backup_results(&result.devices, &entry.end_time).await;
+            }
+        }
+    }
+
+    /// 最終成功から指定日数を超えた検査機器を判定し、新たに該当した検査機器を返す
+    /// stale_days が0の場合は判定しない
+    pub async fn update_backup_staleness(&self, stale_days: u32) -> Vec<InspConfig> {
+        let mut configs = self.insp_configs.write().await;
+        let now = Local::now().naive_local();
+        let mut newly_stale = Vec::new();
+
+        for insp_config in configs.iter_mut() {
+            let is_stale = if stale_days == 0 || !insp_config.is_backup {
+                false
+            } else {
+                match insp_config
+                    .last_successful_backup
+                    .as_deref()
+                    .and_then(|t| NaiveDateTime::parse_from_str(t, "%Y-%m-%d %H:%M:%S").ok())
+                {
+                    Some(last) => (now - last).num_days() >= stale_days as i64,
+                    None => true, // 一度も成功していない
+                }
+            };
+
+            if is_stale && !insp_config.is_backup_stale {
+                newly_stale.push(insp_config.clone());
+            }
+            insp_config.is_backup_stale = is_stale;
+        }
+
+        newly_stale
+    }
+
     ///メモリ上のNASを削除
     pub async fn delete_nas(&self,id:u32) -> Option<NasInfo> {
         let mut configs = self.nas_configs.write().await;
@@ -204,6 +515,7 @@ impl AppMonitor {
                 name: config.name.clone(),
                 nas_ip: config.nas_ip.clone(),
                 drive: config.drive.clone(),
+                backend: config.backend.clone(),
             });
 
         // 要素を削除
@@ -213,34 +525,129 @@ impl AppMonitor {
     }
 }
 
-/// NASへの接続をチェック（SMBポート445へのTCP接続を試行）
+/// NASへの接続をチェック（SMBポートへのTCP接続を試行）
+/// アドレスにポートが指定されていない場合は445を使用
 pub fn check_nas_connection(nas_ip: &str) -> bool {
-    let address = format!("{}:445", nas_ip);
+    check_tcp_connection(nas_ip, SMB_PORT, StdDuration::from_secs(1))
+}
 
-    match TcpStream::connect_timeout(
-        &address.parse().unwrap(),
-        StdDuration::from_secs(1)
-    ) {
-        Ok(_) => true,
-        Err(_) => false,
+/// 検査機器への接続をチェック
+/// アドレスにポートが指定されていない場合はdefault_port（SMBは445・SFTPは22・FTPは21）を使用
+pub fn check_device_connection(device_ip: &str, default_port: u16) -> bool {
+    check_tcp_connection(device_ip, default_port, StdDuration::from_secs(3))
+}
+
+/// NAS1台分のチェック結果
+struct NasProbeResult {
+    is_connected: bool,
+    space_info: Option<DriveSpaceInfo>,
+    mapping_error: Option<String>,
+    connection_error: Option<String>,
+}
+
+impl NasProbeResult {
+    fn disconnected() -> Self {
+        Self { is_connected: false, space_info: None, mapping_error: None, connection_error: None }
     }
 }
 
-/// 検査機器への接続をチェック
-/// ポート番号は検査機器の仕様に応じて変更してください
-fn check_device_connection(device_ip: &str) -> bool {
-    // 仮に445ポートでチェック（実際のポート番号に変更してください）
-    let address = format!("{}:445", device_ip);
-
-    match TcpStream::connect_timeout(
-        &address.parse().unwrap(),
-        StdDuration::from_secs(3)
-    ) {
-        Ok(_) => true,
-        Err(_) => false,
+/// NAS1台分の接続・容量チェック（ブロッキング処理）
+fn probe_nas(nas_config: &NasConfig) -> NasProbeResult {
+    // アドレスが不正な場合は接続を確認できないため、理由を画面に表示する
+    if let Err(e) = parse_host_address(&nas_config.nas_ip) {
+        return NasProbeResult { connection_error: Some(e), ..NasProbeResult::disconnected() };
+    }
+
+    if !check_nas_connection(&nas_config.nas_ip) {
+        return NasProbeResult::disconnected();
+    }
+
+    // ドライブが別のNASやローカルディスクを指している場合は容量情報も信用できないため取得しない
+    // （S3はドライブを使わないため確認不要）
+    if !matches!(nas_config.backend, StorageBackendConfig::S3 { .. }) {
+        if let Err(mapping_error) = verify_drive_mapping(&nas_config.drive, &nas_config.nas_ip) {
+            return NasProbeResult {
+                is_connected: true,
+                space_info: None,
+                mapping_error: Some(mapping_error),
+                connection_error: None,
+            };
+        }
+    }
+
+    // 接続できている場合は容量情報を取得
+    let space_info = match open_backend(nas_config).and_then(|backend| backend.free_space()) {
+        Ok(space_info) => Some(space_info),
+        Err(e) => {
+            log::warn!("Could not get space info for NAS {}: {}", nas_config.name, e);
+            None
+        }
+    };
+
+    NasProbeResult { is_connected: true, space_info, mapping_error: None, connection_error: None }
+}
+
+/// 検査機器1台分のチェック結果
+struct InspProbeResult {
+    is_connected: bool,
+    unreadable_paths: Option<Vec<String>>,  // コピー元パスを確認しなかった場合はNone
+    connection_error: Option<String>,
+}
+
+impl InspProbeResult {
+    fn disconnected() -> Self {
+        Self { is_connected: false, unreadable_paths: Some(vec![]), connection_error: None }
+    }
+}
+
+/// 検査機器1台分の接続・コピー元パスチェック（ブロッキング処理）
+/// check_paths: コピー元パスが読み込めるかも確認するか
+fn probe_insp(insp_config: &InspConfig, check_paths: bool) -> InspProbeResult {
+    // ローカルパスの場合は接続チェック不要
+    if let Some(port) = source_port(&insp_config.source) {
+        // アドレスが不正な場合は接続を確認できないため、理由を画面に表示する
+        if let Err(e) = parse_host_address(&insp_config.insp_ip) {
+            return InspProbeResult { connection_error: Some(e), ..InspProbeResult::disconnected() };
+        }
+        if !check_device_connection(&insp_config.insp_ip, port) {
+            return InspProbeResult::disconnected();
+        }
+    }
+
+    // 接続できている場合はコピー元パスが読み込めるか確認
+    InspProbeResult {
+        is_connected: true,
+        unreadable_paths: check_paths.then(|| check_source_paths(insp_config)),
+        connection_error: None,
     }
 }
 
+/// 検査機器のコピー元パス（未設定のものは除く）のうち読み込めないものを返す
+fn check_source_paths(insp_config: &InspConfig) -> Vec<String> {
+    let paths = [
+        &insp_config.surface_image_path,
+        &insp_config.back_image_path,
+        &insp_config.surface_result_path,
+        &insp_config.back_result_path,
+    ]
+    .into_iter()
+    .filter(|path| !path.is_empty());
+
+    // SFTP・FTPのログインに失敗した場合はすべてのパスを読み込めないものとする
+    let source = match open_source(insp_config) {
+        Ok(source) => source,
+        Err(e) => {
+            log::warn!("Inspection device {}: {}", insp_config.name, e);
+            return paths.cloned().collect();
+        }
+    };
+
+    paths
+        .filter(|path| source.list(path).is_err())
+        .cloned()
+        .collect()
+}
+
 /// ドライブの容量情報
 pub struct DriveSpaceInfo {
     pub total: u64,
@@ -248,45 +655,42 @@ pub struct DriveSpaceInfo {
     pub free: u64,
 }
 
-/// ドライブの容量情報を取得
-pub fn get_drive_space_info(drive_letter: &str) -> Result<DriveSpaceInfo, String> {
-    // ドライブレターを正規化（例: "P:" -> "P:\\"）
-    let drive_path = if drive_letter.ends_with(":\\") {
-        drive_letter.to_string()
-    } else if drive_letter.ends_with(":") {
-        format!("{}\\", drive_letter)
-    } else {
-        format!("{}:\\", drive_letter)
-    };
+/// コピー先ルート（ドライブレター・UNCパス・マウントポイント・ローカルディレクトリ）の容量情報を取得
+pub fn get_drive_space_info(drive: &str) -> Result<DriveSpaceInfo, String> {
+    // ドライブレターは "P:\\" の形式に正規化される
+    let root_path = dest_root_path(drive);
 
-    // Windows用の実装
+    // Windows用の実装（GetDiskFreeSpaceExWは任意のディレクトリを受け付ける）
     #[cfg(windows)]
     {
-        get_drive_space_info_windows(&drive_path)
+        get_drive_space_info_windows(&root_path.to_string_lossy())
     }
 
     // Windows以外の場合はsysinfoを使用
+    // ルートを含むマウントポイントのうち最も深いものの容量を返す
     #[cfg(not(windows))]
     {
-        let disks = Disks::new_with_refreshed_list();
-
-        for disk in &disks {
-            let mount_point = disk.mount_point().to_string_lossy();
-
-            if mount_point.to_uppercase() == drive_path.to_uppercase() {
-                let total = disk.total_space();
-                let free = disk.available_space();
-                let used = total.saturating_sub(free);
+        let root_path = root_path
+            .canonicalize()
+            .map_err(|e| format!("Path {} not found: {}", root_path.display(), e))?;
 
-                return Ok(DriveSpaceInfo {
-                    total,
-                    used,
-                    free,
-                });
-            }
-        }
+        let disks = Disks::new_with_refreshed_list();
 
-        Err(format!("Drive {} not found", drive_path))
+        let disk = disks
+            .iter()
+            .filter(|disk| root_path.starts_with(disk.mount_point()))
+            .max_by_key(|disk| disk.mount_point().as_os_str().len())
+            .ok_or(format!("Mount point for {} not found", root_path.display()))?;
+
+        let total = disk.total_space();
+        let free = disk.available_space();
+        let used = total.saturating_sub(free);
+
+        Ok(DriveSpaceInfo {
+            total,
+            used,
+            free,
+        })
     }
 }
 
@@ -315,7 +719,7 @@ fn get_drive_space_info_windows(drive_path: &str) -> Result<DriveSpaceInfo, Stri
         if result.is_ok() {
             let used = total_bytes.saturating_sub(total_free_bytes);
 
-            log::info!(
+            log::debug!(
                 "Drive {} - Total: {} GB, Used: {} GB, Free: {} GB",
                 drive_path,
                 total_bytes / (1024 * 1024 * 1024),
@@ -1,13 +1,26 @@
-use std::fs;
-use std::fs::DirEntry;
-use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use std::time::Instant;
-use tauri::{AppHandle, Emitter};
+use chrono::Local;
+use tauri::AppHandle;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
 use tokio::time::{sleep, Duration};
-use crate::types::{InspConfig, NasConfig, SettingsConfig, BackupResult, BackupProgress};
-use crate::app_monitor::get_drive_space_info;
-use std::collections::HashMap;
-use walkdir::WalkDir;
+use crate::types::{InspConfig, NasConfig, SettingsConfig, BackupResult, DeviceBackupResult, CategoryBackupResult, ConflictPolicy, FileAction, FileAuditEntry, FileFilter, RootFileHandling};
+use crate::progress_tracker::ProgressTracker;
+use crate::copy_engine::{copy_stream, CopyOptions};
+use crate::nas_pool::{NasPool, NasLease};
+use crate::nas_inventory::NasInventory;
+use crate::conflict_policy::{ConflictCheck, Resolution};
+use crate::file_audit::{FileAudit, AUDIT_TIMESTAMP_FORMAT};
+use crate::lot_versions::{new_version_name, preserve_file, prune_versions, revert_file};
+use crate::root_files::{find_changed_root_files, root_files_folder_path};
+use crate::file_filter::is_selected;
+use crate::dest_template::{DestContext, DestTemplate};
+use crate::source_access::{open_source, join_source_path, join_relative_source_path, FileAttributes, LotScan, SourceAccess, SourceFile};
+use crate::storage_backend::{open_backend, join_storage_path, StorageBackend};
+use crate::throttle::{RateLimiter, Throttle};
+use std::collections::{HashMap, HashSet};
+use std::time::SystemTime;
 
 const MAX_RETRIES: u32 = 3;
 const RETRY_DELAY_SECS: u64 = 5;
@@ -36,6 +49,109 @@ impl From<BackupError> for String {
     }
 }
 
+/// 検査機器1台分のバックアップ対象カテゴリ
+struct BackupTarget {
+    category: &'static str,         // 表面画像・裏面画像など
+    source_relative_path: String,   // 検査機器側のパス（空の場合はバックアップしない）
+    nas_base_path: String,          // NAS側の保存先パス
+    conflict_policy: ConflictPolicy,    // コピー先に同名ファイルがある場合の処理
+    file_filter: FileFilter,            // カテゴリのファイルの絞り込み（検査機器の絞り込みと合わせて適用）
+}
+
+/// 検査機器1台分のバックアップで各ワーカーが共有する情報
+struct DeviceContext {
+    device_name: String,
+    source: Arc<dyn SourceAccess>,
+    throttle: Throttle,
+    copy_options: CopyOptions,
+    settings: Arc<SettingsConfig>,
+    nas_pool: Arc<NasPool>,
+    nas_inventory: NasInventory,
+    file_audit: FileAudit,
+    progress: Arc<Mutex<ProgressTracker>>,
+    disk_full: Arc<Mutex<Option<String>>>,     // すべてのNASで容量不足になった場合の最初のエラー（全検査機器で共有）
+}
+
+impl DeviceContext {
+    /// すべてのNASで容量不足になっているか（なっている場合は未処理のロット・検査機器をコピーしない）
+    fn disk_full_error(&self) -> Option<String> {
+        self.disk_full.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// コピーしなかったファイルを進捗に失敗として記録（計画に含めたファイルを残さず、進捗が100%に届くようにする）
+    fn record_not_copied(&self, category: &str, entry_path: &str, files: &[SourceFile]) {
+        let mut progress = self.progress.lock().unwrap_or_else(|e| e.into_inner());
+        for file in files {
+            let display_path = self.source.display_path(&join_relative_source_path(entry_path, &file.relative_path));
+            progress.record_file(&self.device_name, category, &display_path, file.size, None);
+        }
+    }
+}
+
+/// コピーするロット1つ分の情報
+#[derive(Clone)]
+struct LotJob {
+    category_index: usize,          // 検査機器のバックアップ対象カテゴリ内の位置
+    category: &'static str,
+    entry_path: String,             // コピー元のロットフォルダのパス
+    entry_name: String,             // ロットフォルダ名
+    dest_path: String,              // コピー先の保存先フォルダ（保存先のテンプレートの {lot} より前のパス）
+    scan: Arc<LotScan>,             // 計画時に走査したロット配下のファイル一覧（コピーでも再利用する）
+    conflict_policy: ConflictPolicy,
+    root_files: bool,               // カテゴリ直下のファイル（ロットフォルダではない）のコピー
+}
+
+/// ロット1つ分のコピーの集計
+#[derive(Debug, Clone, Copy, Default)]
+struct LotStats {
+    total_files: u64,
+    copied_files: u64,
+    skipped_files: u64,             // コピー先に同一のファイルがあるためスキップ
+    failed_files: u64,
+    conflict_files: u64,            // コピー先に異なる内容の同名ファイルがあるためコピーしなかった（failed_filesに含む）
+    total_size: u64,                // コピーしたバイト数
+}
+
+/// NASに既にあるロット（差分の確認用）
+#[derive(Default)]
+struct ExistingLot {
+    file_counts: Vec<u32>,      // そのロットがあるNASごとのファイル数（複数のNASにある場合は複数）
+    folder_path: String,        // ロットがある保存先フォルダ（再コピーする場合は同じフォルダにコピーする）
+}
+
+/// 差分の確認でコピー対象になったロット
+struct FoundLot {
+    entry_path: String,         // コピー元のロットフォルダのパス
+    entry_name: String,         // ロットフォルダ名
+    scan: Arc<LotScan>,         // 走査結果
+    dest_path: String,          // コピー先の保存先フォルダ（保存先のテンプレートから決める）
+}
+
+/// 差分の確認でコピー対象になったロット（カテゴリ1つ分）
+#[derive(Default)]
+struct FoundLots {
+    lots: Vec<FoundLot>,
+    lots_found: u64,                            // コピー元にあったロットフォルダ数
+    lots_skipped: u64,                          // NASに既にあるためスキップしたロット数
+    root_files: Vec<SourceFile>,                // コピーするカテゴリ直下のファイル
+    root_files_found: u64,                      // コピー元のカテゴリ直下にあったファイル数
+    root_files_skipped: u64,                    // NASにサイズ・更新日時が一致するファイルがあるためスキップしたファイル数
+    errors: Vec<String>,                        // 走査できなかったロットのエラー
+}
+
+/// ロット1つ分のコピー結果
+struct LotOutcome {
+    category_index: usize,
+    lot_name: String,
+    root_files: bool,
+    nas_name: Option<String>,       // NASを割り当てられなかった場合はNone
+    result: Result<LotStats, BackupError>,
+}
+
+/// 連続バックアップで対象とするロット
+/// 検査機器ID → (コピー元パス, ロットフォルダ名) の集合
+pub type LotScope = HashMap<u32, HashSet<(String, String)>>;
+
 /// バックアップ実行を担当する構造体
 pub struct BackupExecutor;
 
@@ -46,7 +162,9 @@ impl BackupExecutor {
         nas_configs: Vec<NasConfig>,
         settings: SettingsConfig,
         app_handle: AppHandle,
-        last_backup_nas_id:Option<u32>
+        last_backup_nas_id:Option<u32>,
+        lot_scope: Option<LotScope>,
+        nas_inventory: NasInventory,
     ) -> Result<BackupResult, String> {
         let start_time = Instant::now();
         let mut total_files = 0u64;
@@ -54,27 +172,97 @@ impl BackupExecutor {
         let mut failed_files = 0u64;
         let mut total_size = 0u64;
         let mut errors = Vec::new();
+        let mut device_results = Vec::new();
 
         log::info!("Starting backup process...");
 
-        // バックアップ対象の検査機器のみをフィルタ
-        let active_insp_configs: Vec<&InspConfig> = insp_configs
+        let settings = Arc::new(settings);
+
+        // バックアップ対象の検査機器のみをフィルタ（ロット指定時は該当する検査機器のみ）
+        let backup_insp_configs: Vec<&InspConfig> = insp_configs
             .iter()
             .filter(|insp| insp.is_backup)
+            .filter(|insp| lot_scope.as_ref().is_none_or(|scope| scope.contains_key(&insp.id)))
             .collect();
 
+        // 接続できない検査機器は事前にスキップし、理由を結果に残す
+        let (mut active_insp_configs, unreachable_insp_configs): (Vec<&InspConfig>, Vec<&InspConfig>) =
+            backup_insp_configs.into_iter().partition(|insp| insp.is_connected);
+
+        for insp_config in &unreachable_insp_configs {
+            let reason = format!("検査機器 {} ({}) に接続できないためスキップしました", insp_config.name, insp_config.insp_ip);
+            log::warn!("{}", reason);
+            errors.push(reason.clone());
+            device_results.push(Self::summarize_device_result(
+                &insp_config.name,
+                Vec::new(),
+                Vec::new(),
+                0,
+                Some(reason),
+            ));
+        }
+
+        // コピー元を開く（SFTP・FTPはここでログインする）。開けない検査機器はスキップ
+        let mut opened_sources = Self::open_sources(&active_insp_configs).await;
+        let mut insp_sources: HashMap<u32, Arc<dyn SourceAccess>> = HashMap::new();
+        active_insp_configs.retain(|insp| match opened_sources.remove(&insp.id).unwrap_or_else(|| Err("コピー元を開けません".to_string())) {
+            Ok(source) => {
+                insp_sources.insert(insp.id, source);
+                true
+            }
+            Err(e) => {
+                let reason = format!("検査機器 {} のコピー元を開けないためスキップしました: {}", insp.name, e);
+                log::warn!("{}", reason);
+                errors.push(reason.clone());
+                device_results.push(Self::summarize_device_result(
+                    &insp.name,
+                    Vec::new(),
+                    Vec::new(),
+                    0,
+                    Some(reason),
+                ));
+                false
+            }
+        });
+
         // 使用可能で接続されているNASのみをフィルタ
         let mut active_nas_configs: Vec<&NasConfig> = nas_configs
             .iter()
             .filter(|nas| nas.is_use && nas.is_connected)
+            .filter(|nas| match &nas.mapping_error {
+                Some(mapping_error) => {
+                    log::error!("NAS {} は使用しません: {}", nas.name, mapping_error);
+                    false
+                }
+                None => true,
+            })
             .collect();
 
+        // コピー先のバックエンドを開く（開けないNASは使用しない）
+        let mut opened_backends = Self::open_backends(&active_nas_configs).await;
+        let mut nas_backends: HashMap<u32, Box<dyn StorageBackend>> = HashMap::new();
+        active_nas_configs.retain(|nas| match opened_backends.remove(&nas.id).unwrap_or_else(|| Err("コピー先を開けません".to_string())) {
+            Ok(backend) => {
+                nas_backends.insert(nas.id, backend);
+                true
+            }
+            Err(e) => {
+                log::error!("NAS {} のコピー先を開けません: {}", nas.name, e);
+                errors.push(format!("NAS {} のコピー先を開けません: {}", nas.name, e));
+                false
+            }
+        });
+
         if active_nas_configs.is_empty() {
             log::error!("利用可能なNASがありません");
             return Err("利用可能なNASがありません".to_string());
         }
 
         if active_insp_configs.is_empty() {
+            if !device_results.is_empty() {
+                log::error!("接続できるバックアップ対象の検査機器がありません");
+                return Err(format!("接続できるバックアップ対象の検査機器がありません: {}", errors.join(", ")));
+            }
             log::error!("バックアップ対象の検査機器がありません");
             return Err("バックアップ対象の検査機器がありません".to_string());
         }
@@ -113,194 +301,146 @@ impl BackupExecutor {
         log::info!("Active inspection devices: {}", active_insp_configs.len());
         log::info!("Active NAS devices: {}", active_nas_configs.len());
 
-        // 現在使用中のNASインデックス (容量不足時に切り替え)
-        let mut nas_index = 0usize;
-
-        // 各検査機器からバックアップを実行
-        for insp_config in active_insp_configs {
-            log::info!("Processing device: {}", insp_config.name);
-
-            // すべてのNASから既存データを収集（重複チェック用）
-            let nas_surface_image_map = Self::collect_all_nas_folder_data(
-                &active_nas_configs,
-                &settings.surface_image_path,
-                &insp_config.name,
-            );
-
-            let nas_back_image_map = Self::collect_all_nas_folder_data(
-                &active_nas_configs,
-                &settings.back_image_path,
-                &insp_config.name,
-            );
+        let active_insp_configs: Vec<InspConfig> = active_insp_configs.into_iter().cloned().collect();
+
+        // NASの割り当てと空き容量の予約を全ワーカーで共有
+        let nas_pool = Arc::new(NasPool::new(
+            active_nas_configs.into_iter().cloned().collect(),
+            nas_backends,
+            settings.max_writers_per_nas,
+        ));
+
+        // 検査機器単位で並列に処理（同時に処理する台数を制限）
+        let device_slots = Arc::new(Semaphore::new(settings.max_parallel_devices.max(1) as usize));
+
+        // 実行全体の進捗を集計（コピー前に全検査機器の差分を計画する）
+        // ロットは計画時に1回だけ走査し、その結果を差分の判定・進捗・コピーで使う
+        // NAS・コピー元の一覧取得はブロッキングするため検査機器ごとに専用スレッドで実行
+        let mut plan_tasks = JoinSet::new();
+        for (order, insp_config) in active_insp_configs.iter().enumerate() {
+            let insp_config = insp_config.clone();
+            let source = insp_sources[&insp_config.id].clone();
+            let nas_pool = nas_pool.clone();
+            let nas_inventory = nas_inventory.clone();
+            let settings = settings.clone();
+            let lot_filter = lot_scope.as_ref().and_then(|scope| scope.get(&insp_config.id)).cloned();
+            let device_slots = device_slots.clone();
+            plan_tasks.spawn(async move {
+                let _slot = device_slots.acquire_owned().await;
+                let plan = tokio::task::spawn_blocking(move || {
+                    Self::plan_device(&insp_config, source.as_ref(), &nas_pool, &nas_inventory, &settings, lot_filter.as_ref())
+                })
+                .await;
+                (order, plan)
+            });
+        }
 
-            let nas_surface_result_file_map = Self::collect_all_nas_folder_data(
-                &active_nas_configs,
-                &settings.surface_result_file_path,
-                &insp_config.name,
-            );
+        let mut device_plans: Vec<Option<Vec<Result<FoundLots, String>>>> =
+            active_insp_configs.iter().map(|_| None).collect();
+        while let Some(joined) = plan_tasks.join_next().await {
+            let (order, plan) = joined.map_err(|e| format!("バックアップの計画処理が異常終了しました: {}", e))?;
+            let plan = plan.map_err(|e| format!("バックアップの計画処理が異常終了しました: {}", e))?;
+            device_plans[order] = Some(plan);
+        }
 
-            let nas_back_result_file_map = Self::collect_all_nas_folder_data(
-                &active_nas_configs,
-                &settings.back_result_file_path,
-                &insp_config.name,
-            );
-
-            // バックアップ処理（NAS容量チェック付き）
-            loop {
-                // NAS容量チェック
-                if nas_index >= active_nas_configs.len() {
-                    log::error!("すべてのNASで容量不足です。バックアップを中断しました。(検査機器: {})",insp_config.name);
-                    return Err(
-                        format!("すべてのNASで容量不足です。バックアップを中断しました。(検査機器: {})",insp_config.name)
-                    );
+        // 計画時に更新したNAS上のロット一覧を保存（途中で中断しても次回の数え直しを減らす）
+        let save_inventory = nas_inventory.clone();
+        let _ = tokio::task::spawn_blocking(move || save_inventory.save()).await;
+
+        let mut progress = ProgressTracker::new(app_handle);
+        for (insp_config, plan) in active_insp_configs.iter().zip(device_plans.iter()) {
+            let backup_targets = Self::build_backup_targets(insp_config, &settings);
+            for (target, found) in backup_targets.iter().zip(plan.iter().flatten()) {
+                if let Ok(found) = found {
+                    let files = found.lots.iter().map(|lot| lot.scan.file_count() as u64).sum::<u64>()
+                        + found.root_files.len() as u64;
+                    let size = found.lots.iter().map(|lot| lot.scan.total_size()).sum::<u64>()
+                        + found.root_files.iter().map(|file| file.size).sum::<u64>();
+                    progress.add_planned(&insp_config.name, target.category, files, size);
                 }
+            }
+        }
 
-                let nas_config = active_nas_configs[nas_index];
-                log::info!("  Backing up to NAS: {} (Index: {})", nas_config.name, nas_index);
+        progress.start();
+        let progress = Arc::new(Mutex::new(progress));
 
-                // NASの空き容量をチェック
-                if nas_config.free_space < settings.required_free_space {
-                    log::warn!(
-                        "  NAS {} の空き容量不足: {} < {} (required). 次のNASに切り替えます...",
-                        nas_config.name,
-                        nas_config.free_space,
-                        settings.required_free_space
-                    );
-                    nas_index += 1;
-                    continue; // 次のNASへ
-                }
+        // 全検査機器で共有する転送速度の上限（生産時間内・外で切り替え）
+        let global_limiter = Arc::new(RateLimiter::new(0));
+        let copy_options = CopyOptions::from_settings(&settings);
+        // ファイル単位のコピー記録（監査用）
+        let file_audit = FileAudit::open();
+        // すべてのNASで容量不足になった場合は、コピー済みの結果を残して未処理のロット・検査機器のみ中断する
+        let disk_full = Arc::new(Mutex::new(None));
 
-                let mut disk_full_occurred = false;
+        // 検査機器単位で並列にバックアップ
+        let mut device_tasks = JoinSet::new();
 
-                // 表面画像のバックアップ（差分のみ）
-                if !disk_full_occurred && !insp_config.surface_image_path.is_empty() {
-                    match Self::backup_folder_with_diff(
-                        &insp_config.insp_ip,
-                        &insp_config.surface_image_path,
-                        nas_config,
-                        &settings.surface_image_path,
-                        &insp_config.name,
-                        "表面画像",
-                        &nas_surface_image_map,
-                        settings.required_free_space,
-                        &app_handle,
-                    ).await {
-                        Ok(stats) => {
-                            total_files += stats.0;
-                            copied_files += stats.1;
-                            failed_files += stats.2;
-                            total_size += stats.3;
-                        }
-                        Err(BackupError::DiskFull(msg)) => {
-                            log::warn!("  表面画像のバックアップ中にNAS容量不足を検知: {}", msg);
-                            disk_full_occurred = true;
-                        }
-                        Err(e) => {
-                            errors.push(format!("{} - 表面画像: {}", insp_config.name, e));
-                        }
-                    }
-                }
+        for (order, (insp_config, plan)) in active_insp_configs.iter().zip(device_plans).enumerate() {
+            let Some(source) = insp_sources.remove(&insp_config.id) else {
+                continue;
+            };
 
-                // 裏面画像のバックアップ（差分のみ）
-                if !disk_full_occurred && !insp_config.back_image_path.is_empty() {
-                    match Self::backup_folder_with_diff(
-                        &insp_config.insp_ip,
-                        &insp_config.back_image_path,
-                        nas_config,
-                        &settings.back_image_path,
-                        &insp_config.name,
-                        "裏面画像",
-                        &nas_back_image_map,
-                        settings.required_free_space,
-                        &app_handle,
-                    ).await {
-                        Ok(stats) => {
-                            total_files += stats.0;
-                            copied_files += stats.1;
-                            failed_files += stats.2;
-                            total_size += stats.3;
-                        }
-                        Err(BackupError::DiskFull(msg)) => {
-                            log::warn!("  裏面画像のバックアップ中にNAS容量不足を検知: {}", msg);
-                            disk_full_occurred = true;
-                        }
-                        Err(e) => {
-                            errors.push(format!("{} - 裏面画像: {}", insp_config.name, e));
-                        }
-                    }
-                }
+            let device = Arc::new(DeviceContext {
+                device_name: insp_config.name.clone(),
+                source,
+                throttle: Throttle::new(&settings, insp_config, global_limiter.clone()),
+                copy_options,
+                settings: settings.clone(),
+                nas_pool: nas_pool.clone(),
+                nas_inventory: nas_inventory.clone(),
+                file_audit: file_audit.clone(),
+                progress: progress.clone(),
+                disk_full: disk_full.clone(),
+            });
+            let backup_targets = Self::build_backup_targets(insp_config, &settings);
+            let plan = plan.unwrap_or_default();
+            let device_slots = device_slots.clone();
+
+            device_tasks.spawn(async move {
+                let _slot = device_slots.acquire_owned().await;
+                (order, Self::backup_device(device, backup_targets, plan).await)
+            });
+        }
 
-                // 表面結果ファイルのバックアップ（差分のみ）
-                if !disk_full_occurred && !insp_config.surface_result_path.is_empty() {
-                    match Self::backup_folder_with_diff(
-                        &insp_config.insp_ip,
-                        &insp_config.surface_result_path,
-                        nas_config,
-                        &settings.surface_result_file_path,
-                        &insp_config.name,
-                        "表面結果ファイル",
-                        &nas_surface_result_file_map,
-                        settings.required_free_space,
-                        &app_handle,
-                    ).await {
-                        Ok(stats) => {
-                            total_files += stats.0;
-                            copied_files += stats.1;
-                            failed_files += stats.2;
-                            total_size += stats.3;
-                        }
-                        Err(BackupError::DiskFull(msg)) => {
-                            log::warn!("  表面結果ファイルのバックアップ中にNAS容量不足を検知: {}", msg);
-                            disk_full_occurred = true;
-                        }
-                        Err(e) => {
-                            errors.push(format!("{} - 結果ファイル: {}", insp_config.name, e));
-                        }
-                    }
+        // すべての検査機器の完了を待って集計（結果は検査機器の順番に並べる）
+        let mut finished_results = Vec::new();
+        while let Some(joined) = device_tasks.join_next().await {
+            match joined {
+                Ok((order, device_result)) => finished_results.push((order, device_result)),
+                Err(e) => {
+                    log::error!("検査機器のバックアップ処理が異常終了しました: {}", e);
+                    errors.push(format!("検査機器のバックアップ処理が異常終了しました: {}", e));
                 }
+            }
+        }
 
-                // 裏面結果ファイルのバックアップ（差分のみ）
-                if !disk_full_occurred && !insp_config.back_result_path.is_empty() {
-                    match Self::backup_folder_with_diff(
-                        &insp_config.insp_ip,
-                        &insp_config.back_result_path,
-                        nas_config,
-                        &settings.back_result_file_path,
-                        &insp_config.name,
-                        "裏面結果ファイル",
-                        &nas_back_result_file_map,
-                        settings.required_free_space,
-                        &app_handle,
-                    ).await {
-                        Ok(stats) => {
-                            total_files += stats.0;
-                            copied_files += stats.1;
-                            failed_files += stats.2;
-                            total_size += stats.3;
-                        }
-                        Err(BackupError::DiskFull(msg)) => {
-                            log::warn!("  裏面結果ファイルのバックアップ中にNAS容量不足を検知: {}", msg);
-                            disk_full_occurred = true;
-                        }
-                        Err(e) => {
-                            errors.push(format!("{} - 結果ファイル: {}", insp_config.name, e));
-                        }
-                    }
-                }
+        // コピーしたロットを反映したNAS上のロット一覧・ファイル単位のコピー記録を保存
+        let _ = tokio::task::spawn_blocking(move || {
+            nas_inventory.save();
+            file_audit.flush();
+        })
+        .await;
 
-                // DiskFullエラーが発生した場合は次のNASに切り替え
-                if disk_full_occurred {
-                    log::info!("  NAS {} が満杯になったため、次のNASに切り替えます", nas_config.name);
-                    nas_index += 1;
-                    continue; // 次のNASへ
-                }
+        // すべてのNASで容量不足になった場合は中断したことを結果に残す
+        if let Some(e) = disk_full.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            let message = format!("すべてのNASで容量不足です。未処理のロット・検査機器のバックアップを中断しました: {}", e);
+            log::error!("{}", message);
+            errors.push(message);
+        }
 
-                // このNASへのバックアップ成功、次の検査機器へ
-                log::info!("  NAS {} へのバックアップ完了", nas_config.name);
-                break;
-            }
+        finished_results.sort_by_key(|(order, _)| *order);
+        for (_, device_result) in finished_results {
+            // 検査機器単位の結果を全体に集計
+            total_files += device_result.total_files;
+            copied_files += device_result.copied_files;
+            failed_files += device_result.failed_files;
+            total_size += device_result.total_size_bytes;
+            errors.extend(device_result.errors.iter().cloned());
+            device_results.push(device_result);
         }
 
+        progress.lock().unwrap_or_else(|e| e.into_inner()).finish();
+
         let duration = start_time.elapsed().as_secs();
         let success = failed_files == 0 && errors.is_empty();
 
@@ -314,13 +454,158 @@ impl BackupExecutor {
             total_size_bytes: total_size,
             duration_secs: duration,
             errors,
+            devices: device_results,
         })
     }
 
-    /// コピー先のパスを構築
-    fn build_dest_path(drive: &str, base_path: &str, device_name: &str) -> String {
-        let drive_clean = drive.trim_end_matches(":\\").trim_end_matches(":");
-        format!("{}:\\{}\\{}", drive_clean, base_path.trim_start_matches("\\"), device_name)
+    /// 検査機器のバックアップ対象カテゴリ一覧を作成
+    fn build_backup_targets(insp_config: &InspConfig, settings: &SettingsConfig) -> Vec<BackupTarget> {
+        vec![
+            BackupTarget {
+                category: "表面画像",
+                source_relative_path: insp_config.surface_image_path.clone(),
+                nas_base_path: settings.surface_image_path.clone(),
+                conflict_policy: settings.surface_image_conflict_policy,
+                file_filter: settings.surface_image_file_filter.clone(),
+            },
+            BackupTarget {
+                category: "裏面画像",
+                source_relative_path: insp_config.back_image_path.clone(),
+                nas_base_path: settings.back_image_path.clone(),
+                conflict_policy: settings.back_image_conflict_policy,
+                file_filter: settings.back_image_file_filter.clone(),
+            },
+            BackupTarget {
+                category: "表面結果ファイル",
+                source_relative_path: insp_config.surface_result_path.clone(),
+                nas_base_path: settings.surface_result_file_path.clone(),
+                conflict_policy: settings.surface_result_conflict_policy,
+                file_filter: settings.surface_result_file_filter.clone(),
+            },
+            BackupTarget {
+                category: "裏面結果ファイル",
+                source_relative_path: insp_config.back_result_path.clone(),
+                nas_base_path: settings.back_result_file_path.clone(),
+                conflict_policy: settings.back_result_conflict_policy,
+                file_filter: settings.back_result_file_filter.clone(),
+            },
+        ]
+    }
+
+    /// 検査機器ごと・カテゴリごとのNAS上の保存先フォルダ（保存先のテンプレートの {lot} より前のパス）の一覧
+    /// テンプレートの日付・ロット名から決まる階層は、NAS上のフォルダを一覧して探す
+    /// base: 探す起点（コピー先ルートの場合は空、世代フォルダの場合はそのルート）、戻り値は起点からのパス
+    pub fn nas_dest_paths(
+        insp_configs: &[InspConfig],
+        settings: &SettingsConfig,
+        backend: &dyn StorageBackend,
+        base: &str,
+    ) -> Result<Vec<String>, String> {
+        let mut dest_paths: Vec<String> = Vec::new();
+        for dest_path in Self::nas_dest_path_groups(insp_configs, settings, backend, base)?.into_iter().flatten() {
+            if !dest_paths.contains(&dest_path) {
+                dest_paths.push(dest_path);
+            }
+        }
+        Ok(dest_paths)
+    }
+
+    /// NAS上の保存先フォルダを検査機器・カテゴリごとにまとめた一覧（同じまとまりのフォルダには同じロットが入る）
+    /// 検査機器・カテゴリの順番はNASによらず同じため、まとまりの位置でNAS間を突き合わせられる
+    pub fn nas_dest_path_groups(
+        insp_configs: &[InspConfig],
+        settings: &SettingsConfig,
+        backend: &dyn StorageBackend,
+        base: &str,
+    ) -> Result<Vec<Vec<String>>, String> {
+        let template = DestTemplate::from_settings(settings)?;
+        let mut groups = Vec::new();
+        for insp_config in insp_configs {
+            for target in Self::build_backup_targets(insp_config, settings) {
+                if target.source_relative_path.is_empty() {
+                    continue;
+                }
+                let context = Self::dest_context(&target, &insp_config.name);
+                groups.push(template.existing_folders(&context, backend, base));
+            }
+        }
+        Ok(groups)
+    }
+
+    /// カテゴリ直下のファイルの保存先フォルダの親（_root_files/NAS内保存パス）の一覧
+    /// カテゴリ直下のファイルの世代は、検査機器名をロット名として扱う
+    pub fn root_files_folder_paths(insp_configs: &[InspConfig], settings: &SettingsConfig) -> Vec<String> {
+        let mut folder_paths: Vec<String> = Vec::new();
+        for insp_config in insp_configs {
+            for target in Self::build_backup_targets(insp_config, settings) {
+                let folder_path = root_files_folder_path(&target.nas_base_path);
+                if !target.source_relative_path.is_empty() && !folder_paths.contains(&folder_path) {
+                    folder_paths.push(folder_path);
+                }
+            }
+        }
+        folder_paths
+    }
+
+    /// 空のカテゴリ単位結果を作成
+    fn new_category_result(category: &str, source_path: &str) -> CategoryBackupResult {
+        CategoryBackupResult {
+            category: category.to_string(),
+            source_path: source_path.to_string(),
+            skipped_reason: None,
+            lots_found: 0,
+            lots_skipped: 0,
+            lots_copied: 0,
+            lots_failed: 0,
+            root_files_found: 0,
+            root_files_skipped: 0,
+            total_files: 0,
+            copied_files: 0,
+            skipped_files: 0,
+            failed_files: 0,
+            total_size_bytes: 0,
+            duration_secs: 0,
+            nas_used: None,
+            errors: Vec::new(),
+        }
+    }
+
+    /// カテゴリ単位の結果を検査機器単位に集計
+    fn summarize_device_result(
+        device_name: &str,
+        categories: Vec<CategoryBackupResult>,
+        nas_used: Vec<String>,
+        duration_secs: u64,
+        skipped_reason: Option<String>,
+    ) -> DeviceBackupResult {
+        let errors: Vec<String> = categories
+            .iter()
+            .flat_map(|c| c.errors.iter().cloned())
+            .collect();
+        let failed_files = categories.iter().map(|c| c.failed_files).sum();
+
+        DeviceBackupResult {
+            device_name: device_name.to_string(),
+            success: skipped_reason.is_none() && failed_files == 0 && errors.is_empty(),
+            skipped_reason,
+            total_files: categories.iter().map(|c| c.total_files).sum(),
+            copied_files: categories.iter().map(|c| c.copied_files).sum(),
+            failed_files,
+            total_size_bytes: categories.iter().map(|c| c.total_size_bytes).sum(),
+            errors,
+            duration_secs,
+            nas_used,
+            categories,
+        }
+    }
+
+    /// 保存先のテンプレートを展開する検査機器・カテゴリ
+    fn dest_context<'a>(target: &'a BackupTarget, device_name: &'a str) -> DestContext<'a> {
+        DestContext {
+            root: &target.nas_base_path,
+            category: target.category,
+            device: device_name,
+        }
     }
 
     ///ベクトルを任意の値から開始する
@@ -339,185 +624,448 @@ impl BackupExecutor {
         Some(new_vec)
     }
 
+    /// 検査機器1台分のNAS上の既存データを収集し、カテゴリごとにコピーするロットを決める
+    /// 戻り値: カテゴリごとのコピー対象のロット（コピー元パス未設定のカテゴリは空）
+    fn plan_device(
+        insp_config: &InspConfig,
+        source: &dyn SourceAccess,
+        nas_pool: &NasPool,
+        nas_inventory: &NasInventory,
+        settings: &SettingsConfig,
+        lot_filter: Option<&HashSet<(String, String)>>,
+    ) -> Vec<Result<FoundLots, String>> {
+        let template = DestTemplate::from_settings(settings);
+        let backup_time = Local::now();
+
+        Self::build_backup_targets(insp_config, settings)
+            .iter()
+            .map(|target| {
+                if target.source_relative_path.is_empty() {
+                    return Ok(FoundLots::default());
+                }
+                let template = template.as_ref().map_err(|e| e.clone())?;
+                let context = Self::dest_context(target, &insp_config.name);
+
+                // すべてのNASから既存データを収集（重複チェック用）
+                let existing_lots = Self::collect_all_nas_folder_data(
+                    nas_inventory,
+                    nas_pool.backends(),
+                    template,
+                    &context,
+                );
+
+                // ロットの保存先フォルダ（NASに既にあるロットはそのフォルダ、無いロットはテンプレートから決める）
+                let lot_dest_path = |lot_name: &str, lot_modified: Option<SystemTime>| match existing_lots.get(lot_name) {
+                    Some(existing) => existing.folder_path.clone(),
+                    None => template.folder_path(&context, lot_name, backup_time, lot_modified),
+                };
+
+                // カテゴリ直下のファイルはロット指定のない（連続バックアップ以外の）バックアップでのみ対象にする
+                let collect_root_files = settings.root_file_handling == RootFileHandling::Backup && lot_filter.is_none();
+                let mut found = Self::find_lots_to_copy(
+                    source,
+                    &target.source_relative_path,
+                    &existing_lots,
+                    lot_filter,
+                    &[&insp_config.file_filter, &target.file_filter],
+                    collect_root_files,
+                    &lot_dest_path,
+                )?;
+
+                // カテゴリ直下のファイルはファイル単位でサイズ・更新日時を比べ、変わったファイルのみコピーする
+                if !found.root_files.is_empty() {
+                    let (changed, skipped) = find_changed_root_files(
+                        std::mem::take(&mut found.root_files),
+                        nas_pool.backends(),
+                        &root_files_folder_path(&target.nas_base_path),
+                        &insp_config.name,
+                    );
+                    found.root_files = changed;
+                    found.root_files_skipped = skipped;
+                }
+                Ok(found)
+            })
+            .collect()
+    }
+
     /// すべてのNASから既存フォルダデータを収集する（重複チェック用）
+    /// 保存先のテンプレートに一致するすべての保存先フォルダを探し、ロット名で突き合わせる
+    /// NASごとのロット一覧のキャッシュを使い、前回から変わったロットのみファイル数を数え直す
+    /// 戻り値: ロット名 → NASにあるロット
     fn collect_all_nas_folder_data(
-        nas_configs: &[&NasConfig],
-        base_path: &str,
-        device_name: &str,
-    ) -> HashMap<String, u32> {
-        let mut all_file_map = HashMap::new();
-
-        for nas_config in nas_configs {
-            let dest_path = Self::build_dest_path(
-                &nas_config.drive,
-                base_path,
-                device_name,
-            );
-            Self::get_all_file_data(dest_path, &mut all_file_map);
+        nas_inventory: &NasInventory,
+        nas_backends: &HashMap<u32, Box<dyn StorageBackend>>,
+        template: &DestTemplate,
+        context: &DestContext,
+    ) -> HashMap<String, ExistingLot> {
+        let mut all_file_map: HashMap<String, ExistingLot> = HashMap::new();
+
+        for (nas_id, backend) in nas_backends {
+            for dest_path in template.existing_folders(context, backend.as_ref(), "") {
+                for (lot_name, count) in nas_inventory.refresh(*nas_id, backend.as_ref(), &dest_path) {
+                    let existing = all_file_map.entry(lot_name).or_insert_with(|| ExistingLot {
+                        file_counts: Vec::new(),
+                        folder_path: dest_path.clone(),
+                    });
+                    existing.file_counts.push(count);
+                }
+            }
         }
 
         all_file_map
     }
 
-    ///フォルダ内の全フォルダに対してpath_nameとフォルダ内のファイル数のhashmapを作成する
-    fn get_all_file_data(folder_path:String,all_file_map: &mut HashMap<String,u32>){
-        //dest_path内の各ロット番号フォルダ一覧に対してフォルダ内のファイル数をhashmapに保存する 
-        let entry_list=match fs::read_dir(folder_path){
-            Ok(v)=>v,
-            _=>return
-        };
+    /// 検査機器のコピー元をブロッキング用スレッドで並行して開く（SFTP・FTPの接続・ログインでランタイムを止めない）
+    /// 戻り値: 検査機器ID → 開いたコピー元
+    async fn open_sources(insp_configs: &[&InspConfig]) -> HashMap<u32, Result<Arc<dyn SourceAccess>, String>> {
+        let tasks: Vec<_> = insp_configs
+            .iter()
+            .map(|insp| {
+                let insp = (*insp).clone();
+                (insp.id, tokio::task::spawn_blocking(move || open_source(&insp).map(Arc::<dyn SourceAccess>::from)))
+            })
+            .collect();
 
-        for entry in entry_list {
-            let entry=match entry{
-                Ok(v)=>v,
-                _=>continue
-            };
-            let metadata = match entry.metadata(){
-                Ok(v)=>v,
-                _=>continue
-            };
+        let mut opened = HashMap::new();
+        for (id, task) in tasks {
+            let source = task
+                .await
+                .unwrap_or_else(|e| Err(format!("コピー元を開く処理が異常終了しました: {}", e)));
+            opened.insert(id, source);
+        }
+        opened
+    }
 
-            //NASの各ロット名フォルダ内のファイル数を再帰的に取得する 
-            if metadata.is_dir() {
-                let mut count=0;
-                for entry in WalkDir::new(entry.path()).into_iter().filter_map(|e| e.ok()) {
-                    if entry.metadata().map(|m| m.is_file()).unwrap_or(false) {
-                        count += 1;
-                    }
-                }
+    /// NASのコピー先をブロッキング用スレッドで並行して開く（ネットワークドライブの接続でランタイムを止めない）
+    /// 戻り値: NAS ID → 開いたコピー先
+    async fn open_backends(nas_configs: &[&NasConfig]) -> HashMap<u32, Result<Box<dyn StorageBackend>, String>> {
+        let tasks: Vec<_> = nas_configs
+            .iter()
+            .map(|nas| {
+                let nas = (*nas).clone();
+                (nas.id, tokio::task::spawn_blocking(move || open_backend(&nas)))
+            })
+            .collect();
 
-                if let Some(p)=entry.path().file_name(){
-                    all_file_map.entry(p.to_string_lossy().to_string()).or_insert(count);
-                }
+        let mut opened = HashMap::new();
+        for (id, task) in tasks {
+            let backend = task
+                .await
+                .unwrap_or_else(|e| Err(format!("コピー先を開く処理が異常終了しました: {}", e)));
+            opened.insert(id, backend);
+        }
+        opened
+    }
 
+    /// 検査機器1台分のバックアップを実行（ロット単位で並列にコピー）
+    /// すべてのNASで容量不足になった場合はエラー
+    async fn backup_device(
+        device: Arc<DeviceContext>,
+        backup_targets: Vec<BackupTarget>,
+        plan: Vec<Result<FoundLots, String>>,
+    ) -> DeviceBackupResult {
+        // 他の検査機器のバックアップ中にすべてのNASで容量不足になった場合はスキップ
+        if let Some(e) = device.disk_full_error() {
+            let reason = format!("すべてのNASで容量不足のためスキップしました: {}", e);
+            log::warn!("{} ({})", reason, device.device_name);
+            for (target, found) in backup_targets.iter().zip(plan.iter()) {
+                if let Ok(found) = found {
+                    for lot in &found.lots {
+                        device.record_not_copied(target.category, &lot.entry_path, &lot.scan.files);
+                    }
+                    device.record_not_copied(target.category, &target.source_relative_path, &found.root_files);
+                }
             }
+            return Self::summarize_device_result(&device.device_name, Vec::new(), Vec::new(), 0, Some(reason));
         }
 
-    }
+        log::info!("Processing device: {}", device.device_name);
+        let device_start_time = Instant::now();
 
-    /// 差分バックアップを実行（既にNASにあるフォルダはスキップ）
-    async fn backup_folder_with_diff(
-        insp_ip: &str,
-        source_relative_path: &str,
-        nas_config: &NasConfig,
-        nas_base_path: &str,
-        device_name: &str,
-        category: &str,
-        existing_folders: &HashMap<String, u32>,
-        required_free_space: u64,
-        app_handle: &AppHandle,
-    ) -> Result<(u64, u64, u64, u64), BackupError> {
-        // 検査機器側のソースパスを構築
-        // source_relative_pathの先頭の/や\を取り除く
-        let clean_relative_path = source_relative_path
-            .trim_start_matches('/')
-            .trim_start_matches('\\');
-
-        let mut source_path = PathBuf::new();
-        source_path.push(format!("\\\\{}", insp_ip)); // UNCパス形式
-        source_path.push(clean_relative_path);
-
-        // NAS側のコピー先パスを取得
-        let dest_path = Self::build_dest_path(&nas_config.drive, nas_base_path, device_name);
-
-        log::debug!("insp_ip(コピー元IP): {}", insp_ip);
-        log::debug!("コピー元パス: {}", source_path.display());
-        log::debug!("コピー先パス: {}", dest_path);
+        // カテゴリ単位の集計（コピー元パス未設定のカテゴリはスキップ理由を記録）
+        let mut category_results: Vec<CategoryBackupResult> = backup_targets
+            .iter()
+            .map(|target| {
+                let mut category_result = Self::new_category_result(
+                    target.category,
+                    &device.source.display_path(&target.source_relative_path),
+                );
+                if target.source_relative_path.is_empty() {
+                    category_result.skipped_reason = Some("コピー元パスが設定されていません".to_string());
+                }
+                category_result
+            })
+            .collect();
 
-        let mut total_files = 0u64;
-        let mut copied_files = 0u64;
-        let mut failed_files = 0u64;
-        let mut total_size = 0u64;
+        // 同じ検査機器から同時にコピーするロット数を制限
+        let lot_slots = Arc::new(Semaphore::new(device.settings.max_parallel_lots_per_device.max(1) as usize));
+        let mut lot_tasks = JoinSet::new();
 
-        // ソースフォルダ内のエントリを読み込み
-        let entries = match fs::read_dir(&source_path) {
-            Ok(entries) => entries,
-            Err(e) => {
-                return Err(BackupError::General(format!(
-                    "コピー元パス読み込みエラー {}: {}",
-                    source_path.display(),
-                    e
-                )));
+        // カテゴリ（表面画像・裏面画像・表面結果ファイル・裏面結果ファイル）ごとに計画したロットを投入
+        for (category_index, (target, found)) in backup_targets.iter().zip(plan).enumerate() {
+            if target.source_relative_path.is_empty() {
+                continue;
             }
-        };
 
-        // フォルダ単位でコピーするかチェックしながら進める
-        for entry_result in entries {
-            let entry = match entry_result {
-                Ok(v) => v,
-                Err(_) => continue,
+            let category_result = &mut category_results[category_index];
+            let (lots, root_files) = match found {
+                Ok(found) => {
+                    category_result.lots_found += found.lots_found;
+                    category_result.lots_skipped += found.lots_skipped;
+                    category_result.lots_failed += found.errors.len() as u64;
+                    category_result.root_files_found += found.root_files_found;
+                    category_result.root_files_skipped += found.root_files_skipped;
+                    category_result.errors.extend(
+                        found.errors.iter().map(|e| format!("{} - {}: {}", device.device_name, target.category, e)),
+                    );
+                    (found.lots, found.root_files)
+                }
+                Err(e) => {
+                    category_result.errors.push(format!("{} - {}: {}", device.device_name, target.category, e));
+                    continue;
+                }
             };
 
-            let metadata = match entry.metadata() {
-                Ok(v) => v,
-                Err(_) => continue,
-            };
+            let mut jobs: Vec<LotJob> = lots
+                .into_iter()
+                .map(|lot| LotJob {
+                    category_index,
+                    category: target.category,
+                    entry_path: lot.entry_path,
+                    entry_name: lot.entry_name,
+                    dest_path: lot.dest_path,
+                    scan: lot.scan,
+                    conflict_policy: target.conflict_policy,
+                    root_files: false,
+                })
+                .collect();
+
+            // カテゴリ直下のファイルは _root_files/NAS内保存パス/検査機器名 にまとめてコピー（ロットフォルダと名前が重ならない）
+            if !root_files.is_empty() {
+                jobs.push(LotJob {
+                    category_index,
+                    category: target.category,
+                    entry_path: target.source_relative_path.clone(),
+                    entry_name: device.device_name.clone(),
+                    dest_path: root_files_folder_path(&target.nas_base_path),
+                    scan: Arc::new(LotScan {
+                        files: root_files,
+                        dirs: Vec::new(),
+                    }),
+                    conflict_policy: target.conflict_policy,
+                    root_files: true,
+                });
+            }
 
-            // ファイルはスキップ（フォルダのみ処理）
-            if metadata.is_file() {
-                continue;
+            let mut not_started = 0u64;
+            for lot in jobs {
+                // すべてのNASで容量不足になった後のロットはコピーしない
+                if device.disk_full_error().is_some() {
+                    not_started += u64::from(!lot.root_files);
+                    device.record_not_copied(lot.category, &lot.entry_path, &lot.scan.files);
+                    continue;
+                }
+                let Ok(permit) = lot_slots.clone().acquire_owned().await else {
+                    break;
+                };
+                if device.disk_full_error().is_some() {
+                    not_started += u64::from(!lot.root_files);
+                    device.record_not_copied(lot.category, &lot.entry_path, &lot.scan.files);
+                    continue;
+                }
+                let device = device.clone();
+                lot_tasks.spawn(async move {
+                    let _permit = permit;
+                    Self::backup_lot(device, lot).await
+                });
             }
 
-            // 既にNASにあるデータかチェック（ファイル数が一致すればスキップ）
-            if !Self::should_copy_folder(&entry, existing_folders) {
-                log::debug!(
-                    "    スキップ: {} (既にNASに存在)",
-                    entry.file_name().to_string_lossy()
-                );
-                continue;
+            if not_started > 0 {
+                let category_result = &mut category_results[category_index];
+                category_result.lots_failed += not_started;
+                category_result.errors.push(format!(
+                    "{} - {}: すべてのNASで容量不足のため {} ロットをコピーしていません",
+                    device.device_name, target.category, not_started
+                ));
             }
+        }
 
-            // entry単位でコピーする
-            match Self::copy_with_retry(&entry, &dest_path, device_name, category, nas_config, required_free_space, app_handle).await
-            {
+        // ロット単位の結果をカテゴリ単位に集計
+        let mut nas_used: Vec<String> = Vec::new();
+        while let Some(joined) = lot_tasks.join_next().await {
+            let outcome = match joined {
+                Ok(outcome) => outcome,
+                Err(e) => {
+                    log::error!("ロットのコピー処理が異常終了しました ({}): {}", device.device_name, e);
+                    continue;
+                }
+            };
+
+            let category_result = &mut category_results[outcome.category_index];
+            // カテゴリの所要時間は最後のロットが完了するまでの時間とする
+            category_result.duration_secs = device_start_time.elapsed().as_secs();
+
+            if let Some(nas_name) = outcome.nas_name {
+                if !nas_used.contains(&nas_name) {
+                    nas_used.push(nas_name.clone());
+                }
+                category_result.nas_used = Some(nas_name);
+            }
+
+            match outcome.result {
                 Ok(stats) => {
-                    total_files += stats.0;
-                    copied_files += stats.1;
-                    failed_files += stats.2;
-                    total_size += stats.3;
+                    category_result.total_files += stats.total_files;
+                    category_result.copied_files += stats.copied_files;
+                    category_result.skipped_files += stats.skipped_files;
+                    category_result.failed_files += stats.failed_files;
+                    category_result.total_size_bytes += stats.total_size;
+                    // カテゴリ直下のファイルはロット数に含めない
+                    if !outcome.root_files {
+                        if stats.failed_files == 0 {
+                            category_result.lots_copied += 1;
+                        } else {
+                            category_result.lots_failed += 1;
+                        }
+                    }
                 }
                 Err(e) => {
-                    // DiskFullエラーの場合はすぐに上位に伝播
-                    if matches!(e, BackupError::DiskFull(_)) {
-                        return Err(e);
+                    category_result.failed_files += 1;
+                    if !outcome.root_files {
+                        category_result.lots_failed += 1;
                     }
-                    failed_files += 1;
-                    log::error!(
-                        "フォルダコピー失敗: {} - {}",
-                        entry.file_name().to_string_lossy(),
+                    category_result.errors.push(format!(
+                        "{} - {} - {}: {}",
+                        device.device_name,
+                        category_result.category,
+                        outcome.lot_name,
                         e
-                    );
+                    ));
+                    log::error!("フォルダコピー失敗: {} - {}", outcome.lot_name, e);
                 }
             }
         }
 
-        Ok((total_files, copied_files, failed_files, total_size))
+        // カテゴリ単位の結果を検査機器単位に集計
+        Self::summarize_device_result(
+            &device.device_name,
+            category_results,
+            nas_used,
+            device_start_time.elapsed().as_secs(),
+            None,
+        )
     }
 
-    /// フォルダをコピーすべきかチェック
-    /// 戻り値: true = コピーする, false = スキップする
-    fn should_copy_folder(entry: &DirEntry, nas_data_hashmap: &HashMap<String, u32>) -> bool {
-        //検査機器側のコピー元ロット番号名フォルダ
-        let folder_name = entry.file_name().to_string_lossy().to_string();
+    /// 差分を確認してコピーするロットを列挙（既にNASにあるフォルダはスキップ）
+    /// 各ロットはここで1回だけ走査し、走査結果をコピーまで持ち回る
+    /// 絞り込みの対象外のファイルは走査結果から除き、コピーもファイル数の比較もしない
+    /// collect_root_files: カテゴリ直下のファイルも集めるか（集めない場合は読み飛ばす）
+    /// lot_dest_path: (ロット名, ロットフォルダの更新日時) → コピー先の保存先フォルダ
+    fn find_lots_to_copy(
+        source: &dyn SourceAccess,
+        source_relative_path: &str,
+        existing_lots: &HashMap<String, ExistingLot>,
+        lot_filter: Option<&HashSet<(String, String)>>,
+        file_filters: &[&FileFilter],
+        collect_root_files: bool,
+        lot_dest_path: &dyn Fn(&str, Option<SystemTime>) -> String,
+    ) -> Result<FoundLots, String> {
+        log::debug!("コピー元パス: {}", source.display_path(source_relative_path));
+
+        // ソースフォルダ内のエントリを読み込み
+        let entries = source.list(source_relative_path).map_err(|e| {
+            format!("コピー元パス読み込みエラー {}: {}", source.display_path(source_relative_path), e)
+        })?;
+
+        let mut found = FoundLots::default();
+        for entry in entries {
+            // カテゴリ直下のファイル（差分の確認は呼び出し元でNASと比べて行う）
+            if !entry.is_dir {
+                let file = SourceFile {
+                    relative_path: entry.name,
+                    size: entry.size,
+                    attributes: entry.attributes,
+                };
+                if collect_root_files && is_selected(file_filters, &file) {
+                    found.root_files_found += 1;
+                    found.root_files.push(file);
+                }
+                continue;
+            }
+
+            // 対象外のロットはスキップ
+            if !Self::is_lot_in_scope(lot_filter, source_relative_path, &entry.name) {
+                continue;
+            }
+
+            found.lots_found += 1;
+
+            let entry_path = join_source_path(source_relative_path, &entry.name);
+            let mut scan = match source.scan(&entry_path) {
+                Ok(scan) => scan,
+                Err(e) => {
+                    log::error!("ロットフォルダを読み込めません {}: {}", source.display_path(&entry_path), e);
+                    found.errors.push(format!("{}: {}", entry.name, e));
+                    continue;
+                }
+            };
+
+            // NAS側は番号を付けて残したファイルを数えないため、コピー元も同じ数え方で比べる
+            let unfiltered_file_count = scan.canonical_file_count();
+            scan.files.retain(|file| is_selected(file_filters, file));
+
+            // 既にNASにあるデータかチェック（ファイル数が一致すればスキップ）
+            if !Self::should_copy_folder(&entry.name, scan.canonical_file_count(), unfiltered_file_count, existing_lots) {
+                log::debug!("    スキップ: {} (既にNASに存在)", entry.name);
+                found.lots_skipped += 1;
+                continue;
+            }
 
-        // entry内のファイル数を取得
-        let file_count = WalkDir::new(entry.path())
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .filter(|e| e.metadata().map(|m| m.is_file()).unwrap_or(false))
-            .count() as u32;
+            found.lots.push(FoundLot {
+                dest_path: lot_dest_path(&entry.name, entry.attributes.modified),
+                entry_path,
+                entry_name: entry.name,
+                scan: Arc::new(scan),
+            });
+        }
+
+        Ok(found)
+    }
 
+    /// ロットがバックアップ対象か（ロット指定がない場合はすべて対象）
+    fn is_lot_in_scope(lot_filter: Option<&HashSet<(String, String)>>, source_relative_path: &str, lot_name: &str) -> bool {
+        lot_filter.is_none_or(|lots| {
+            lots.contains(&(source_relative_path.to_string(), lot_name.to_string()))
+        })
+    }
+
+    /// フォルダをコピーすべきかチェック
+    /// 複数のNASに同じロットがある場合は、いずれかのファイル数が一致すればバックアップ済みとする
+    /// file_count: 絞り込み後のファイル数
+    /// unfiltered_file_count: 絞り込み前のファイル数（絞り込みを設定する前にすべてのファイルをコピーしたロットもバックアップ済みとする）
+    /// 戻り値: true = コピーする, false = スキップする
+    fn should_copy_folder(
+        folder_name: &str,
+        file_count: u32,
+        unfiltered_file_count: u32,
+        nas_data_hashmap: &HashMap<String, ExistingLot>,
+    ) -> bool {
         // NASに既に存在するかチェック
-        match nas_data_hashmap.get(&folder_name) {
-            Some(&nas_file_count) => {
-                // ファイル数が一致する場合はスキップ（既にバックアップ済み）
-                if nas_file_count == file_count {
+        match nas_data_hashmap.get(folder_name).map(|existing| &existing.file_counts) {
+            Some(nas_file_counts) => {
+                // ファイル数が一致するNASがある場合はスキップ（既にバックアップ済み）
+                if nas_file_counts.contains(&file_count) || nas_file_counts.contains(&unfiltered_file_count) {
+                    if nas_file_counts.len() > 1 {
+                        log::warn!(
+                            "    複数のNASに重複: {} (NAS: {:?}, 検査機器: {})",
+                            folder_name, nas_file_counts, file_count
+                        );
+                    }
                     false // スキップ
                 } else {
                     // ファイル数が違う場合はコピー（更新が必要）
                     log::info!(
-                        "    ファイル数不一致: {} (NAS: {}, 検査機器: {})",
-                        folder_name, nas_file_count, file_count
+                        "    ファイル数不一致: {} (NAS: {:?}, 検査機器: {})",
+                        folder_name, nas_file_counts, file_count
                     );
                     true // コピーする
                 }
@@ -528,39 +1076,89 @@ impl BackupExecutor {
             }
         }
     }
-    
-    /// リトライ付きでディレクトリをコピー
-    async fn copy_with_retry(
-        entry:&DirEntry,
-        dest: &str,
-        device_name: &str,
-        category: &str,
-        nas_config: &NasConfig,
-        required_free_space: u64,
-        app_handle: &AppHandle,
-    ) -> Result<(u64, u64, u64, u64), BackupError> {
-        // NAS容量チェック（コピー前にリアルタイムで確認）
-        let current_free = get_drive_space_info(&nas_config.drive)
-            .map(|info| info.free)
-            .unwrap_or(nas_config.free_space); // 取得失敗時はキャッシュ値で代替
-        if current_free < required_free_space {
-            let error_msg = format!(
-                "NAS {} の空き容量不足: {} < {} (required)",
-                nas_config.name,
-                current_free,
-                required_free_space
-            );
-            log::warn!("{}", error_msg);
-            return Err(BackupError::DiskFull(error_msg));
+
+    /// コピー先NASを割り当ててロットをコピー
+    async fn backup_lot(device: Arc<DeviceContext>, lot: LotJob) -> LotOutcome {
+        // ロットのサイズ分の空き容量を予約（他のワーカーと同じNASに書き込んでも容量を超えないようにする）
+        let lease = match device.nas_pool.acquire(lot.scan.total_size(), device.settings.required_free_space).await {
+            Ok(lease) => Arc::new(lease),
+            Err(e) => {
+                // すべてのNASで容量不足の場合は以降のロット・検査機器のコピーを中断する
+                // どのNASにも収まらないロットはそのロットのみコピーしない
+                if let BackupError::DiskFull(message) = &e {
+                    device.disk_full.lock().unwrap_or_else(|e| e.into_inner()).get_or_insert_with(|| message.clone());
+                }
+                device.record_not_copied(lot.category, &lot.entry_path, &lot.scan.files);
+                return LotOutcome {
+                    category_index: lot.category_index,
+                    lot_name: lot.entry_name,
+                    root_files: lot.root_files,
+                    nas_name: None,
+                    result: Err(e),
+                };
+            }
+        };
+
+        let nas_name = lease.nas_config().name.clone();
+        log::info!("  Backing up {} - {} to NAS: {}", device.device_name, lot.entry_name, nas_name);
+
+        let result = Self::copy_with_retry(&device, &lease, &lot).await;
+
+        // NAS上のロット一覧のキャッシュを更新（失敗したロットは次回数え直す）
+        // 競合のみのロットは競合をファイルごとの記録に残して完了とする（競合を解消するまで毎回コピーし直さない）
+        // カテゴリ直下のファイルはロット一覧に含めない
+        let nas_id = lease.nas_config().id;
+        match &result {
+            _ if lot.root_files => {}
+            Ok(stats) if stats.failed_files == stats.conflict_files => {
+                // コピー後のフォルダの更新日時を取得するためブロッキング用スレッドで記録
+                let (record_device, record_lease, record_lot) = (device.clone(), lease.clone(), lot.clone());
+                let _ = tokio::task::spawn_blocking(move || {
+                    record_device.nas_inventory.record_lot(
+                        nas_id,
+                        record_lease.backend(),
+                        &record_lot.dest_path,
+                        &record_lot.entry_name,
+                        record_lot.scan.canonical_file_count(),
+                    )
+                })
+                .await;
+            }
+            _ => device.nas_inventory.invalidate_lot(nas_id, &lot.dest_path, &lot.entry_name),
         }
 
+        LotOutcome {
+            category_index: lot.category_index,
+            lot_name: lot.entry_name,
+            root_files: lot.root_files,
+            nas_name: Some(nas_name),
+            result,
+        }
+    }
+
+    /// リトライ付きでディレクトリをコピー
+    async fn copy_with_retry(
+        device: &Arc<DeviceContext>,
+        lease: &Arc<NasLease>,
+        lot: &LotJob,
+    ) -> Result<LotStats, BackupError> {
         let mut last_error = String::new();
 
         for attempt in 1..=MAX_RETRIES {
-            match Self::copy_directory(entry, dest,device_name, category, app_handle).await {
+            // ファイルコピーはブロッキングするため専用スレッドで実行
+            let copy_device = device.clone();
+            let copy_lease = lease.clone();
+            let copy_lot = lot.clone();
+            let copied = tokio::task::spawn_blocking(move || {
+                Self::copy_directory(&copy_device, copy_lease.backend(), &copy_lot)
+            })
+            .await
+            .unwrap_or_else(|e| Err(format!("コピー処理が異常終了しました: {}", e)));
+
+            match copied {
                 Ok(result) => {
                     if attempt > 1 {
-                        log::info!("  リトライ成功 (試行 {}/{}): {} - {}", attempt, MAX_RETRIES, device_name, category);
+                        log::info!("  リトライ成功 (試行 {}/{}): {} - {}", attempt, MAX_RETRIES, device.device_name, lot.category);
                     }
                     return Ok(result);
                 }
@@ -568,12 +1166,12 @@ impl BackupExecutor {
                     last_error = e.clone();
                     if attempt < MAX_RETRIES {
                         log::warn!("  コピー失敗 (試行 {}/{}): {} - {} - エラー: {}",
-                            attempt, MAX_RETRIES, device_name, category, e);
+                            attempt, MAX_RETRIES, device.device_name, lot.category, e);
                         log::info!("  {}秒後にリトライします...", RETRY_DELAY_SECS);
                         sleep(Duration::from_secs(RETRY_DELAY_SECS)).await;
                     } else {
                         log::error!("  コピー失敗 (最終試行): {} - {} - エラー: {}",
-                            device_name, category, e);
+                            device.device_name, lot.category, e);
                     }
                 }
             }
@@ -582,133 +1180,239 @@ impl BackupExecutor {
         Err(BackupError::General(format!("{}回のリトライ後も失敗: {}", MAX_RETRIES, last_error)))
     }
 
-    /// ディレクトリを再帰的にコピー
-    /// 戻り値: (total_files, copied_files, failed_files, total_size)
-    async fn copy_directory(
-        entry:&DirEntry,
-        dest: &str,
-        device_name: &str,
-        category: &str,
-        app_handle: &AppHandle,
-    ) -> Result<(u64, u64, u64, u64), String> {
-        let mut dest_path = PathBuf::new();     //NAS側のパス
-        dest_path.push(dest);
-        dest_path.push(entry.file_name().to_string_lossy().to_string());
-
-        if !entry.path().exists() {
-            return Err(format!("検査機器側のコピー元フォルダが存在しません: {}", entry.path().to_string_lossy().to_string()));
+    /// ロットフォルダをコピー（計画時に走査したファイル一覧を使い、コピー元を再度走査しない）
+    /// コピー先に同名のファイルがある場合はカテゴリの設定に従って上書き・スキップなどを決め、ファイルごとの処理を記録する
+    fn copy_directory(
+        device: &DeviceContext,
+        backend: &dyn StorageBackend,
+        lot: &LotJob,
+    ) -> Result<LotStats, String> {
+        let dest_path = join_storage_path(&lot.dest_path, &lot.entry_name);     //NAS側のパス
+        let source = device.source.as_ref();
+
+        // 生産時間内・外の切り替えを反映
+        device.throttle.refresh();
+
+        // コピー先ディレクトリを作成（ロット番号名フォルダの中のフォルダもNASに作成）
+        backend.create_dir_all(&dest_path)?;
+        for dir in &lot.scan.dirs {
+            backend.create_dir_all(&join_storage_path(&dest_path, dir))?;
         }
 
-        // コピー先ディレクトリを作成
-        fs::create_dir_all(&dest_path)
-            .map_err(|e| format!("ディレクトリ作成エラー {}: {}", dest_path.display(), e))?;
+        // コピー先に既にあるファイル（同名ファイルの処理を決めるため）
+        let existing_files: HashMap<String, u64> = backend.list_files(&dest_path)?.into_iter().collect();
+        let conflict_check = ConflictCheck {
+            policy: lot.conflict_policy,
+            identical_check: device.settings.identical_check,
+            source,
+            throttle: &device.throttle,
+            backend,
+            dest_path: &dest_path,
+            existing_files: &existing_files,
+        };
 
-        // フォルダ内の総ファイル数を事前にカウント
-        let total_file_count = WalkDir::new(entry.path())
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .filter(|e| e.metadata().map(|m| m.is_file()).unwrap_or(false))
-            .count() as u64;
+        // 世代管理が有効な場合は、内容が変わるファイルを世代フォルダに移してからコピーする
+        // 世代名は最初にファイルを移す時に決める（移すファイルが無いロットでは世代を作らない）
+        let mut version: Option<String> = None;
+        let mut preserved_files = 0u64;
 
-        let mut copied_files = 0u64;
-        let mut failed_files = 0u64;
-        let mut total_size = 0u64;
+        let mut stats = LotStats {
+            total_files: lot.scan.files.len() as u64,
+            ..LotStats::default()
+        };
 
-        // ディレクトリ内のファイルをコピー
-        Self::copy_files_recursive(
-            entry.path().as_path(),
-            dest_path.as_path(),
-            device_name,
-            category,
-            app_handle,
-            total_file_count,
-            &mut copied_files,
-            &mut failed_files,
-            &mut total_size,
-        )?;
-
-        Ok((total_file_count, copied_files, failed_files, total_size))
-    }
+        for file in &lot.scan.files {
+            let source_path = join_relative_source_path(&lot.entry_path, &file.relative_path);
+            let display_path = source.display_path(&source_path);
+            let resolution = conflict_check.resolve(&source_path, file);
+            let file_dest_path = match &resolution {
+                Ok(Resolution::KeepBoth(suffixed_path)) => join_storage_path(&dest_path, suffixed_path),
+                _ => join_storage_path(&dest_path, &file.relative_path),
+            };
+            let copy = || {
+                Self::copy_file(
+                    source,
+                    &source_path,
+                    file,
+                    backend,
+                    &file_dest_path,
+                    &device.throttle,
+                    device.copy_options,
+                )
+            };
 
-    /// ディレクトリを再帰的にコピー（内部実装）
-    fn copy_files_recursive(
-        source: &Path,
-        dest: &Path,
-        device_name: &str,
-        category: &str,
-        app_handle: &AppHandle,
-        total_file_count: u64,
-        copied_files: &mut u64,
-        failed_files: &mut u64,
-        total_size: &mut u64,
-    ) -> Result<(), String> {
-        if source.is_dir() {
-            // ディレクトリの場合、中身を再帰的にコピー
-            let entries = fs::read_dir(source)
-                .map_err(|e| format!("ディレクトリ読み込みエラー {}: {}", source.display(), e))?;
-
-            for entry in entries {
-                let entry = entry.map_err(|e| format!("エントリ読み込みエラー: {}", e))?;
-                let source_path = entry.path();
-                let file_name = entry.file_name();
-                let dest_path = dest.join(&file_name);
-
-                if source_path.is_dir() {
-                    //ロット番号名フォルダの中にさらにフォルダがある場合、それをNASにも作成
-                    fs::create_dir_all(&dest_path)
-                        .map_err(|e| format!("ディレクトリ作成エラー {}: {}", dest_path.display(), e))?;
-
-                    Self::copy_files_recursive(
-                        &source_path,
-                        &dest_path,
-                        device_name,
-                        category,
-                        app_handle,
-                        total_file_count,
-                        copied_files,
-                        failed_files,
-                        total_size,
-                    )?;
-                } else {
-                    // ファイルコピー
-                    match fs::copy(&source_path, &dest_path) {
-                        Ok(size) => {
-                            *copied_files += 1;
-                            *total_size += size;
-
-                            // 進捗を通知（結果ファイルは1回ごと、画像は100ファイルに1回）
-                            let should_report = if category.contains("結果ファイル") {
-                                true  // 結果ファイルは毎回報告
-                            } else {
-                                *copied_files % 100 == 0  // 画像は100ファイルに1回
-                            };
-
-                            if should_report {
-                                let progress = BackupProgress {
-                                    current_files: *copied_files,
-                                    total_files: total_file_count,
-                                    current_size: *total_size,
-                                    total_size: *total_size,
-                                    percentage: (*copied_files as f32 / total_file_count as f32) * 100.0,
-                                    current_file: source_path.to_string_lossy().to_string(),
-                                    current_device: format!("{} - {}", device_name, category),
-                                };
-
-                                let _ = app_handle.emit("backup-progress", progress);
-                            }
-
-                            log::info!("Backup file : {}",&source_path.to_string_lossy().to_string());
-                        }
-                        Err(e) => {
-                            *failed_files += 1;
-                            log::error!("ファイルコピー失敗 {} -> {}: {}",
-                                source_path.display(), dest_path.display(), e);
+            let (action, copied) = match resolution {
+                Ok(Resolution::Copy) => (FileAction::Copied, copy().map(Some)),
+                Ok(Resolution::KeepBoth(_)) => (FileAction::KeptBoth, copy().map(Some)),
+                Ok(Resolution::Skip) => (FileAction::SkippedIdentical, Ok(None)),
+                Ok(Resolution::Conflict) => (
+                    FileAction::Conflict,
+                    Err("コピー先に異なる内容の同名ファイルがあります".to_string()),
+                ),
+                Ok(Resolution::Overwrite) if device.settings.lot_versioning => {
+                    // サイズ・更新日時が同じファイルは世代に残さず上書きする（再コピーで世代が増えないようにする）
+                    let copied = match conflict_check.is_unchanged(file) {
+                        Ok(true) => copy(),
+                        Ok(false) => {
+                            let version = version.get_or_insert_with(new_version_name);
+                            preserve_file(backend, &lot.dest_path, &lot.entry_name, version, &file.relative_path)
+                                .and_then(|_| {
+                                    preserved_files += 1;
+                                    let copied = copy();
+                                    if copied.is_err() {
+                                        // コピーに失敗した場合は上書き前のファイルを戻す
+                                        match revert_file(backend, &lot.dest_path, &lot.entry_name, version, &file.relative_path) {
+                                            Ok(()) => preserved_files -= 1,
+                                            Err(e) => log::error!("上書き前のファイルを戻せません {}: {}", file_dest_path, e),
+                                        }
+                                    }
+                                    copied
+                                })
                         }
-                    }
+                        Err(e) => Err(e),
+                    };
+                    (FileAction::Overwritten, copied.map(Some))
+                }
+                Ok(Resolution::Overwrite) => (FileAction::Overwritten, copy().map(Some)),
+                Err(e) => (FileAction::Failed, Err(e)),
+            };
+
+            // コピーしたファイルにコピー元の日時・属性を引き継ぐ
+            let (attributes_verified, attributes_error) = match copied {
+                Ok(Some(_)) => Self::preserve_attributes(backend, &file_dest_path, &file.attributes),
+                _ => (None, None),
+            };
+
+            // 進捗を記録（通知は一定時間ごとに間引かれる）
+            let mut progress = device.progress.lock().unwrap_or_else(|e| e.into_inner());
+            let (action, detail) = match copied {
+                Ok(Some(size)) => {
+                    stats.copied_files += 1;
+                    stats.total_size += size;
+                    progress.record_file(
+                        &device.device_name,
+                        lot.category,
+                        &display_path,
+                        size,
+                        Some(size),
+                    );
+
+                    log::info!("Backup file : {}", display_path);
+                    (action, attributes_error)
+                }
+                Ok(None) => {
+                    stats.skipped_files += 1;
+                    progress.record_file(
+                        &device.device_name,
+                        lot.category,
+                        &display_path,
+                        file.size,
+                        None,
+                    );
+
+                    log::debug!("    スキップ: {} (コピー先に同一のファイルあり)", display_path);
+                    (action, None)
+                }
+                Err(e) => {
+                    stats.failed_files += 1;
+                    progress.record_file(
+                        &device.device_name,
+                        lot.category,
+                        &display_path,
+                        file.size,
+                        None,
+                    );
+                    log::error!("ファイルコピー失敗 {} -> {}: {}",
+                        display_path, file_dest_path, e);
+                    // 競合以外の失敗はコピーエラーとして記録
+                    let action = if action == FileAction::Conflict {
+                        stats.conflict_files += 1;
+                        action
+                    } else {
+                        FileAction::Failed
+                    };
+                    (action, Some(e))
                 }
+            };
+            drop(progress);
+
+            device.file_audit.record(&FileAuditEntry {
+                timestamp: Local::now().format(AUDIT_TIMESTAMP_FORMAT).to_string(),
+                device_name: device.device_name.clone(),
+                category: lot.category.to_string(),
+                lot_name: lot.entry_name.clone(),
+                source_path: display_path,
+                dest_path: file_dest_path,
+                nas: backend.display_name(),
+                action,
+                attributes_verified,
+                detail,
+            });
+        }
+
+        if let Some(version) = version.filter(|_| preserved_files > 0) {
+            log::info!("上書き前のファイルを世代フォルダに残しました: {} ({}, {} ファイル)", dest_path, version, preserved_files);
+            if let Err(e) = prune_versions(backend, &lot.dest_path, &lot.entry_name, device.settings.max_lot_versions) {
+                log::warn!("古い世代を削除できません {}: {}", dest_path, e);
+            }
+        }
+
+        Ok(stats)
+    }
+
+    /// コピー元の更新日時・作成日時・読み取り専用属性をコピー先のファイルに設定し、読み直して確認する
+    /// 戻り値: (確認結果（コピー先が対応していない場合はNone）, 引き継げなかった内容)
+    pub fn preserve_attributes(
+        backend: &dyn StorageBackend,
+        dest_path: &str,
+        attributes: &FileAttributes,
+    ) -> (Option<bool>, Option<String>) {
+        let copied = backend.set_attributes(dest_path, attributes).and_then(|applied| {
+            if applied {
+                backend.attributes(dest_path)
+            } else {
+                Ok(None)
+            }
+        });
+
+        let differences = match copied {
+            Ok(Some(copied)) => attributes.differences(&copied),
+            Ok(None) => return (None, None),
+            Err(e) => vec![e],
+        };
+        if differences.is_empty() {
+            return (Some(true), None);
+        }
+
+        let message = format!("日時・属性を引き継げません: {}", differences.join(", "));
+        log::warn!("{} ({})", message, dest_path);
+        (Some(false), Some(message))
+    }
+
+    /// ファイルを1つコピー
+    /// ローカルからアクセスできるコピー元は転送速度の制御がなければOSのコピー機能を使い（COPY_BENCHMARK.md）、
+    /// それ以外は読み込みと書き込みを並行させた先読み付きのストリームで転送する
+    /// 読み込み遅延による自動調整が有効な場合も、SMB共有の読み込みを制御できるようストリームで転送する
+    fn copy_file(
+        source: &dyn SourceAccess,
+        source_path: &str,
+        file: &SourceFile,
+        backend: &dyn StorageBackend,
+        dest_path: &str,
+        throttle: &Throttle,
+        copy_options: CopyOptions,
+    ) -> Result<u64, String> {
+        if !throttle.is_limited() {
+            if let Some(local_path) = source.local_path(source_path) {
+                return backend.copy_from_local(&local_path, dest_path);
             }
         }
 
-        Ok(())
+        let mut reader = throttle.reader(source.open(source_path)?);
+        copy_stream(
+            &mut reader,
+            |chunks| backend.write(dest_path, chunks, file.size),
+            copy_options,
+        )
     }
 }
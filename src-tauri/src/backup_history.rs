@@ -0,0 +1,139 @@
+use std::fs;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::config::get_data_file_path;
+use crate::types::{BackupHistoryEntry, BackupHistoryPage, BackupHistoryQuery};
+
+/// 履歴ファイル名（config.jsonと同じディレクトリに保存）
+const HISTORY_FILE_NAME: &str = "backup_history.json";
+/// 保持する履歴の最大件数（超えた分は古いものから削除）
+const MAX_HISTORY_ENTRIES: usize = 1000;
+
+/// バックアップ実行履歴を管理する構造体
+#[derive(Clone)]
+pub struct BackupHistory {
+    entries: Arc<RwLock<Vec<BackupHistoryEntry>>>,
+}
+
+impl BackupHistory {
+    /// 履歴ファイルを読み込んでBackupHistoryインスタンスを作成
+    /// ファイルが無い・壊れている場合は空の履歴から始める
+    pub fn load() -> Self {
+        let entries = match Self::read_history_file() {
+            Ok(entries) => entries,
+            Err(e) => {
+                log::warn!("Failed to load backup history, starting empty: {}", e);
+                Vec::new()
+            }
+        };
+
+        log::info!("Loaded {} backup history entries", entries.len());
+
+        Self {
+            entries: Arc::new(RwLock::new(entries)),
+        }
+    }
+
+    /// 実行結果を履歴に追加してファイルに保存
+    pub async fn record(&self, mut entry: BackupHistoryEntry) -> Result<(), String> {
+        let mut entries = self.entries.write().await;
+
+        entry.id = entries.iter().map(|e| e.id).max().unwrap_or(0) + 1;
+        entries.push(entry);
+
+        if entries.len() > MAX_HISTORY_ENTRIES {
+            let excess = entries.len() - MAX_HISTORY_ENTRIES;
+            entries.drain(..excess);
+        }
+
+        Self::write_history_file(&entries)
+    }
+
+    /// すべての履歴を古い順に取得
+    pub async fn get_entries(&self) -> Vec<BackupHistoryEntry> {
+        self.entries.read().await.clone()
+    }
+
+    /// 条件に一致する履歴を新しい順にページ単位で取得
+    pub async fn query(&self, query: BackupHistoryQuery) -> BackupHistoryPage {
+        let entries = self.entries.read().await;
+
+        let matched: Vec<&BackupHistoryEntry> = entries
+            .iter()
+            .rev()
+            .filter(|entry| Self::matches(entry, &query))
+            .collect();
+
+        let page_size = query.page_size.max(1);
+        let page_entries = matched
+            .iter()
+            .skip(query.page * page_size)
+            .take(page_size)
+            .map(|entry| (*entry).clone())
+            .collect();
+
+        BackupHistoryPage {
+            total: matched.len(),
+            entries: page_entries,
+        }
+    }
+
+    /// 履歴1件が検索条件に一致するか判定
+    fn matches(entry: &BackupHistoryEntry, query: &BackupHistoryQuery) -> bool {
+        // 開始日時 "YYYY-MM-DD HH:MM:SS" の日付部分で比較
+        let start_date = entry.start_time.get(..10).unwrap_or(&entry.start_time);
+
+        if let Some(from_date) = &query.from_date {
+            if start_date < from_date.as_str() {
+                return false;
+            }
+        }
+
+        if let Some(to_date) = &query.to_date {
+            if start_date > to_date.as_str() {
+                return false;
+            }
+        }
+
+        if let Some(status) = query.status {
+            if entry.status != status {
+                return false;
+            }
+        }
+
+        if let Some(device_name) = &query.device_name {
+            if !entry.devices.iter().any(|name| name == device_name) {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// 履歴ファイルを読み込む
+    fn read_history_file() -> Result<Vec<BackupHistoryEntry>, String> {
+        let history_path = get_data_file_path(HISTORY_FILE_NAME)?;
+
+        if !history_path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = fs::read_to_string(&history_path)
+            .map_err(|e| format!("Failed to read history file at {:?}: {}", history_path, e))?;
+
+        serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse history file at {:?}: {}", history_path, e))
+    }
+
+    /// 履歴ファイルに書き込む
+    fn write_history_file(entries: &[BackupHistoryEntry]) -> Result<(), String> {
+        let history_path = get_data_file_path(HISTORY_FILE_NAME)?;
+
+        let content = serde_json::to_string_pretty(entries)
+            .map_err(|e| format!("Failed to serialize backup history: {}", e))?;
+
+        fs::write(&history_path, content)
+            .map_err(|e| format!("Failed to write history file at {:?}: {}", history_path, e))
+    }
+}
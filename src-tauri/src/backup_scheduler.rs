@@ -1,19 +1,24 @@
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use tokio::time::{interval, Duration};
+use tokio::time::{interval, sleep, Duration};
 use tauri::{AppHandle, Emitter};
 use chrono::{Local, Timelike};
 
 use crate::app_monitor::AppMonitor;
 use crate::settings_monitor::SettingsMonitor;
-use crate::backup_executor::BackupExecutor;
-use crate::types::BackupStatus;
+use crate::backup_executor::{BackupExecutor, LotScope};
+use crate::backup_history::BackupHistory;
+use crate::lot_watcher::LotWatcher;
+use crate::nas_inventory::NasInventory;
+use crate::types::{BackupStatus, BackupHistoryEntry, BackupTrigger, BackupRunStatus, InspConfig};
 
 /// バックアップのスケジューリングを担当する構造体
 #[derive(Clone)]
 pub struct BackupScheduler {
     settings_monitor: SettingsMonitor,
     app_monitor: AppMonitor,
+    backup_history: BackupHistory,
+    nas_inventory: NasInventory,
     is_running: Arc<RwLock<bool>>,
     last_backup_date: Arc<RwLock<Option<String>>>,
     last_backup_nas_id: Arc<RwLock<Option<u32>>>,
@@ -21,7 +26,12 @@ pub struct BackupScheduler {
 
 impl BackupScheduler {
     /// 新しいBackupSchedulerインスタンスを作成
-    pub async fn new(settings_monitor: SettingsMonitor, app_monitor: AppMonitor) -> Self {
+    pub async fn new(
+        settings_monitor: SettingsMonitor,
+        app_monitor: AppMonitor,
+        backup_history: BackupHistory,
+        nas_inventory: NasInventory,
+    ) -> Self {
 
         // 使用可能で接続されているNASのみをフィルタ
         let nas_configs = app_monitor.get_nas_configs().await;
@@ -36,6 +46,8 @@ impl BackupScheduler {
         Self {
             settings_monitor,
             app_monitor,
+            backup_history,
+            nas_inventory,
             is_running: Arc::new(RwLock::new(false)),
             last_backup_date: Arc::new(RwLock::new(None)),
             last_backup_nas_id:Arc::new(RwLock::new(target_nas_id))
@@ -46,14 +58,14 @@ impl BackupScheduler {
     pub fn start_scheduling(self, app_handle: AppHandle) {
         tauri::async_runtime::spawn(async move {
             let mut interval = interval(Duration::from_secs(60)); // 1分ごと
+            let mut is_scheduled_pending = false;           // 連続バックアップの実行中に設定時刻になった場合、終了後に実行する
+            let mut last_scheduled_at: Option<String> = None; // 同じ時刻で2回実行しないように記録
 
             loop {
                 interval.tick().await;
 
-                // バックアップ実行中はスキップ
-                if *self.is_running.read().await {
-                    continue;
-                }
+                // 最終成功から日数が経過した検査機器を警告
+                self.check_backup_staleness(&app_handle).await;
 
                 // 現在時刻を取得
                 let now = Local::now();
@@ -70,10 +82,19 @@ impl BackupScheduler {
                 // 時刻が一致し、今日未実行の場合にバックアップ開始
                 //デバッグ時1日1回バックアップの制限外す
                 //if current_time == backup_time && !already_backed_up_today { 
-                if current_time == backup_time{
-                    log::info!("Backup time reached: {} - Starting backup...", current_time);
+                let scheduled_at = format!("{} {}", current_date, current_time);
+                if current_time == backup_time && last_scheduled_at.as_ref() != Some(&scheduled_at) {
+                    log::info!("Backup time reached: {}", current_time);
+                    last_scheduled_at = Some(scheduled_at);
+                    is_scheduled_pending = true;
+                }
 
-                    if let Err(e) = self.execute_backup(app_handle.clone()).await {
+                // バックアップ実行中の場合は終了後に開始する
+                if is_scheduled_pending && self.try_start_backup().await {
+                    is_scheduled_pending = false;
+                    log::info!("Starting scheduled backup...");
+
+                    if let Err(e) = self.execute_backup(app_handle.clone(), BackupTrigger::Scheduled, None).await {
                         let end_time = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
                         log::error!("Backup failed: {}", e);
                         let _ = app_handle.emit("backup-failed", (e,end_time));
@@ -83,11 +104,80 @@ impl BackupScheduler {
         });
     }
 
-    /// バックアップを実行
-    async fn execute_backup(&self, app_handle: AppHandle) -> Result<(), String> {
-        // 実行中フラグを立てる
-        *self.is_running.write().await = true;
+    /// 連続バックアップを開始（設定間隔ごとにコピー元の変化を確認し、確定したロットをバックアップ）
+    pub fn start_continuous_watch(self, app_handle: AppHandle) {
+        tauri::async_runtime::spawn(async move {
+            let mut lot_watcher = LotWatcher::new();
+
+            loop {
+                let settings = self.settings_monitor.get_settings().await;
+                sleep(Duration::from_secs(settings.continuous_poll_secs.max(1))).await;
+
+                // 連続バックアップ対象で接続中の検査機器
+                let insp_configs: Vec<InspConfig> = self.app_monitor.get_insp_configs().await
+                    .into_iter()
+                    .filter(|insp| insp.is_backup && insp.is_continuous && insp.is_connected)
+                    .collect();
+
+                // コピー元の確認はSFTP・FTPで時間がかかるためブロッキング用スレッドで実行
+                let stable_after = Duration::from_secs(settings.continuous_stable_secs);
+                let polled = tokio::task::spawn_blocking(move || {
+                    let lot_scope = lot_watcher.poll(&insp_configs, stable_after);
+                    (lot_watcher, lot_scope)
+                }).await;
+
+                let lot_scope = match polled {
+                    Ok((returned_watcher, lot_scope)) => {
+                        lot_watcher = returned_watcher;
+                        lot_scope
+                    }
+                    Err(e) => {
+                        log::error!("連続バックアップ: コピー元の確認に失敗しました: {}", e);
+                        lot_watcher = LotWatcher::new();
+                        continue;
+                    }
+                };
+
+                if lot_scope.is_empty() {
+                    continue;
+                }
+
+                // 他のバックアップ実行中は次回の確認で再度対象にする
+                if !self.try_start_backup().await {
+                    lot_watcher.requeue(&lot_scope);
+                    continue;
+                }
+
+                log::info!("Starting continuous backup...");
+                if let Err(e) = self.execute_backup(app_handle.clone(), BackupTrigger::Continuous, Some(lot_scope)).await {
+                    let end_time = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+                    log::error!("Continuous backup failed: {}", e);
+                    let _ = app_handle.emit("backup-failed", (e,end_time));
+                }
+            }
+        });
+    }
+
+    /// 実行中でなければ実行中フラグを立てる
+    /// 重複ロットの統合・世代の復元など、バックアップと同時に実行できないNASの操作でも使う
+    /// 戻り値: true = 実行を開始できる, false = 他のバックアップが実行中
+    pub async fn try_start_backup(&self) -> bool {
+        let mut is_running = self.is_running.write().await;
+        if *is_running {
+            return false;
+        }
+        *is_running = true;
+        true
+    }
+
+    /// 実行中フラグを下ろす
+    pub async fn finish_backup(&self) {
+        *self.is_running.write().await = false;
+    }
 
+    /// バックアップを実行（呼び出し前にtry_start_backupで実行中フラグを立てておく）
+    /// lot_scopeを指定した場合は指定したロットのみをバックアップする
+    async fn execute_backup(&self, app_handle: AppHandle, trigger: BackupTrigger, lot_scope: Option<LotScope>) -> Result<(), String> {
         log::info!("Starting backup execution...");
 
         // 開始イベントを通知
@@ -99,6 +189,14 @@ impl BackupScheduler {
         let insp_configs = self.app_monitor.get_insp_configs().await;
         let settings = self.settings_monitor.get_settings().await;
 
+        // 履歴用にバックアップ対象の検査機器名を控えておく
+        let target_devices: Vec<String> = insp_configs
+            .iter()
+            .filter(|insp| insp.is_backup)
+            .filter(|insp| lot_scope.as_ref().is_none_or(|scope| scope.contains_key(&insp.id)))
+            .map(|insp| insp.name.clone())
+            .collect();
+
         // バックアップを実行
         let result = BackupExecutor::execute(
             insp_configs,
@@ -106,18 +204,53 @@ impl BackupScheduler {
             settings,
             app_handle.clone(),
         *self.last_backup_nas_id.read().await,
+            lot_scope,
+            self.nas_inventory.clone(),
         ).await;
 
         // 実行中フラグを下ろす
-        *self.is_running.write().await = false;
+        self.finish_backup().await;
+
+        let end_time = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+
+        // 実行結果を履歴に保存
+        let history_entry = match &result {
+            Ok(backup_result) => BackupHistoryEntry {
+                id: 0,
+                start_time: start_time.clone(),
+                end_time: end_time.clone(),
+                trigger,
+                status: if backup_result.success { BackupRunStatus::Success } else { BackupRunStatus::Partial },
+                devices: target_devices,
+                result: Some(backup_result.clone()),
+                errors: backup_result.errors.clone(),
+            },
+            Err(e) => BackupHistoryEntry {
+                id: 0,
+                start_time: start_time.clone(),
+                end_time: end_time.clone(),
+                trigger,
+                status: BackupRunStatus::Failed,
+                devices: target_devices,
+                result: None,
+                errors: vec![e.clone()],
+            },
+        };
+        if let Err(e) = self.backup_history.record(history_entry).await {
+            log::error!("Failed to save backup history: {}", e);
+        }
 
         match result {
             Ok(backup_result) => {
-                // 成功した場合、最終バックアップ日を更新
-                let current_date = Local::now().format("%Y-%m-%d").to_string();
-                *self.last_backup_date.write().await = Some(current_date);
+                // 定期実行が成功した場合、最終バックアップ日を更新
+                if trigger == BackupTrigger::Scheduled {
+                    let current_date = Local::now().format("%Y-%m-%d").to_string();
+                    *self.last_backup_date.write().await = Some(current_date);
+                }
+
+                // 検査機器・カテゴリごとの最終成功日時を更新
+                self.app_monitor.record_backup_results(&backup_result.devices, &end_time).await;
 
-                let end_time = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
                 log::info!("Backup completed successfully: {:?}", backup_result);
                 let _ = app_handle.emit("backup-completed", (backup_result,end_time));
                 Ok(())
@@ -129,6 +262,29 @@ impl BackupScheduler {
         }
     }
 
+    /// 最終成功から設定日数を超えた検査機器があれば警告イベントを送信
+    async fn check_backup_staleness(&self, app_handle: &AppHandle) {
+        let stale_days = self.settings_monitor.get_settings().await.stale_backup_days;
+        let newly_stale = self.app_monitor.update_backup_staleness(stale_days).await;
+
+        if newly_stale.is_empty() {
+            return;
+        }
+
+        for insp_config in &newly_stale {
+            log::warn!(
+                "検査機器 {} のバックアップが{}日以上成功していません (最終成功: {})",
+                insp_config.name,
+                stale_days,
+                insp_config.last_successful_backup.as_deref().unwrap_or("なし")
+            );
+        }
+
+        if let Err(e) = app_handle.emit("backup-stale-alert", newly_stale) {
+            log::error!("Failed to emit backup-stale-alert event: {}", e);
+        }
+    }
+
     /// バックアップが実行中かどうかを取得
     pub async fn is_backup_running(&self) -> bool {
         *self.is_running.read().await
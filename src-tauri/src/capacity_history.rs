@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Arc;
+use chrono::{Duration, Local, NaiveDateTime};
+use tokio::sync::RwLock;
+
+use crate::config::get_data_file_path;
+use crate::types::{CapacityForecast, CapacitySample, NasConfig};
+
+/// 容量履歴ファイル名（config.jsonと同じディレクトリに保存）
+const CAPACITY_HISTORY_FILE_NAME: &str = "capacity_history.json";
+/// 同じNASのサンプルを記録する最小間隔（分）
+const SAMPLE_INTERVAL_MINUTES: i64 = 60;
+/// サンプルを保持する日数
+const SAMPLE_RETENTION_DAYS: i64 = 180;
+/// 増加量の推定に使う直近の日数
+const FORECAST_WINDOW_DAYS: i64 = 30;
+/// 予測に必要な最小観測期間（日）
+const MIN_FORECAST_SPAN_DAYS: f64 = 1.0;
+
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// NASごとの容量サンプルを管理する構造体
+#[derive(Clone)]
+pub struct CapacityHistory {
+    samples: Arc<RwLock<HashMap<u32, Vec<CapacitySample>>>>,
+}
+
+impl CapacityHistory {
+    /// 容量履歴ファイルを読み込んでCapacityHistoryインスタンスを作成
+    pub fn load() -> Self {
+        let samples = match Self::read_history_file() {
+            Ok(samples) => samples,
+            Err(e) => {
+                log::warn!("Failed to load capacity history, starting empty: {}", e);
+                HashMap::new()
+            }
+        };
+
+        Self {
+            samples: Arc::new(RwLock::new(samples)),
+        }
+    }
+
+    /// 接続中のNASの容量を記録（前回の記録から一定時間経過したNASのみ）
+    pub async fn record(&self, nas_configs: &[NasConfig]) {
+        let now = Local::now().naive_local();
+        let mut samples = self.samples.write().await;
+        let mut updated = false;
+
+        for nas_config in nas_configs {
+            if !nas_config.is_connected || nas_config.total_space == 0 {
+                continue;
+            }
+
+            let nas_samples = samples.entry(nas_config.id).or_default();
+
+            let is_due = match nas_samples.last().and_then(|s| parse_time(&s.timestamp)) {
+                Some(last) => now - last >= Duration::minutes(SAMPLE_INTERVAL_MINUTES),
+                None => true,
+            };
+            if !is_due {
+                continue;
+            }
+
+            nas_samples.push(CapacitySample {
+                timestamp: now.format(TIME_FORMAT).to_string(),
+                total_space: nas_config.total_space,
+                used_space: nas_config.used_space,
+                free_space: nas_config.free_space,
+            });
+
+            // 保持期間を過ぎたサンプルを削除
+            let cutoff = now - Duration::days(SAMPLE_RETENTION_DAYS);
+            nas_samples.retain(|s| parse_time(&s.timestamp).map(|t| t >= cutoff).unwrap_or(false));
+
+            updated = true;
+        }
+
+        if updated {
+            if let Err(e) = Self::write_history_file(&samples) {
+                log::error!("Failed to save capacity history: {}", e);
+            }
+        }
+    }
+
+    /// 各NASについて、空き容量がrequired_free_spaceに達するまでの日数を予測
+    pub async fn forecast(&self, nas_configs: &[NasConfig], required_free_space: u64) -> Vec<CapacityForecast> {
+        let samples = self.samples.read().await;
+        let now = Local::now().naive_local();
+        let window_start = now - Duration::days(FORECAST_WINDOW_DAYS);
+
+        nas_configs
+            .iter()
+            .map(|nas_config| {
+                // 直近の期間のサンプルを (経過日数, 使用量) に変換
+                let points: Vec<(f64, f64)> = samples
+                    .get(&nas_config.id)
+                    .map(|nas_samples| {
+                        nas_samples
+                            .iter()
+                            .filter_map(|s| parse_time(&s.timestamp).map(|t| (t, s.used_space)))
+                            .filter(|(t, _)| *t >= window_start)
+                            .map(|(t, used)| ((t - window_start).num_seconds() as f64 / 86400.0, used as f64))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+
+                let daily_growth_bytes = estimate_daily_growth(&points);
+
+                // 最新のサンプル（未接続の場合もサンプルの値で予測する）
+                let latest = samples.get(&nas_config.id).and_then(|s| s.last());
+                let free_space = if nas_config.is_connected {
+                    nas_config.free_space
+                } else {
+                    latest.map(|s| s.free_space).unwrap_or(0)
+                };
+
+                let days_until_full = match daily_growth_bytes {
+                    _ if free_space <= required_free_space && latest.is_some() => Some(0.0),
+                    Some(growth) if growth > 0.0 => {
+                        Some(free_space.saturating_sub(required_free_space) as f64 / growth)
+                    }
+                    _ => None,
+                };
+
+                CapacityForecast {
+                    nas_id: nas_config.id,
+                    nas_name: nas_config.name.clone(),
+                    free_space,
+                    required_free_space,
+                    daily_growth_bytes,
+                    days_until_full,
+                    sample_count: points.len(),
+                    last_sample_time: latest.map(|s| s.timestamp.clone()),
+                }
+            })
+            .collect()
+    }
+
+    /// 容量履歴ファイルを読み込む
+    fn read_history_file() -> Result<HashMap<u32, Vec<CapacitySample>>, String> {
+        let history_path = get_data_file_path(CAPACITY_HISTORY_FILE_NAME)?;
+
+        if !history_path.exists() {
+            return Ok(HashMap::new());
+        }
+
+        let content = fs::read_to_string(&history_path)
+            .map_err(|e| format!("Failed to read capacity history at {:?}: {}", history_path, e))?;
+
+        serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse capacity history at {:?}: {}", history_path, e))
+    }
+
+    /// 容量履歴ファイルに書き込む
+    fn write_history_file(samples: &HashMap<u32, Vec<CapacitySample>>) -> Result<(), String> {
+        let history_path = get_data_file_path(CAPACITY_HISTORY_FILE_NAME)?;
+
+        let content = serde_json::to_string(samples)
+            .map_err(|e| format!("Failed to serialize capacity history: {}", e))?;
+
+        fs::write(&history_path, content)
+            .map_err(|e| format!("Failed to write capacity history at {:?}: {}", history_path, e))
+    }
+}
+
+fn parse_time(timestamp: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(timestamp, TIME_FORMAT).ok()
+}
+
+/// 最小二乗法で1日あたりの使用量の増加を推定
+/// 観測期間が短すぎる場合はNone
+fn estimate_daily_growth(points: &[(f64, f64)]) -> Option<f64> {
+    if points.len() < 2 {
+        return None;
+    }
+
+    let first_day = points.iter().map(|p| p.0).fold(f64::INFINITY, f64::min);
+    let last_day = points.iter().map(|p| p.0).fold(f64::NEG_INFINITY, f64::max);
+    if last_day - first_day < MIN_FORECAST_SPAN_DAYS {
+        return None;
+    }
+
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+
+    let covariance: f64 = points.iter().map(|p| (p.0 - mean_x) * (p.1 - mean_y)).sum();
+    let variance: f64 = points.iter().map(|p| (p.0 - mean_x).powi(2)).sum();
+
+    if variance == 0.0 {
+        return None;
+    }
+
+    Some(covariance / variance)
+}
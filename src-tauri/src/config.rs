@@ -6,6 +6,8 @@ use serde_json::{Value, json};
 //独自クレートのimport
 use crate::types::{NasInfos,InspInfos,NasConfig,InspConfig,Configs,SettingsConfig,InspInfo,NasInfo};
 use crate::app_monitor::{check_nas_connection};
+use crate::address::parse_host_address;
+use crate::source_access::source_port;
 
 /// 設定ファイルの読み込みで初期化
 #[command]
@@ -39,6 +41,12 @@ pub async fn init_info() -> Result<(Configs,SettingsConfig), String> {
     //各NAS情報を追加
     let mut nas_configs = vec![];
     for data in nas_info.nass {
+        // 不正なアドレスは接続不可として扱い、理由を画面に表示する（監視スレッドは停止させない）
+        let connection_error = parse_host_address(&data.nas_ip).err();
+        if let Some(e) = &connection_error {
+            log::warn!("NAS {} のアドレスが不正です: {}", data.name, e);
+        }
+
         let nas_config = NasConfig {
             id: data.id,
             name: data.name,
@@ -49,6 +57,9 @@ pub async fn init_info() -> Result<(Configs,SettingsConfig), String> {
             free_space: 0,
             is_use: true,        //このNASを使用するかどうか(NASに接続できていてもここがfalseだと使用しない)
             is_connected: check_nas_connection(&data.nas_ip), //NASに接続できているか
+            mapping_error: None, //ドライブの接続先の確認は監視スレッドで行う
+            connection_error,
+            backend: data.backend,
         };
 
         nas_configs.push(nas_config);
@@ -57,6 +68,12 @@ pub async fn init_info() -> Result<(Configs,SettingsConfig), String> {
     //各insp情報を追加
     let mut insp_configs = vec![];
     for data in insp_info.insps {
+        // ローカルパスの場合はアドレスを使わないため確認しない
+        let connection_error = source_port(&data.source).and_then(|_| parse_host_address(&data.insp_ip).err());
+        if let Some(e) = &connection_error {
+            log::warn!("検査機器 {} のアドレスが不正です: {}", data.name, e);
+        }
+
         let insp_config = InspConfig {
             id: data.id,
             name: data.name,
@@ -66,6 +83,16 @@ pub async fn init_info() -> Result<(Configs,SettingsConfig), String> {
             surface_result_path:data.surface_result_path,
             back_result_path:data.back_result_path,
             is_backup:data.is_backup, //バックアップを実施するかどうか(config.jsonから読み込み)
+            last_successful_backup: None, //バックアップ履歴から復元する
+            category_backups: vec![],
+            is_backup_stale: false,
+            is_connected: false, //監視スレッドで更新する
+            connection_error,
+            unreadable_paths: vec![],
+            source: data.source,
+            is_continuous: data.is_continuous, //連続バックアップを行うかどうか(config.jsonから読み込み)
+            bandwidth_limit_mbps: data.bandwidth_limit_mbps,
+            file_filter: data.file_filter,
         };
 
         insp_configs.push(insp_config);
@@ -93,15 +120,12 @@ pub async fn save_settings(settings: SettingsConfig) -> Result<(), String> {
             format!("Failed to parse config JSON: {}", e)
         })?;
 
-    // settings部分を更新
-    value["settings"] = json!({
-        "backup_time": settings.backup_time,
-        "surface_image_path": settings.surface_image_path,
-        "back_image_path": settings.back_image_path,
-        "surface_result_file_path": settings.surface_result_file_path,
-        "back_result_file_path": settings.back_result_file_path,
-        "required_free_space": settings.required_free_space,
-    });
+    // settings部分を更新（SettingsConfigの全項目をそのまま保存）
+    value["settings"] = serde_json::to_value(&settings)
+        .map_err(|e| {
+            log::error!("Failed to serialize settings: {}", e);
+            format!("Failed to serialize settings: {}", e)
+        })?;
 
     // ファイルに書き込む（インデント付き）
     let updated_content = serde_json::to_string_pretty(&value)
@@ -157,6 +181,10 @@ pub async fn save_insp_settings(insp: InspInfo,keyword:&str) -> Result<(), Strin
                 info.surface_result_path=insp.surface_result_path.clone();
                 info.back_result_path=insp.back_result_path.clone();
                 info.is_backup=insp.is_backup;
+                info.bandwidth_limit_mbps=insp.bandwidth_limit_mbps;
+                info.file_filter=insp.file_filter.clone();
+                info.source=insp.source.clone().with_secrets_from(&info.source);
+                info.is_continuous=insp.is_continuous;
             }
         }
     }else if keyword=="add"{
@@ -215,6 +243,7 @@ pub async fn save_nas_settings(nas: NasInfo,keyword:&str) -> Result<(), String>
                 info.name=nas.name.clone();
                 info.nas_ip=nas.nas_ip.clone();
                 info.drive=nas.drive.clone();
+                info.backend=nas.backend.clone().with_secrets_from(&info.backend);
             }
         }
     }else if keyword=="add"{
@@ -279,6 +308,49 @@ pub async fn save_insp_backup_setting(insp_id: u32, is_backup: bool) -> Result<(
     Ok(())
 }
 
+/// 連続バックアップ設定の切り替えをconfig.jsonに保存
+#[command]
+pub async fn save_insp_continuous_setting(insp_id: u32, is_continuous: bool) -> Result<(), String> {
+    let config_path = get_config_path()?;
+
+    // 既存のconfig.jsonを読み込む
+    let config_content = fs::read_to_string(&config_path)
+        .map_err(|e| format!("Failed to read config file at {:?}: {}", config_path, e))?;
+
+    // JSONとしてパース
+    let mut value: Value = serde_json::from_str(&config_content)
+        .map_err(|e| format!("Failed to parse config JSON: {}", e))?;
+
+    let mut insp_info: InspInfos = serde_json::from_value(value["insp_units"].clone())
+        .map_err(|e| format!("Failed to parse insp_units: {}", e))?;
+
+    // idが一致する情報のis_continuousを更新
+    for info in &mut insp_info.insps {
+        if info.id == insp_id {
+            info.is_continuous = is_continuous;
+            break;
+        }
+    }
+
+    value["insp_units"]["insps"] = json!(insp_info.insps);
+
+    // ファイルに書き込む（インデント付き）
+    let updated_content = serde_json::to_string_pretty(&value)
+        .map_err(|e| format!("Failed to serialize config: {}", e))?;
+
+    fs::write(&config_path, updated_content)
+        .map_err(|e| format!("Failed to write config file at {:?}: {}", config_path, e))?;
+
+    log::info!("Continuous backup setting saved successfully to {:?}", config_path);
+    Ok(())
+}
+
+/// config.jsonと同じディレクトリに置くデータファイルのパスを取得
+pub fn get_data_file_path(file_name: &str) -> Result<PathBuf, String> {
+    let config_path = get_config_path()?;
+    Ok(config_path.with_file_name(file_name))
+}
+
 fn get_config_path()->Result<PathBuf,String>{
     // 開発時とリリース時でパスを変える
     #[cfg(debug_assertions)]
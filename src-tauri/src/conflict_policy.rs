@@ -0,0 +1,200 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Read};
+
+use sha2::{Digest, Sha256};
+
+use crate::source_access::{SourceAccess, SourceFile, MTIME_TOLERANCE_SECS};
+use crate::storage_backend::{join_storage_path, StorageBackend};
+use crate::throttle::Throttle;
+use crate::types::{ConflictPolicy, IdenticalCheck};
+
+/// コピー先に同名のファイルがある場合の処理
+#[derive(Debug, Clone, PartialEq)]
+pub enum Resolution {
+    /// コピー先に無いためそのままコピー
+    Copy,
+    /// コピー先のファイルを上書き
+    Overwrite,
+    /// コピー先に同一のファイルがあるためスキップ
+    Skip,
+    /// 番号を付けた名前でコピー（ロットフォルダからの相対パス）
+    KeepBoth(String),
+    /// コピー先に異なるファイルがあるため失敗
+    Conflict,
+}
+
+/// ロット1つ分のコピー先の同名ファイルの確認
+pub struct ConflictCheck<'a> {
+    pub policy: ConflictPolicy,
+    pub identical_check: IdenticalCheck,
+    pub source: &'a dyn SourceAccess,
+    pub throttle: &'a Throttle,
+    pub backend: &'a dyn StorageBackend,
+    pub dest_path: &'a str,                     // コピー先のロットフォルダのパス
+    pub existing_files: &'a HashMap<String, u64>,   // コピー先のロットフォルダ内のファイル（相対パス → サイズ）
+}
+
+impl ConflictCheck<'_> {
+    /// コピー元のファイルをどう処理するか決める
+    /// 上書き以外の処理では、同一のファイルがあればスキップする
+    pub fn resolve(&self, source_path: &str, file: &SourceFile) -> Result<Resolution, String> {
+        let Some(&dest_size) = self.existing_files.get(&file.relative_path) else {
+            return Ok(Resolution::Copy);
+        };
+        if self.policy == ConflictPolicy::Overwrite {
+            return Ok(Resolution::Overwrite);
+        }
+        if self.is_identical(source_path, file, &file.relative_path, dest_size)? {
+            return Ok(Resolution::Skip);
+        }
+
+        match self.policy {
+            ConflictPolicy::Overwrite | ConflictPolicy::SkipIfIdentical => Ok(Resolution::Overwrite),
+            ConflictPolicy::Fail => Ok(Resolution::Conflict),
+            ConflictPolicy::KeepBoth => {
+                // 前回番号を付けてコピーしたファイルと同一ならスキップ（毎回番号付きのファイルが増えないようにする）
+                let mut number = 1;
+                loop {
+                    let candidate = suffixed_path(&file.relative_path, number);
+                    match self.existing_files.get(&candidate) {
+                        None => return Ok(Resolution::KeepBoth(candidate)),
+                        Some(&size) if self.is_identical(source_path, file, &candidate, size)? => {
+                            return Ok(Resolution::Skip);
+                        }
+                        Some(_) => number += 1,
+                    }
+                }
+            }
+        }
+    }
+
+    /// コピー先の同名のファイルがコピー元とサイズ・更新日時とも同じか（同名のファイルが無い場合はfalse）
+    /// 上書きする前のファイルを世代に残すかの判定に使う（同じ場合は残さない）
+    pub fn is_unchanged(&self, file: &SourceFile) -> Result<bool, String> {
+        match self.existing_files.get(&file.relative_path) {
+            Some(&dest_size) => self.has_same_size_and_mtime(file, &file.relative_path, dest_size),
+            None => Ok(false),
+        }
+    }
+
+    /// コピー元のファイルとコピー先のファイル（ロットフォルダからの相対パス）のサイズ・更新日時が同じか
+    fn has_same_size_and_mtime(&self, file: &SourceFile, relative_path: &str, dest_size: u64) -> Result<bool, String> {
+        if file.size != dest_size {
+            return Ok(false);
+        }
+        let dest_file_path = join_storage_path(self.dest_path, relative_path);
+        let dest_modified = self.backend.stat(&dest_file_path)?.and_then(|entry| entry.modified);
+        // 更新日時を取得できない場合は同じとみなさない
+        Ok(match (file.attributes.modified_secs(), dest_modified) {
+            (Some(source_modified), Some(dest_modified)) => source_modified.abs_diff(dest_modified) <= MTIME_TOLERANCE_SECS,
+            _ => false,
+        })
+    }
+
+    /// コピー元のファイルとコピー先のファイル（ロットフォルダからの相対パス）が同一か
+    fn is_identical(&self, source_path: &str, file: &SourceFile, relative_path: &str, dest_size: u64) -> Result<bool, String> {
+        if file.size != dest_size {
+            return Ok(false);
+        }
+
+        let dest_file_path = join_storage_path(self.dest_path, relative_path);
+        match self.identical_check {
+            IdenticalCheck::SizeAndMtime => self.has_same_size_and_mtime(file, relative_path, dest_size),
+            IdenticalCheck::Hash => {
+                let source_hash = sha256(self.throttle.reader(self.source.open(source_path)?))
+                    .map_err(|e| format!("ファイル読み込みエラー {}: {}", self.source.display_path(source_path), e))?;
+                let dest_hash = sha256(self.backend.open(&dest_file_path)?)
+                    .map_err(|e| format!("ファイル読み込みエラー {}: {}", dest_file_path, e))?;
+                Ok(source_hash == dest_hash)
+            }
+        }
+    }
+}
+
+/// 相対パスをフォルダ・ファイル名の幹・拡張子に分ける
+fn split_path(relative_path: &str) -> (Option<&str>, &str, Option<&str>) {
+    let (dir, name) = match relative_path.rsplit_once('/') {
+        Some((dir, name)) => (Some(dir), name),
+        None => (None, relative_path),
+    };
+    match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (dir, stem, Some(extension)),
+        _ => (dir, name, None),
+    }
+}
+
+/// フォルダ・ファイル名の幹・拡張子から相対パスを組み立てる
+fn join_path(dir: Option<&str>, stem: &str, extension: Option<&str>) -> String {
+    let name = match extension {
+        Some(extension) => format!("{}.{}", stem, extension),
+        None => stem.to_string(),
+    };
+    match dir {
+        Some(dir) => format!("{}/{}", dir, name),
+        None => name,
+    }
+}
+
+/// ファイル名の末尾（拡張子の前）に番号を付ける（例: img/0001.jpg → img/0001 (1).jpg）
+fn suffixed_path(relative_path: &str, number: u32) -> String {
+    let (dir, stem, extension) = split_path(relative_path);
+    join_path(dir, &format!("{} ({})", stem, number), extension)
+}
+
+/// 番号を付けた名前から元の名前を求める（番号が付いていない場合はNone）
+fn unsuffixed_path(relative_path: &str) -> Option<String> {
+    let (dir, stem, extension) = split_path(relative_path);
+    let (original, number) = stem.strip_suffix(')')?.rsplit_once(" (")?;
+    if original.is_empty() || number.starts_with('0') || number.parse::<u32>().is_err() {
+        return None;
+    }
+    Some(join_path(dir, original, extension))
+}
+
+/// 番号を付けて残したファイル（同じフォルダに元の名前のファイルがあるもの）を除いたファイル数
+/// 両方残す設定でコピーしたロットも、コピー元とファイル数を比べられるようにする
+pub fn canonical_file_count<'a>(relative_paths: impl IntoIterator<Item = &'a str>) -> u32 {
+    let paths: HashSet<&str> = relative_paths.into_iter().collect();
+    paths
+        .iter()
+        .filter(|path| !unsuffixed_path(path).is_some_and(|original| paths.contains(original.as_str())))
+        .count() as u32
+}
+
+/// 読み込んだ内容のSHA-256
+pub fn sha256(mut reader: impl Read) -> io::Result<Vec<u8>> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut reader, &mut hasher)?;
+    Ok(hasher.finalize().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suffixed_path_numbers_before_extension() {
+        assert_eq!(suffixed_path("0001.jpg", 1), "0001 (1).jpg");
+        assert_eq!(suffixed_path("img/0001.tar.gz", 2), "img/0001.tar (2).gz");
+        assert_eq!(suffixed_path("README", 3), "README (3)");
+        assert_eq!(suffixed_path(".hidden", 1), ".hidden (1)");
+    }
+
+    #[test]
+    fn unsuffixed_path_reverses_suffixed_path() {
+        for path in ["0001.jpg", "img/0001.tar.gz", "README", ".hidden"] {
+            assert_eq!(unsuffixed_path(&suffixed_path(path, 12)).as_deref(), Some(path));
+        }
+        for path in ["0001.jpg", "0001_1.jpg", "0001 (0).jpg", "0001 (01).jpg", "0001 (a).jpg", " (1).jpg"] {
+            assert_eq!(unsuffixed_path(path), None, "{}", path);
+        }
+    }
+
+    #[test]
+    fn canonical_file_count_skips_kept_copies() {
+        assert_eq!(canonical_file_count(["a.jpg", "a (1).jpg", "a (2).jpg", "img/b.jpg", "img/b (1).jpg"]), 2);
+        // 元の名前のファイルが無い場合は番号付きの名前でも数える
+        assert_eq!(canonical_file_count(["a (1).jpg", "img/a.jpg"]), 2);
+        assert_eq!(canonical_file_count(Vec::<&str>::new()), 0);
+    }
+}
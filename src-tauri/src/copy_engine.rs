@@ -0,0 +1,406 @@
+use std::fs;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::thread;
+use std::time::Instant;
+
+use walkdir::WalkDir;
+
+use crate::types::{CopyBenchmarkResult, SettingsConfig};
+
+/// ストリームコピーの設定
+#[derive(Debug, Clone, Copy)]
+pub struct CopyOptions {
+    pub buffer_size: usize,     // 1回に読み込むバイト数
+    pub read_ahead: usize,      // 書き込みを待たずに先読みしておくバッファ数
+}
+
+impl CopyOptions {
+    /// 設定からコピーの設定を作成
+    pub fn from_settings(settings: &SettingsConfig) -> Self {
+        Self {
+            buffer_size: (settings.copy_buffer_size_kib.max(4) as usize) * 1024,
+            read_ahead: settings.copy_read_ahead_buffers.max(1) as usize,
+        }
+    }
+}
+
+/// 読み込みと書き込みを別スレッドで並行して行うストリームコピー
+/// 呼び出し元のスレッドがコピー元を大きなバッファ単位で先読みし、writeに渡す読み込みは別スレッドで消費する
+/// バッファ1つに収まる小さなファイルはスレッドを使わずにそのまま書き込む
+/// 戻り値: writeの戻り値（書き込んだバイト数）
+pub fn copy_stream<W>(reader: &mut dyn Read, write: W, options: CopyOptions) -> Result<u64, String>
+where
+    W: FnOnce(&mut dyn Read) -> Result<u64, String> + Send,
+{
+    let mut first = vec![0u8; options.buffer_size];
+    let first_len = read_chunk(reader, &mut first).map_err(|e| format!("ファイル読み込みエラー: {}", e))?;
+    if first_len < options.buffer_size {
+        return write(&mut &first[..first_len]);
+    }
+    first.truncate(first_len);
+
+    thread::scope(|scope| {
+        let (sender, receiver) = sync_channel::<io::Result<Vec<u8>>>(options.read_ahead);
+        // 書き込み済みのバッファを読み込み側に戻して再利用する
+        let (recycle_sender, recycle_receiver) = sync_channel::<Vec<u8>>(options.read_ahead + 1);
+
+        let writer = scope.spawn(move || {
+            let mut chunks = ChunkReader {
+                receiver,
+                recycle: recycle_sender,
+                current: Vec::new(),
+                position: 0,
+            };
+            write(&mut chunks)
+        });
+
+        let mut read_error = None;
+        let mut chunk = first;
+        loop {
+            // 書き込み側が終了している（エラー）場合は読み込みをやめる
+            if sender.send(Ok(chunk)).is_err() {
+                break;
+            }
+
+            let mut buffer = recycle_receiver
+                .try_recv()
+                .unwrap_or_else(|_| Vec::with_capacity(options.buffer_size));
+            buffer.resize(options.buffer_size, 0);
+            match read_chunk(reader, &mut buffer) {
+                Ok(0) => break,
+                Ok(n) => {
+                    buffer.truncate(n);
+                    chunk = buffer;
+                }
+                Err(e) => {
+                    read_error = Some(format!("ファイル読み込みエラー: {}", e));
+                    let _ = sender.send(Err(e));
+                    break;
+                }
+            }
+        }
+        drop(sender);
+
+        let written = writer
+            .join()
+            .unwrap_or_else(|_| Err("書き込みスレッドが異常終了しました".to_string()));
+
+        // 読み込みエラーの場合は書き込み側のエラーより読み込みエラーを優先して返す
+        match read_error {
+            Some(e) => Err(e),
+            None => written,
+        }
+    })
+}
+
+/// バッファがいっぱいになるか終端まで読み込む
+/// 戻り値: 読み込んだバイト数（終端の場合は0）
+fn read_chunk(reader: &mut dyn Read, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+/// 先読みしたバッファを順に読み出す
+struct ChunkReader {
+    receiver: Receiver<io::Result<Vec<u8>>>,
+    recycle: SyncSender<Vec<u8>>,
+    current: Vec<u8>,
+    position: usize,
+}
+
+impl Read for ChunkReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position >= self.current.len() {
+            match self.receiver.recv() {
+                Ok(chunk) => {
+                    let finished = std::mem::replace(&mut self.current, chunk?);
+                    let _ = self.recycle.try_send(finished);
+                    self.position = 0;
+                }
+                // 送信側が閉じたら終端
+                Err(_) => return Ok(0),
+            }
+        }
+
+        let n = buf.len().min(self.current.len() - self.position);
+        buf[..n].copy_from_slice(&self.current[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+/// コピー方式のスループットを計測する
+/// source_dir内のファイルを方式ごとにdest_dir下のサブフォルダへコピーし、所要時間を比較する
+/// - os_copy: OSのコピー機能（fs::copy、ローカル・SMBのコピー元の既定のコピー方式で比較の基準）
+/// - buffered_read_ahead: 読み込みと書き込みを並行させた先読み付きのストリーム（copy_stream）
+pub fn run_benchmark(source_dir: &Path, dest_dir: &Path, options: CopyOptions) -> Result<Vec<CopyBenchmarkResult>, String> {
+    let files: Vec<PathBuf> = WalkDir::new(source_dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .collect();
+
+    if files.is_empty() {
+        return Err(format!("計測用のファイルがありません: {}", source_dir.display()));
+    }
+
+    let methods: [(&str, BenchmarkCopy); 2] = [
+        ("os_copy", |source, dest, _| {
+            fs::copy(source, dest).map_err(|e| format!("ファイルコピーエラー {}: {}", source.display(), e))
+        }),
+        ("buffered_read_ahead", |source, dest, options| {
+            let mut reader = open_file(source)?;
+            copy_stream(
+                &mut reader,
+                |chunks| {
+                    let mut writer = BufWriter::with_capacity(options.buffer_size, create_file(dest)?);
+                    io::copy(chunks, &mut writer)
+                        .and_then(|written| writer.flush().map(|_| written))
+                        .map_err(|e| format!("ファイル書き込みエラー {}: {}", dest.display(), e))
+                },
+                options,
+            )
+        }),
+    ];
+
+    let mut results = Vec::with_capacity(methods.len());
+    for (method, copy) in methods {
+        let method_dir = dest_dir.join(method);
+        fs::create_dir_all(&method_dir)
+            .map_err(|e| format!("ディレクトリ作成エラー {}: {}", method_dir.display(), e))?;
+
+        let started = Instant::now();
+        let mut total_size = 0u64;
+        for (index, file) in files.iter().enumerate() {
+            total_size += copy(file, &method_dir.join(format!("{}.bin", index)), options)?;
+        }
+        let duration_secs = started.elapsed().as_secs_f64();
+
+        // 計測用にコピーしたファイルは残さない
+        if let Err(e) = fs::remove_dir_all(&method_dir) {
+            log::warn!("計測用のフォルダを削除できません {}: {}", method_dir.display(), e);
+        }
+
+        let throughput_mb_per_sec = if duration_secs > 0.0 {
+            total_size as f64 / 1_000_000.0 / duration_secs
+        } else {
+            0.0
+        };
+        log::info!(
+            "コピー計測 {}: {} files, {} bytes, {:.2}s, {:.1} MB/s",
+            method,
+            files.len(),
+            total_size,
+            duration_secs,
+            throughput_mb_per_sec
+        );
+
+        results.push(CopyBenchmarkResult {
+            method: method.to_string(),
+            files: files.len() as u64,
+            total_size_bytes: total_size,
+            duration_secs,
+            throughput_mb_per_sec,
+        });
+    }
+
+    Ok(results)
+}
+
+/// 計測するコピー方式（コピー元, コピー先, 設定）→ コピーしたバイト数
+type BenchmarkCopy = fn(&Path, &Path, CopyOptions) -> Result<u64, String>;
+
+fn open_file(path: &Path) -> Result<fs::File, String> {
+    fs::File::open(path).map_err(|e| format!("ファイル読み込みエラー {}: {}", path.display(), e))
+}
+
+fn create_file(path: &Path) -> Result<fs::File, String> {
+    fs::File::create(path).map_err(|e| format!("ファイル作成エラー {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// 模擬するネットワークの往復時間（読み込み・書き込み1回ごと）
+    const SIMULATED_RTT: Duration = Duration::from_micros(500);
+    /// 模擬するネットワークの転送速度（1GbE相当）
+    const SIMULATED_BYTES_PER_SEC: f64 = 110_000_000.0;
+    /// 模擬するコピー元が1回の読み込みで返す最大バイト数（SFTPの1リクエスト相当）
+    const SIMULATED_MAX_READ: usize = 64 * 1024;
+
+    fn options() -> CopyOptions {
+        CopyOptions { buffer_size: 1024 * 1024, read_ahead: 4 }
+    }
+
+    fn test_data(size: usize) -> Vec<u8> {
+        (0..size).map(|i| (i % 251) as u8).collect()
+    }
+
+    /// 1回の読み書きごとに往復時間と転送時間だけ待つ（ネットワーク越しのコピー元・コピー先の模擬）
+    fn simulate_network(bytes: usize) {
+        thread::sleep(SIMULATED_RTT + Duration::from_secs_f64(bytes as f64 / SIMULATED_BYTES_PER_SEC));
+    }
+
+    struct SimulatedSource<'a> {
+        data: &'a [u8],
+    }
+
+    impl Read for SimulatedSource<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = buf.len().min(self.data.len()).min(SIMULATED_MAX_READ);
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            simulate_network(n);
+            Ok(n)
+        }
+    }
+
+    struct SimulatedDest {
+        written: u64,
+    }
+
+    impl Write for SimulatedDest {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            simulate_network(buf.len());
+            self.written += buf.len() as u64;
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// 指定バイト数の後にエラーを返す読み込み
+    struct FailingSource {
+        remaining: usize,
+    }
+
+    impl Read for FailingSource {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.remaining == 0 {
+                return Err(io::Error::other("切断"));
+            }
+            let n = buf.len().min(self.remaining);
+            buf[..n].fill(0);
+            self.remaining -= n;
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn copy_stream_copies_all_bytes() {
+        // バッファ1つに収まるファイル・ちょうど1つ分・複数バッファにまたがるファイル
+        for size in [0, 10, 1024 * 1024, 5 * 1024 * 1024 + 123] {
+            let data = test_data(size);
+            let mut copied = Vec::new();
+            let written = copy_stream(
+                &mut &data[..],
+                |chunks| io::copy(chunks, &mut copied).map_err(|e| e.to_string()),
+                options(),
+            )
+            .unwrap();
+            assert_eq!(written, size as u64);
+            assert_eq!(copied, data, "size {}", size);
+        }
+    }
+
+    #[test]
+    fn copy_stream_returns_read_error() {
+        let mut reader = FailingSource { remaining: 3 * 1024 * 1024 };
+        let result = copy_stream(
+            &mut reader,
+            |chunks| io::copy(chunks, &mut io::sink()).map_err(|e| e.to_string()),
+            options(),
+        );
+        assert!(result.unwrap_err().starts_with("ファイル読み込みエラー"));
+    }
+
+    #[test]
+    fn copy_stream_returns_write_error() {
+        let data = test_data(3 * 1024 * 1024);
+        let result = copy_stream(&mut &data[..], |_| Err("書き込み失敗".to_string()), options());
+        assert_eq!(result.unwrap_err(), "書き込み失敗");
+    }
+
+    /// SFTP・FTPのようにOSのコピー機能を使えないコピー元を模擬して、
+    /// 読み込みと書き込みを交互に行うストリーム（io::copy）と先読み付きのストリームを比較する
+    /// 実行: cargo test --release --bin image_backup copy_engine::tests::simulated_network_benchmark -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn simulated_network_benchmark() {
+        for (label, size, count) in [("20MB x 5", 20_000_000, 5), ("100KB x 200", 100_000, 200)] {
+            let data = test_data(size);
+
+            let started = Instant::now();
+            for _ in 0..count {
+                let mut dest = SimulatedDest { written: 0 };
+                io::copy(&mut SimulatedSource { data: &data }, &mut dest).unwrap();
+                assert_eq!(dest.written, size as u64);
+            }
+            let sequential = started.elapsed().as_secs_f64();
+
+            let started = Instant::now();
+            for _ in 0..count {
+                let written = copy_stream(
+                    &mut SimulatedSource { data: &data },
+                    |chunks| {
+                        let mut writer = BufWriter::with_capacity(options().buffer_size, SimulatedDest { written: 0 });
+                        io::copy(chunks, &mut writer)
+                            .and_then(|written| writer.flush().map(|_| written))
+                            .map_err(|e| e.to_string())
+                    },
+                    options(),
+                )
+                .unwrap();
+                assert_eq!(written, size as u64);
+            }
+            let read_ahead = started.elapsed().as_secs_f64();
+
+            let total_mb = (size * count) as f64 / 1_000_000.0;
+            println!(
+                "{}: io::copy {:.1} MB/s, buffered_read_ahead {:.1} MB/s",
+                label,
+                total_mb / sequential,
+                total_mb / read_ahead
+            );
+        }
+    }
+
+    /// ローカルディスク間でOSのコピー機能と先読み付きのストリームを比較する（run_benchmarkと同じ計測）
+    /// 実行: cargo test --release --bin image_backup copy_engine::tests::local_disk_benchmark -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn local_disk_benchmark() {
+        let work_dir = std::env::temp_dir().join(format!("copy_benchmark_{}", std::process::id()));
+        for (label, size, count) in [("50MB x 20", 50_000_000, 20), ("100KB x 2000", 100_000, 2000)] {
+            let source_dir = work_dir.join("source");
+            fs::create_dir_all(&source_dir).unwrap();
+            let data = test_data(size);
+            for index in 0..count {
+                fs::write(source_dir.join(format!("{}.bin", index)), &data).unwrap();
+            }
+
+            // 1回目はページキャッシュ・ディスクの状態で値がぶれやすいため、同じデータで3回計測する
+            for round in 1..=3 {
+                for result in run_benchmark(&source_dir, &work_dir.join("dest"), options()).unwrap() {
+                    println!("{} ({}/3): {} {:.1} MB/s", label, round, result.method, result.throughput_mb_per_sec);
+                }
+            }
+            fs::remove_dir_all(&work_dir).unwrap();
+        }
+    }
+}
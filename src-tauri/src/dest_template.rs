@@ -0,0 +1,397 @@
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, Datelike, Local};
+use regex::{Captures, Regex};
+
+use crate::storage_backend::{join_storage_path, StorageBackend};
+use crate::types::{DestDateSource, SettingsConfig};
+
+/// 保存先のパスのテンプレートの既定値（NAS内保存パス/検査機器名/ロット名）
+pub const DEFAULT_DEST_PATH_TEMPLATE: &str = "{root}/{device}/{lot}";
+
+/// ロット名が正規表現に一致しない場合に、ロット名から取り出す値の代わりに使うフォルダ名
+const UNMATCHED_FOLDER_NAME: &str = "_unmatched";
+
+/// 更新日時からこの秒数が経つまではフォルダの一覧をキャッシュしない（更新日時と同じ秒の変更を見逃さないようにする）
+const LISTING_SETTLE_SECS: u64 = 2;
+
+/// 保存先フォルダの一覧のキャッシュ（(コピー先の表示名, フォルダのパス) → 一覧した時の状態）
+/// 日付などの階層が増えても、更新日時が変わっていないフォルダは一覧し直さない
+static FOLDER_LISTINGS: LazyLock<Mutex<HashMap<(String, String), CachedListing>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// 一覧したフォルダの状態
+struct CachedListing {
+    modified: u64,                              // 一覧した時のフォルダの更新日時（UNIX秒）
+    subfolders: Vec<(String, Option<u64>)>,     // 配下のフォルダ（名前, 更新日時）
+}
+
+fn lock_listings() -> MutexGuard<'static, HashMap<(String, String), CachedListing>> {
+    FOLDER_LISTINGS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// テンプレートのフォルダ名を構成する部品
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Literal(String),
+    Root,               // {root}: カテゴリのNAS内保存パス
+    Category,           // {category}: カテゴリ名（表面画像など）
+    Device,             // {device}: 検査機器名
+    Year,               // {yyyy}
+    Month,              // {mm}
+    Day,                // {dd}
+    Capture(String),    // {名前}・{番号}: ロット名の正規表現のグループ
+}
+
+impl Part {
+    /// ロットごとに値が変わる部品か（NAS上のフォルダを一覧して探す必要がある）
+    fn is_dynamic(&self) -> bool {
+        matches!(self, Part::Year | Part::Month | Part::Day | Part::Capture(_))
+    }
+}
+
+/// テンプレートを展開する検査機器・カテゴリ
+pub struct DestContext<'a> {
+    pub root: &'a str,
+    pub category: &'a str,
+    pub device: &'a str,
+}
+
+/// 保存先のパスのテンプレート（例: {root}/{category}/{device}/{yyyy}/{mm}/{lot}）
+/// 末尾は {lot}（ロットフォルダ）とし、その親フォルダを保存先フォルダとしてロット一覧・世代・重複の確認に使う
+pub struct DestTemplate {
+    folders: Vec<Vec<Part>>,        // {lot} より前のフォルダ階層
+    lot_name_pattern: Option<Regex>,
+    date_source: DestDateSource,
+}
+
+impl DestTemplate {
+    /// 設定からテンプレートを作成
+    pub fn from_settings(settings: &SettingsConfig) -> Result<Self, String> {
+        Self::parse(&settings.dest_path_template, &settings.lot_name_pattern, settings.dest_date_source)
+    }
+
+    /// テンプレートを解析（空の場合は既定値）
+    pub fn parse(template: &str, lot_name_pattern: &str, date_source: DestDateSource) -> Result<Self, String> {
+        let lot_name_pattern = match lot_name_pattern.trim() {
+            "" => None,
+            pattern => Some(Regex::new(pattern).map_err(|e| format!("ロット名の正規表現が不正です: {}", e))?),
+        };
+
+        let template = match template.trim() {
+            "" => DEFAULT_DEST_PATH_TEMPLATE,
+            template => template,
+        };
+        let mut segments: Vec<&str> = template.split(['/', '\\']).filter(|segment| !segment.is_empty()).collect();
+        if segments.pop() != Some("{lot}") {
+            return Err(format!("保存先のテンプレートは /{{lot}} で終わる必要があります: {}", template));
+        }
+
+        let folders = segments
+            .into_iter()
+            .map(|segment| parse_segment(segment, lot_name_pattern.as_ref()))
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(Self {
+            folders,
+            lot_name_pattern,
+            date_source,
+        })
+    }
+
+    /// ロットの保存先フォルダ（コピー先ルートからのパス）
+    /// lot_modified: コピー元のロットフォルダの更新日時（日付の基準がフォルダの更新日時で、取得できない場合はバックアップ日時を使う）
+    pub fn folder_path(
+        &self,
+        context: &DestContext,
+        lot_name: &str,
+        backup_time: DateTime<Local>,
+        lot_modified: Option<SystemTime>,
+    ) -> String {
+        let date = match (self.date_source, lot_modified) {
+            (DestDateSource::FolderMtime, Some(modified)) => DateTime::<Local>::from(modified),
+            _ => backup_time,
+        };
+        let captures = self.lot_name_pattern.as_ref().and_then(|pattern| pattern.captures(lot_name));
+
+        let mut path = String::new();
+        for folder in &self.folders {
+            let name = render_folder(folder, context, date, captures.as_ref()).unwrap_or_else(|| {
+                log::warn!("ロット名が正規表現に一致しないため {} に保存します: {}", UNMATCHED_FOLDER_NAME, lot_name);
+                UNMATCHED_FOLDER_NAME.to_string()
+            });
+            path = join_storage_path(&path, &name);
+        }
+        path
+    }
+
+    /// NAS上にある保存先フォルダの一覧（日付・ロット名から決まる階層はNAS上のフォルダを一覧して探す）
+    /// 親の一覧で分かった更新日時が前回と同じフォルダは、前回の一覧を使う
+    /// base: 探す起点（コピー先ルートの場合は空、世代フォルダの場合はそのルート）、戻り値は起点からのパス
+    pub fn existing_folders(&self, context: &DestContext, backend: &dyn StorageBackend, base: &str) -> Vec<String> {
+        // (起点からのパス, フォルダの更新日時（分からない場合はNone）)
+        let mut folders: Vec<(String, Option<u64>)> = vec![(String::new(), None)];
+        for folder in &self.folders {
+            if !folder.iter().any(Part::is_dynamic) {
+                let name = render_folder(folder, context, Local::now(), None).unwrap_or_default();
+                folders = folders.iter().map(|(parent, _)| (join_storage_path(parent, &name), None)).collect();
+                continue;
+            }
+
+            let pattern = folder_regex(folder, context);
+            let mut children = Vec::new();
+            for (parent, modified) in &folders {
+                let parent_path = join_storage_path(base, parent);
+                match list_subfolders(backend, &parent_path, *modified) {
+                    Ok(subfolders) => children.extend(
+                        subfolders
+                            .into_iter()
+                            .filter(|(name, _)| {
+                                name == UNMATCHED_FOLDER_NAME
+                                    || pattern.as_ref().is_none_or(|pattern| pattern.is_match(name))
+                            })
+                            .map(|(name, modified)| (join_storage_path(parent, &name), modified)),
+                    ),
+                    Err(e) => log::warn!("保存先フォルダの一覧取得に失敗しました {} ({}): {}", parent_path, backend.display_name(), e),
+                }
+            }
+            folders = children;
+        }
+        folders.into_iter().map(|(path, _)| path).collect()
+    }
+}
+
+/// フォルダ直下のフォルダ（名前, 更新日時）の一覧
+/// modified: 親の一覧で分かったフォルダの更新日時（前回一覧した時と同じ場合はキャッシュを使う、Noneの場合は毎回一覧する）
+fn list_subfolders(backend: &dyn StorageBackend, path: &str, modified: Option<u64>) -> Result<Vec<(String, Option<u64>)>, String> {
+    let key = (backend.display_name(), path.to_string());
+    if let Some(modified) = modified {
+        if let Some(cached) = lock_listings().get(&key).filter(|cached| cached.modified == modified) {
+            return Ok(cached.subfolders.clone());
+        }
+    }
+
+    let subfolders: Vec<(String, Option<u64>)> = backend
+        .list(path)?
+        .into_iter()
+        .filter(|entry| entry.is_dir)
+        .map(|entry| (entry.name, entry.modified))
+        .collect();
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or_default();
+    match modified {
+        Some(modified) if modified + LISTING_SETTLE_SECS <= now => {
+            lock_listings().insert(key, CachedListing { modified, subfolders: subfolders.clone() });
+        }
+        _ => {
+            lock_listings().remove(&key);
+        }
+    }
+    Ok(subfolders)
+}
+
+/// テンプレートのフォルダ名1つ分を解析
+fn parse_segment(segment: &str, lot_name_pattern: Option<&Regex>) -> Result<Vec<Part>, String> {
+    let mut parts = Vec::new();
+    let mut rest = segment;
+    while let Some(start) = rest.find('{') {
+        if start > 0 {
+            parts.push(Part::Literal(rest[..start].to_string()));
+        }
+        let end = rest[start..]
+            .find('}')
+            .map(|end| start + end)
+            .ok_or_else(|| format!("保存先のテンプレートの {{ が閉じられていません: {}", segment))?;
+        let token = &rest[start + 1..end];
+        parts.push(match token {
+            "root" => Part::Root,
+            "category" => Part::Category,
+            "device" => Part::Device,
+            "yyyy" => Part::Year,
+            "mm" => Part::Month,
+            "dd" => Part::Day,
+            "lot" => return Err("{lot} は保存先のテンプレートの末尾にのみ指定できます".to_string()),
+            group if has_group(lot_name_pattern, group) => Part::Capture(group.to_string()),
+            _ => return Err(format!("保存先のテンプレートに不明なトークンがあります: {{{}}}", token)),
+        });
+        rest = &rest[end + 1..];
+    }
+    if !rest.is_empty() {
+        parts.push(Part::Literal(rest.to_string()));
+    }
+    Ok(parts)
+}
+
+/// ロット名の正規表現に名前・番号のグループがあるか
+fn has_group(lot_name_pattern: Option<&Regex>, group: &str) -> bool {
+    let Some(pattern) = lot_name_pattern else {
+        return false;
+    };
+    match group.parse::<usize>() {
+        Ok(index) => index > 0 && index < pattern.captures_len(),
+        Err(_) => pattern.capture_names().any(|name| name == Some(group)),
+    }
+}
+
+/// フォルダ名を作成（ロット名から取り出す値が無い場合はNone）
+fn render_folder(
+    folder: &[Part],
+    context: &DestContext,
+    date: DateTime<Local>,
+    captures: Option<&Captures>,
+) -> Option<String> {
+    let mut name = String::new();
+    for part in folder {
+        match part {
+            Part::Literal(text) => name.push_str(text),
+            Part::Root => name.push_str(context.root),
+            Part::Category => name.push_str(context.category),
+            Part::Device => name.push_str(context.device),
+            Part::Year => name.push_str(&format!("{:04}", date.year())),
+            Part::Month => name.push_str(&format!("{:02}", date.month())),
+            Part::Day => name.push_str(&format!("{:02}", date.day())),
+            Part::Capture(group) => {
+                let value = captures.and_then(|captures| match group.parse::<usize>() {
+                    Ok(index) => captures.get(index),
+                    Err(_) => captures.name(group),
+                })?;
+                if value.as_str().is_empty() {
+                    return None;
+                }
+                // 取り出した値でフォルダの階層が変わらないようにする
+                name.push_str(&value.as_str().replace(['/', '\\'], "_"));
+            }
+        }
+    }
+    Some(name)
+}
+
+/// NAS上のフォルダ名がテンプレートのフォルダ名に一致するかを判定する正規表現
+fn folder_regex(folder: &[Part], context: &DestContext) -> Option<Regex> {
+    let pattern: String = folder
+        .iter()
+        .map(|part| match part {
+            Part::Literal(text) => regex::escape(text),
+            Part::Root => regex::escape(context.root),
+            Part::Category => regex::escape(context.category),
+            Part::Device => regex::escape(context.device),
+            Part::Year => r"\d{4}".to_string(),
+            Part::Month | Part::Day => r"\d{2}".to_string(),
+            Part::Capture(_) => ".+".to_string(),
+        })
+        .collect();
+    Regex::new(&format!("^{}$", pattern)).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::storage_backend::LocalBackend;
+
+    const CONTEXT: DestContext = DestContext {
+        root: "data/surface",
+        category: "表面画像",
+        device: "AOI-1",
+    };
+
+    fn backup_time() -> DateTime<Local> {
+        Local.with_ymd_and_hms(2026, 3, 7, 12, 0, 0).unwrap()
+    }
+
+    fn render(template: &str, lot_name_pattern: &str, lot_name: &str) -> String {
+        DestTemplate::parse(template, lot_name_pattern, DestDateSource::BackupDate)
+            .unwrap()
+            .folder_path(&CONTEXT, lot_name, backup_time(), None)
+    }
+
+    #[test]
+    fn parse_rejects_invalid_templates() {
+        let cases = [
+            ("{root}/{device}", ""),                    // {lot} で終わらない
+            ("{root}/{lot}/{device}/{lot}", ""),        // {lot} が途中にある
+            ("{root}/{unknown}/{lot}", ""),             // 不明なトークン
+            ("{root}/{line}/{lot}", r"^(?P<kind>\w)"),  // 正規表現に無いグループ名
+            ("{root}/{2}/{lot}", r"^(\w)"),             // 正規表現に無いグループ番号
+            ("{root}/{0}/{lot}", r"^(\w)"),             // 0番（一致全体）は使えない
+            ("{root}/{device/{lot}", ""),               // { が閉じられていない
+            ("{root}/{lot}", "("),                      // 正規表現が不正
+        ];
+        for (template, pattern) in cases {
+            assert!(DestTemplate::parse(template, pattern, DestDateSource::BackupDate).is_err(), "{}", template);
+        }
+    }
+
+    #[test]
+    fn default_template_matches_root_and_device() {
+        // 既定値は従来の保存先（NAS内保存パス/検査機器名/ロット名）と同じ
+        let expected = join_storage_path(CONTEXT.root, CONTEXT.device);
+        assert_eq!(render("", "", "LOT001"), expected);
+        assert_eq!(render("  ", "", "LOT001"), expected);
+        assert_eq!(render(DEFAULT_DEST_PATH_TEMPLATE, "", "LOT001"), expected);
+        assert_eq!(render("{root}\\{device}\\{lot}", "", "LOT001"), expected);
+    }
+
+    #[test]
+    fn render_formats_dates_and_tokens() {
+        assert_eq!(
+            render("{root}/{category}/{device}/{yyyy}/{mm}/{dd}/{lot}", "", "LOT001"),
+            "data/surface/表面画像/AOI-1/2026/03/07"
+        );
+        assert_eq!(render("backup_{yyyy}{mm}/{lot}", "", "LOT001"), "backup_202603");
+    }
+
+    #[test]
+    fn render_uses_folder_mtime_when_configured() {
+        let template = DestTemplate::parse("{yyyy}-{mm}-{dd}/{lot}", "", DestDateSource::FolderMtime).unwrap();
+        let modified = SystemTime::from(Local.with_ymd_and_hms(2025, 12, 31, 23, 0, 0).unwrap());
+        assert_eq!(template.folder_path(&CONTEXT, "LOT001", backup_time(), Some(modified)), "2025-12-31");
+        // 更新日時を取得できない場合はバックアップ日
+        assert_eq!(template.folder_path(&CONTEXT, "LOT001", backup_time(), None), "2026-03-07");
+    }
+
+    #[test]
+    fn render_captures_and_sanitises_lot_name_values() {
+        let pattern = r"^(?P<line>[A-Z]+)-(\d{4})";
+        assert_eq!(render("{root}/{line}/{2}/{lot}", pattern, "AB-2026-001"), "data/surface/AB/2026");
+        // 取り出した値の区切り文字は _ にする
+        assert_eq!(render("{root}/{kind}/{lot}", r"^(?P<kind>[^_]+)_", "a/b\\c_001"), "data/surface/a_b_c");
+        // 一致しない場合は _unmatched
+        assert_eq!(render("{root}/{line}/{lot}", pattern, "lot001"), "data/surface/_unmatched");
+        // 空のグループも _unmatched
+        assert_eq!(render("{root}/{kind}/{lot}", r"^(?P<kind>\d*)", "lot001"), "data/surface/_unmatched");
+    }
+
+    /// テストごとの作業フォルダ
+    fn work_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("dest_template_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn existing_folders_lists_matching_date_folders() {
+        let dir = work_dir("existing");
+        let backend = LocalBackend::new(&dir.to_string_lossy());
+        for path in ["data/surface/AOI-1/2026/01", "data/surface/AOI-1/2026/02", "data/surface/AOI-1/misc/01", "data/surface/AOI-1/2026/x"] {
+            backend.create_dir_all(path).unwrap();
+        }
+        let template = DestTemplate::parse("{root}/{device}/{yyyy}/{mm}/{lot}", "", DestDateSource::BackupDate).unwrap();
+
+        let mut folders = template.existing_folders(&CONTEXT, &backend, "");
+        folders.sort();
+        assert_eq!(folders, vec!["data/surface/AOI-1/2026/01", "data/surface/AOI-1/2026/02"]);
+
+        // 追加したフォルダは次の一覧に含まれる
+        backend.create_dir_all("data/surface/AOI-1/2026/03").unwrap();
+        assert_eq!(template.existing_folders(&CONTEXT, &backend, "").len(), 3);
+        backend.delete("").unwrap();
+    }
+}
@@ -0,0 +1,208 @@
+use std::collections::HashSet;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+use crate::address::{resolve_socket_addrs, unc_host};
+
+/// 名前解決時に使用するポート（IPアドレスの比較のみに使うため値は任意）
+const RESOLVE_PORT: u16 = 445;
+
+/// ドライブ設定がUNCパス（\\host\share）かどうか
+pub fn is_unc_path(drive: &str) -> bool {
+    drive.starts_with("\\\\") || drive.starts_with("//")
+}
+
+/// ドライブ設定がドライブレターのみ（P, P:, P:\\）の場合はそのドライブレター
+pub fn drive_letter(drive: &str) -> Option<char> {
+    let trimmed = drive.trim_end_matches('\\').trim_end_matches(':');
+    let mut chars = trimmed.chars();
+    match (chars.next(), chars.next()) {
+        (Some(letter), None) if letter.is_ascii_alphabetic() => Some(letter.to_ascii_uppercase()),
+        _ => None,
+    }
+}
+
+/// NASのコピー先ルートのパスを取得
+/// ドライブレターは "P:\\" に変換し、UNCパス・マウントポイント・ローカルディレクトリはそのまま使う
+pub fn dest_root_path(drive: &str) -> PathBuf {
+    if let Some(letter) = drive_letter(drive) {
+        return PathBuf::from(format!("{}:\\", letter));
+    }
+
+    if is_unc_path(drive) {
+        // Win32 APIにUNCパスを渡す場合は末尾の区切り文字が必要
+        return PathBuf::from(format!("{}\\", drive.trim_end_matches(['\\', '/'])));
+    }
+
+    PathBuf::from(drive)
+}
+
+/// パスに相対パスを結合（\\ と / のどちらの区切り文字も受け付ける）
+pub fn join_relative(root: &Path, relative_path: &str) -> PathBuf {
+    relative_path
+        .split(['\\', '/'])
+        .filter(|component| !component.is_empty())
+        .fold(root.to_path_buf(), |path, component| path.join(component))
+}
+
+/// UNCパスからホスト名を取り出す
+pub fn parse_unc_host(unc_path: &str) -> Option<String> {
+    let rest = unc_path
+        .strip_prefix("\\\\")
+        .or_else(|| unc_path.strip_prefix("//"))?;
+    let host = rest.split(['\\', '/']).next()?;
+    if host.is_empty() {
+        None
+    } else {
+        Some(host.to_string())
+    }
+}
+
+/// コピー先ルートの実際の接続先ホストを取得
+/// 戻り値: Ok(Some(host)) ネットワークドライブ・ネットワークマウント / Ok(None) ローカルディスク
+pub fn resolve_remote_host(drive: &str) -> Result<Option<String>, String> {
+    // UNCパスが直接設定されている場合はそのホスト
+    if is_unc_path(drive) {
+        return parse_unc_host(drive)
+            .map(Some)
+            .ok_or(format!("UNCパスが不正です: {}", drive));
+    }
+
+    // ドライブレター、またはドライブレターで始まるパスの割り当て先を確認
+    #[cfg(windows)]
+    {
+        let letter = drive
+            .chars()
+            .next()
+            .filter(|c| c.is_ascii_alphabetic() && drive[1..].starts_with(':'))
+            .ok_or(format!("ドライブレターを特定できません: {}", drive))?;
+        get_drive_remote_name_windows(letter).map(|remote| remote.as_deref().and_then(parse_unc_host))
+    }
+
+    // マウントポイントのマウント元を確認
+    #[cfg(target_os = "linux")]
+    {
+        get_mount_source_linux(&dest_root_path(drive))
+    }
+
+    #[cfg(not(any(windows, target_os = "linux")))]
+    {
+        Err(format!("コピー先の接続先の確認はこのOSでは未対応です: {}", drive))
+    }
+}
+
+/// ドライブの接続先が設定されたNASのアドレスと一致するか確認
+/// 一致しない場合・ローカルディスクの場合はエラーメッセージを返す
+/// 接続先を確認できない環境では一致しているものとして扱う
+pub fn verify_drive_mapping(drive: &str, nas_ip: &str) -> Result<(), String> {
+    let remote_host = match resolve_remote_host(drive) {
+        Ok(remote_host) => remote_host,
+        Err(e) => {
+            log::debug!("Skip drive mapping verification: {}", e);
+            return Ok(());
+        }
+    };
+
+    match remote_host {
+        // ドライブレターのみの設定はネットワークドライブを前提とする
+        None if drive_letter(drive).is_some() => Err(format!(
+            "ドライブ {} はネットワークドライブではありません（ローカルディスク）",
+            drive
+        )),
+        // マウントポイント・ローカルディレクトリが明示的に設定されている場合はローカルディスクも許可
+        None => Ok(()),
+        Some(remote_host) if hosts_match(&remote_host, nas_ip) => Ok(()),
+        Some(remote_host) => Err(format!(
+            "ドライブ {} の接続先 ({}) が設定されたNASのアドレス ({}) と一致しません",
+            drive, remote_host, nas_ip
+        )),
+    }
+}
+
+/// 2つのホストが同じ機器を指しているか判定
+/// 文字列として一致しない場合は名前解決したIPアドレスで比較する
+fn hosts_match(remote_host: &str, nas_ip: &str) -> bool {
+    let configured_host = unc_host(nas_ip);
+    if remote_host.eq_ignore_ascii_case(&configured_host) {
+        return true;
+    }
+
+    let resolve = |host: &str| -> HashSet<IpAddr> {
+        resolve_socket_addrs(host, RESOLVE_PORT)
+            .map(|addrs| addrs.into_iter().map(|addr| addr.ip()).collect())
+            .unwrap_or_default()
+    };
+
+    let remote_addrs = resolve(remote_host);
+    let configured_addrs = resolve(nas_ip);
+    !remote_addrs.is_disjoint(&configured_addrs)
+}
+
+/// Windows専用: ドライブレターに割り当てられた接続先（\\host\share）を取得
+/// ローカルディスクの場合はNone
+#[cfg(windows)]
+fn get_drive_remote_name_windows(letter: char) -> Result<Option<String>, String> {
+    use windows::core::{PCWSTR, PWSTR};
+    use windows::Win32::Foundation::{ERROR_NOT_CONNECTED, NO_ERROR};
+    use windows::Win32::NetworkManagement::WNet::WNetGetConnectionW;
+
+    let local_name = format!("{}:", letter);
+    let local_name_w: Vec<u16> = local_name.encode_utf16().chain(std::iter::once(0)).collect();
+
+    let mut buffer = vec![0u16; 1024];
+    let mut length = buffer.len() as u32;
+
+    let result = unsafe {
+        WNetGetConnectionW(
+            PCWSTR(local_name_w.as_ptr()),
+            PWSTR(buffer.as_mut_ptr()),
+            &mut length,
+        )
+    };
+
+    if result == NO_ERROR {
+        let end = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+        Ok(Some(String::from_utf16_lossy(&buffer[..end])))
+    } else if result == ERROR_NOT_CONNECTED {
+        Ok(None)
+    } else {
+        Err(format!(
+            "WNetGetConnectionW failed for drive {}: Error code {:?}",
+            local_name, result
+        ))
+    }
+}
+
+/// Linux専用: パスを含むマウントポイントのマウント元ホストを/proc/mountsから取得
+/// CIFS(//host/share)・NFS(host:/export)以外はローカルディスクとしてNone
+#[cfg(target_os = "linux")]
+fn get_mount_source_linux(path: &Path) -> Result<Option<String>, String> {
+    let path = path.canonicalize()
+        .map_err(|e| format!("コピー先のパスを解決できません {}: {}", path.display(), e))?;
+
+    let mounts = std::fs::read_to_string("/proc/mounts")
+        .map_err(|e| format!("Failed to read /proc/mounts: {}", e))?;
+
+    // パスを含むマウントポイントのうち最も深いもの
+    let (source, fs_type) = mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let source = fields.next()?;
+            let mount_point = fields.next()?.replace("\\040", " ");
+            let fs_type = fields.next()?;
+            Some((source, PathBuf::from(mount_point), fs_type))
+        })
+        .filter(|(_, mount_point, _)| path.starts_with(mount_point))
+        .max_by_key(|(_, mount_point, _)| mount_point.as_os_str().len())
+        .map(|(source, _, fs_type)| (source, fs_type))
+        .ok_or(format!("マウントポイントが見つかりません: {}", path.display()))?;
+
+    match fs_type {
+        "cifs" | "smb3" | "smbfs" => Ok(parse_unc_host(source)),
+        "nfs" | "nfs4" => Ok(source
+            .split_once(":/")
+            .map(|(host, _)| host.trim_start_matches('[').trim_end_matches(']').to_string())),
+        _ => Ok(None),
+    }
+}
@@ -0,0 +1,368 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::backup_executor::BackupExecutor;
+use crate::conflict_policy::sha256;
+use crate::lot_versions::{new_version_name, preserve_lot, prune_versions};
+use crate::nas_inventory::NasInventory;
+use crate::storage_backend::{join_storage_path, open_backend, DestPathGroups, StorageBackend};
+use crate::types::{ConsolidationResult, DuplicateLot, DuplicateLotCopy, NasConfig};
+
+/// 複数のNASにある同じロットを検出・統合する構造体
+/// 統合ではファイル数が最も多いNASのロットを残し、不足しているファイルを他のNASからコピーしてから他のNASのロットを世代フォルダに移す
+/// 両方にあるファイルの内容が異なる場合は、どちらが正しいか判断できないため統合しない
+pub struct DuplicateLots {
+    nas_configs: Vec<NasConfig>,
+    backends: HashMap<u32, Box<dyn StorageBackend>>,
+    nas_inventory: NasInventory,
+}
+
+impl DuplicateLots {
+    /// NASに接続してDuplicateLotsインスタンスを作成（接続できないNASは対象外）
+    pub fn connect(nas_configs: Vec<NasConfig>, nas_inventory: NasInventory) -> Result<Self, String> {
+        let mut connected = Vec::new();
+        let mut backends = HashMap::new();
+        for nas_config in nas_configs {
+            match open_backend(&nas_config) {
+                Ok(backend) => {
+                    backends.insert(nas_config.id, backend);
+                    connected.push(nas_config);
+                }
+                Err(e) => log::warn!("NAS {} に接続できません: {}", nas_config.name, e),
+            }
+        }
+
+        if connected.len() < 2 {
+            return Err("重複を確認するには2台以上のNASに接続できる必要があります".to_string());
+        }
+
+        Ok(Self {
+            nas_configs: connected,
+            backends,
+            nas_inventory,
+        })
+    }
+
+    /// 検査機器・カテゴリごとに、2台以上のNASにあるロットを検出
+    /// 保存先のテンプレートの日付などで保存先フォルダがNASごとに異なっても、同じまとまりの同名のロットは重複とみなす
+    /// NASごとのロット一覧のキャッシュを更新してから比較する
+    /// dest_path_groups: NAS上の保存先フォルダを検査機器・カテゴリごとにまとめて取得する関数
+    pub fn find(&self, dest_path_groups: &DestPathGroups<'_>) -> Result<Vec<DuplicateLot>, String> {
+        // 保存先フォルダ → まとまりの位置（いずれかのNASにある保存先フォルダを比較する）
+        let mut folder_groups: Vec<(String, usize)> = Vec::new();
+        for nas_config in &self.nas_configs {
+            for (group, folder_paths) in dest_path_groups(self.backends[&nas_config.id].as_ref())?.into_iter().enumerate() {
+                for folder_path in folder_paths {
+                    if !folder_groups.iter().any(|(path, _)| path == &folder_path) {
+                        folder_groups.push((folder_path, group));
+                    }
+                }
+            }
+        }
+
+        // (まとまりの位置, ロット名) → NAS・保存先フォルダごとのファイル数（NASの優先順）
+        let mut lots: BTreeMap<(usize, String), Vec<DuplicateLotCopy>> = BTreeMap::new();
+        for nas_config in &self.nas_configs {
+            let backend = self.backends[&nas_config.id].as_ref();
+            for (folder_path, group) in &folder_groups {
+                for (lot_name, file_count) in self.nas_inventory.refresh(nas_config.id, backend, folder_path) {
+                    lots.entry((*group, lot_name)).or_default().push(DuplicateLotCopy {
+                        nas_id: nas_config.id,
+                        nas_name: nas_config.name.clone(),
+                        folder_path: folder_path.clone(),
+                        file_count,
+                    });
+                }
+            }
+        }
+
+        let mut duplicates = Vec::new();
+        for ((_, lot_name), copies) in lots {
+            if copies.len() < 2 {
+                continue;
+            }
+            // ファイル数が同じ場合は優先順が先のNASを残す
+            let Some(keep) = copies.iter().rev().max_by_key(|copy| copy.file_count) else {
+                continue;
+            };
+            duplicates.push(DuplicateLot {
+                folder_path: keep.folder_path.clone(),
+                lot_name,
+                keep_nas_id: keep.nas_id,
+                copies,
+            });
+        }
+
+        self.nas_inventory.save();
+        Ok(duplicates)
+    }
+
+    /// 重複ロットを1台のNASにまとめる
+    /// 残すNASに無いファイルを他のNASからコピーし、コピーがすべて成功したロットを世代フォルダに移す
+    /// max_versions: 世代フォルダに残す世代数（移したロットは最新の世代になる）
+    pub fn consolidate(&self, duplicates: &[DuplicateLot], required_free_space: u64, max_versions: u32) -> ConsolidationResult {
+        let mut result = ConsolidationResult::default();
+
+        for duplicate in duplicates {
+            let lot_label = join_storage_path(&duplicate.folder_path, &duplicate.lot_name);
+            match self.consolidate_lot(duplicate, required_free_space, max_versions, &mut result) {
+                Ok(()) => {
+                    log::info!("重複ロットを統合しました: {}", lot_label);
+                    result.lots_consolidated += 1;
+                }
+                Err(e) => {
+                    log::error!("重複ロットの統合に失敗しました {}: {}", lot_label, e);
+                    result.errors.push(format!("{}: {}", lot_label, e));
+                }
+            }
+        }
+
+        self.nas_inventory.save();
+        result
+    }
+
+    /// 重複ロット1つを統合（いずれかのNASで失敗した場合はエラー、成功したNASのロットは世代フォルダに移し済み）
+    fn consolidate_lot(
+        &self,
+        duplicate: &DuplicateLot,
+        required_free_space: u64,
+        max_versions: u32,
+        result: &mut ConsolidationResult,
+    ) -> Result<(), String> {
+        let keep_backend = self.backend(duplicate.keep_nas_id)?;
+        let keep_lot_path = join_storage_path(&duplicate.folder_path, &duplicate.lot_name);
+        let mut keep_files: HashMap<String, u64> = keep_backend.list_files(&keep_lot_path)?.into_iter().collect();
+
+        let is_keep = |copy: &DuplicateLotCopy| copy.nas_id == duplicate.keep_nas_id && copy.folder_path == duplicate.folder_path;
+        let version = new_version_name();
+        let mut errors = Vec::new();
+        for copy in duplicate.copies.iter().filter(|copy| !is_keep(copy)) {
+            let lot_path = join_storage_path(&copy.folder_path, &duplicate.lot_name);
+            let merged = self.backend(copy.nas_id).and_then(|backend| {
+                Self::merge_missing_files(backend, &lot_path, keep_backend, &keep_lot_path, &mut keep_files, required_free_space)
+            });
+            let removed = merged.and_then(|merged| {
+                result.files_merged += merged;
+                // 削除せず世代フォルダに移す（統合後も復元できるようにする）
+                let backend = self.backends[&copy.nas_id].as_ref();
+                preserve_lot(backend, &copy.folder_path, &duplicate.lot_name, &version)?;
+                if let Err(e) = prune_versions(backend, &copy.folder_path, &duplicate.lot_name, max_versions) {
+                    log::warn!("古い世代を削除できません {} ({}): {}", lot_path, copy.nas_name, e);
+                }
+                Ok(())
+            });
+
+            match removed {
+                Ok(()) => {
+                    log::info!("重複ロットを世代フォルダに移しました: {} ({}, {})", lot_path, copy.nas_name, version);
+                    result.copies_removed += 1;
+                    self.nas_inventory.invalidate_lot(copy.nas_id, &copy.folder_path, &duplicate.lot_name);
+                }
+                Err(e) => errors.push(format!("{}: {}", copy.nas_name, e)),
+            }
+        }
+
+        // 不足ファイルをコピーしたため、次回の更新時に数え直す
+        self.nas_inventory.invalidate_lot(duplicate.keep_nas_id, &duplicate.folder_path, &duplicate.lot_name);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join(", "))
+        }
+    }
+
+    /// 残すロットに無いファイルを他のロットからコピー
+    /// 両方にあるファイルはサイズ・内容（SHA-256）を比べ、異なるファイルがある場合は何もコピーせずにエラーにする
+    /// 戻り値: コピーしたファイル数
+    fn merge_missing_files(
+        from: &dyn StorageBackend,
+        from_lot_path: &str,
+        to: &dyn StorageBackend,
+        to_lot_path: &str,
+        keep_files: &mut HashMap<String, u64>,
+        required_free_space: u64,
+    ) -> Result<u32, String> {
+        let mut missing: Vec<(String, u64)> = Vec::new();
+        let mut different: Vec<String> = Vec::new();
+        for (relative_path, size) in from.list_files(from_lot_path)? {
+            match keep_files.get(&relative_path) {
+                None => missing.push((relative_path, size)),
+                Some(&keep_size) if keep_size != size => different.push(relative_path),
+                Some(_) => {
+                    let from_hash = sha256(from.open(&join_storage_path(from_lot_path, &relative_path))?)
+                        .map_err(|e| format!("ファイル読み込みエラー {}/{}: {}", from_lot_path, relative_path, e))?;
+                    let to_hash = sha256(to.open(&join_storage_path(to_lot_path, &relative_path))?)
+                        .map_err(|e| format!("ファイル読み込みエラー {}/{}: {}", to_lot_path, relative_path, e))?;
+                    if from_hash != to_hash {
+                        different.push(relative_path);
+                    }
+                }
+            }
+        }
+
+        if !different.is_empty() {
+            different.sort();
+            return Err(format!(
+                "残すロットと内容が異なるファイルがあるため統合を中止しました ({} ファイル): {}",
+                different.len(),
+                different.join(", ")
+            ));
+        }
+        if missing.is_empty() {
+            return Ok(0);
+        }
+
+        // コピー後も必要な空き容量が残るか確認
+        let missing_size: u64 = missing.iter().map(|(_, size)| size).sum();
+        let free = to.free_space()?.free;
+        if free < missing_size.saturating_add(required_free_space) {
+            return Err(format!(
+                "{} の空き容量が不足しているため不足ファイルをコピーできません (必要: {} bytes, 空き: {} bytes)",
+                to.display_name(),
+                missing_size,
+                free
+            ));
+        }
+
+        let mut merged = 0;
+        for (relative_path, size) in missing {
+            let from_path = join_storage_path(from_lot_path, &relative_path);
+            let to_path = join_storage_path(to_lot_path, &relative_path);
+            if let Some((parent, _)) = to_path.rsplit_once('/') {
+                to.create_dir_all(parent)?;
+            }
+            let mut reader = from.open(&from_path)?;
+            to.write(&to_path, &mut reader, size)?;
+
+            // コピー元の日時・属性を引き継ぐ
+            if let Some(attributes) = from.attributes(&from_path)? {
+                BackupExecutor::preserve_attributes(to, &to_path, &attributes);
+            }
+
+            log::debug!("    不足ファイルをコピー: {} -> {}", from_path, to_path);
+            keep_files.insert(relative_path, size);
+            merged += 1;
+        }
+        Ok(merged)
+    }
+
+    fn backend(&self, nas_id: u32) -> Result<&dyn StorageBackend, String> {
+        self.backends
+            .get(&nas_id)
+            .map(|backend| backend.as_ref())
+            .ok_or_else(|| format!("NAS(ID: {})に接続されていません", nas_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use super::*;
+    use crate::lot_versions::VERSIONS_DIR_NAME;
+    use crate::storage_backend::LocalBackend;
+
+    /// テストごとの作業フォルダ
+    fn work_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("duplicate_lots_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write(path: &Path, data: &[u8]) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, data).unwrap();
+    }
+
+    /// 作業フォルダ内の nas1・nas2 をコピー先にしたDuplicateLots
+    fn duplicate_lots(dir: &Path) -> DuplicateLots {
+        let mut backends: HashMap<u32, Box<dyn StorageBackend>> = HashMap::new();
+        for id in 1..=2 {
+            let root = dir.join(format!("nas{}", id));
+            fs::create_dir_all(&root).unwrap();
+            backends.insert(id, Box::new(LocalBackend::new(&root.to_string_lossy())));
+        }
+        DuplicateLots {
+            nas_configs: Vec::new(),
+            backends,
+            nas_inventory: NasInventory::empty(),
+        }
+    }
+
+    /// nas1のロットを残す line/LOT1 の重複
+    fn duplicate() -> DuplicateLot {
+        let copy = |nas_id: u32, file_count: u32| DuplicateLotCopy {
+            nas_id,
+            nas_name: format!("NAS{}", nas_id),
+            folder_path: "line".to_string(),
+            file_count,
+        };
+        DuplicateLot {
+            folder_path: "line".to_string(),
+            lot_name: "LOT1".to_string(),
+            copies: vec![copy(1, 1), copy(2, 2)],
+            keep_nas_id: 1,
+        }
+    }
+
+    #[test]
+    fn missing_files_are_merged_into_kept_lot() {
+        let dir = work_dir("merge");
+        write(&dir.join("nas1/line/LOT1/a.jpg"), b"same");
+        write(&dir.join("nas2/line/LOT1/a.jpg"), b"same");
+        write(&dir.join("nas2/line/LOT1/img/b.jpg"), b"only nas2");
+        let lots = duplicate_lots(&dir);
+
+        let mut result = ConsolidationResult::default();
+        lots.consolidate_lot(&duplicate(), 0, 5, &mut result).unwrap();
+
+        assert_eq!(result.files_merged, 1);
+        assert_eq!(result.copies_removed, 1);
+        assert_eq!(fs::read(dir.join("nas1/line/LOT1/a.jpg")).unwrap(), b"same");
+        assert_eq!(fs::read(dir.join("nas1/line/LOT1/img/b.jpg")).unwrap(), b"only nas2");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn different_content_aborts_merge() {
+        let dir = work_dir("different");
+        write(&dir.join("nas1/line/LOT1/a.jpg"), b"aaaa");
+        write(&dir.join("nas2/line/LOT1/a.jpg"), b"bbbb");
+        write(&dir.join("nas2/line/LOT1/b.jpg"), b"only nas2");
+        let lots = duplicate_lots(&dir);
+
+        let mut result = ConsolidationResult::default();
+        let error = lots.consolidate_lot(&duplicate(), 0, 5, &mut result).unwrap_err();
+
+        // 同じサイズでも内容が異なるファイルがあれば、不足ファイルもコピーせずロットも移さない
+        assert!(error.contains("a.jpg"), "{}", error);
+        assert_eq!(result.files_merged, 0);
+        assert_eq!(result.copies_removed, 0);
+        assert!(!dir.join("nas1/line/LOT1/b.jpg").exists());
+        assert_eq!(fs::read(dir.join("nas2/line/LOT1/a.jpg")).unwrap(), b"bbbb");
+        assert!(dir.join("nas2/line/LOT1/b.jpg").exists());
+        assert!(!dir.join("nas2").join(VERSIONS_DIR_NAME).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn removed_lot_is_kept_in_versions() {
+        let dir = work_dir("versions");
+        write(&dir.join("nas1/line/LOT1/a.jpg"), b"same");
+        write(&dir.join("nas2/line/LOT1/a.jpg"), b"same");
+        let lots = duplicate_lots(&dir);
+
+        let mut result = ConsolidationResult::default();
+        lots.consolidate_lot(&duplicate(), 0, 5, &mut result).unwrap();
+
+        // 残さないロットは削除せず _versions/保存先フォルダ/ロット名/世代名 に移す
+        assert!(!dir.join("nas2/line/LOT1").exists());
+        let versions_path = dir.join("nas2").join(VERSIONS_DIR_NAME).join("line/LOT1");
+        let versions: Vec<PathBuf> = fs::read_dir(&versions_path).unwrap().map(|entry| entry.unwrap().path()).collect();
+        assert_eq!(versions.len(), 1);
+        assert_eq!(fs::read(versions[0].join("a.jpg")).unwrap(), b"same");
+        assert!(dir.join("nas1/line/LOT1/a.jpg").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
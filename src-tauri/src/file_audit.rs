@@ -0,0 +1,96 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::sync::{Arc, Mutex};
+
+use chrono::Local;
+
+use crate::config::get_data_file_path;
+use crate::types::FileAuditEntry;
+
+/// 記録の日時の形式
+pub const AUDIT_TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// ファイル単位のコピー記録のファイル名（config.jsonと同じディレクトリに日付ごとに保存）
+fn audit_file_name(date: &str) -> String {
+    format!("file_audit_{}.jsonl", date)
+}
+
+/// ファイル単位のコピー記録（どのファイルをコピー・上書き・スキップしたか）を追記する構造体
+/// 1行に1件のJSONで、バックアップを開始した日付のファイルに追記する
+#[derive(Clone)]
+pub struct FileAudit {
+    writer: Arc<Mutex<Option<BufWriter<File>>>>,    // ファイルを開けなかった場合はNone（記録しない）
+}
+
+impl FileAudit {
+    /// 今日の記録ファイルを追記用に開く
+    pub fn open() -> Self {
+        let opened = get_data_file_path(&audit_file_name(&Local::now().format("%Y-%m-%d").to_string()))
+            .and_then(|audit_path| {
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&audit_path)
+                    .map_err(|e| format!("Failed to open file audit at {:?}: {}", audit_path, e))
+            });
+
+        let writer = match opened {
+            Ok(file) => Some(BufWriter::new(file)),
+            Err(e) => {
+                log::error!("Failed to open file audit, file actions will not be recorded: {}", e);
+                None
+            }
+        };
+
+        Self {
+            writer: Arc::new(Mutex::new(writer)),
+        }
+    }
+
+    /// ファイル1つ分の処理内容を記録
+    pub fn record(&self, entry: &FileAuditEntry) {
+        let mut writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(writer) = writer.as_mut() else {
+            return;
+        };
+        let result = serde_json::to_writer(&mut *writer, entry)
+            .map_err(|e| e.to_string())
+            .and_then(|_| writer.write_all(b"\n").map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            log::error!("Failed to write file audit: {}", e);
+        }
+    }
+
+    /// 書き込みバッファをファイルに書き出す
+    pub fn flush(&self) {
+        if let Some(writer) = self.writer.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+            if let Err(e) = writer.flush() {
+                log::error!("Failed to flush file audit: {}", e);
+            }
+        }
+    }
+}
+
+/// 指定した日付（YYYY-MM-DD）の記録を読み込む（検査機器名を指定した場合はその検査機器のみ）
+pub fn read_file_audit(date: &str, device_name: Option<&str>) -> Result<Vec<FileAuditEntry>, String> {
+    let audit_path = get_data_file_path(&audit_file_name(date))?;
+    if !audit_path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let file = File::open(&audit_path)
+        .map_err(|e| format!("Failed to read file audit at {:?}: {}", audit_path, e))?;
+
+    let mut entries = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|e| format!("Failed to read file audit at {:?}: {}", audit_path, e))?;
+        // 書き込み途中で終了した行などは読み飛ばす
+        let Ok(entry) = serde_json::from_str::<FileAuditEntry>(&line) else {
+            continue;
+        };
+        if device_name.is_none_or(|name| entry.device_name == name) {
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
@@ -0,0 +1,113 @@
+use crate::source_access::SourceFile;
+use crate::types::FileFilter;
+
+/// 空行を除いたパターン
+pub fn active_patterns(patterns: &[String]) -> impl Iterator<Item = &str> {
+    patterns.iter().map(|pattern| pattern.trim()).filter(|pattern| !pattern.is_empty())
+}
+
+/// 検査機器とカテゴリの絞り込みをすべて満たすファイルか
+pub fn is_selected(filters: &[&FileFilter], file: &SourceFile) -> bool {
+    filters.iter().all(|filter| filter.matches(&file.relative_path, file.size))
+}
+
+/// globパターンに一致するか（大文字・小文字は区別しない）
+/// / を含まないパターンはファイル名と比べ、含むパターンはロットフォルダからの相対パス全体と比べる
+/// * は / 以外の任意の文字列、? は / 以外の1文字、** は / を含む任意の文字列（**/ は0個以上のフォルダ）
+pub fn glob_match(pattern: &str, relative_path: &str) -> bool {
+    let pattern: Vec<char> = pattern.replace('\\', "/").to_lowercase().chars().collect();
+    let relative_path = relative_path.replace('\\', "/").to_lowercase();
+    let target = if pattern.contains(&'/') {
+        relative_path.as_str()
+    } else {
+        relative_path.rsplit('/').next().unwrap_or_default()
+    };
+    let target: Vec<char> = target.chars().collect();
+    match_from(&pattern, &target)
+}
+
+fn match_from(pattern: &[char], text: &[char]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        ['*', '*', '/', rest @ ..] => {
+            match_from(rest, text)
+                || text
+                    .iter()
+                    .enumerate()
+                    .any(|(i, &c)| c == '/' && match_from(rest, &text[i + 1..]))
+        }
+        ['*', '*', rest @ ..] => (0..=text.len()).any(|i| match_from(rest, &text[i..])),
+        ['*', rest @ ..] => {
+            let segment_end = text.iter().position(|&c| c == '/').unwrap_or(text.len());
+            (0..=segment_end).any(|i| match_from(rest, &text[i..]))
+        }
+        ['?', rest @ ..] => text.first().is_some_and(|&c| c != '/') && match_from(rest, &text[1..]),
+        [c, rest @ ..] => text.first() == Some(c) && match_from(rest, &text[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_match_table() {
+        let cases = [
+            // (パターン, ロットフォルダからの相対パス, 一致するか)
+            ("*.jpg", "0001.jpg", true),
+            ("*.jpg", "img/0001.jpg", true),
+            ("*.jpg", "0001.png", false),
+            ("*.JPG", "img/0001.jpg", true),
+            ("*.jpg", "0001.JPG", true),
+            ("0001.*", "0001.jpg", true),
+            ("000?.jpg", "0001.jpg", true),
+            ("000?.jpg", "00001.jpg", false),
+            ("*", "img/0001.jpg", true),
+            ("thumbs/*", "thumbs/0001.jpg", true),
+            ("thumbs/*", "thumbs/small/0001.jpg", false),
+            ("thumbs/*", "img/thumbs/0001.jpg", false),
+            ("thumbs/**", "thumbs/small/0001.jpg", true),
+            ("**/thumbs/*", "thumbs/0001.jpg", true),
+            ("**/thumbs/*", "a/b/thumbs/0001.jpg", true),
+            ("**/thumbs/*", "a/thumbs2/0001.jpg", false),
+            ("img/*.jpg", "img/0001.jpg", true),
+            ("img/*.jpg", "img/sub/0001.jpg", false),
+            ("img/**/*.jpg", "img/0001.jpg", true),
+            ("img/**/*.jpg", "img/a/b/0001.jpg", true),
+            ("img\\*.jpg", "img/0001.jpg", true),
+            ("*.jpg", "img\\0001.jpg", true),
+            ("a?c", "a/c", false),
+            ("", "", true),
+            ("", "a", false),
+        ];
+        for (pattern, relative_path, expected) in cases {
+            assert_eq!(glob_match(pattern, relative_path), expected, "{} / {}", pattern, relative_path);
+        }
+    }
+
+    #[test]
+    fn filter_matches_table() {
+        let filter = |include: &[&str], exclude: &[&str], max_file_size_mb| FileFilter {
+            include: include.iter().map(|s| s.to_string()).collect(),
+            exclude: exclude.iter().map(|s| s.to_string()).collect(),
+            max_file_size_mb,
+        };
+        const MB: u64 = 1024 * 1024;
+        let cases = [
+            // (絞り込み, 相対パス, サイズ, 対象か)
+            (filter(&[], &[], 0), "0001.jpg", 10 * MB, true),
+            (filter(&["*.jpg"], &[], 0), "0001.jpg", 1, true),
+            (filter(&["*.jpg"], &[], 0), "0001.csv", 1, false),
+            (filter(&["*.jpg", "*.csv"], &[], 0), "0001.csv", 1, true),
+            (filter(&["", "  "], &[], 0), "0001.csv", 1, true),
+            (filter(&[" *.jpg "], &[], 0), "0001.jpg", 1, true),
+            (filter(&[], &["*.tmp"], 0), "0001.tmp", 1, false),
+            (filter(&["*.jpg"], &["thumbs/**"], 0), "thumbs/0001.jpg", 1, false),
+            (filter(&[], &[], 1), "0001.jpg", MB, true),
+            (filter(&[], &[], 1), "0001.jpg", MB + 1, false),
+        ];
+        for (filter, relative_path, size, expected) in cases {
+            assert_eq!(filter.matches(relative_path, size), expected, "{:?} / {} / {}", filter, relative_path, size);
+        }
+    }
+}
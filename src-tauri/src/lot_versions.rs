@@ -0,0 +1,263 @@
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use chrono::Local;
+
+use crate::storage_backend::{join_storage_path, StorageBackend};
+use crate::types::{LotVersion, VersionedLot};
+
+/// 世代フォルダのルート（コピー先ルート直下、ロット一覧に混ざらないよう保存先フォルダとは分ける）
+/// 世代フォルダ: _versions/NAS内保存パス/検査機器名/ロット名/世代名/ロット内の相対パス
+pub const VERSIONS_DIR_NAME: &str = "_versions";
+
+/// ロットの世代フォルダの親（ロットごと）
+fn lot_versions_path(folder_path: &str, lot_name: &str) -> String {
+    join_storage_path(&join_storage_path(VERSIONS_DIR_NAME, folder_path), lot_name)
+}
+
+/// 世代フォルダのパス
+fn version_path(folder_path: &str, lot_name: &str, version: &str) -> String {
+    join_storage_path(&lot_versions_path(folder_path, lot_name), version)
+}
+
+/// 最後に作成した世代名（同じミリ秒に作成した世代の名前が重ならないようにする）
+static LAST_VERSION_NAME: Mutex<String> = Mutex::new(String::new());
+
+/// 新しい世代名（現在日時のミリ秒まで、名前順に並べると古い順になる）
+/// 以前に作成した世代名と重ならないよう、同じミリ秒の場合は次のミリ秒まで待つ
+pub fn new_version_name() -> String {
+    let mut last = LAST_VERSION_NAME.lock().unwrap_or_else(|e| e.into_inner());
+    loop {
+        let name = Local::now().format("%Y%m%d_%H%M%S_%3f").to_string();
+        if name > *last {
+            last.clone_from(&name);
+            return name;
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+/// 画面から指定されたロット名・世代名が1階層の名前か（.. ・区切り文字・ドライブ指定を含まない）
+pub fn is_plain_name(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && !name.contains(['/', '\\', ':'])
+}
+
+/// 上書きする前のファイルを世代フォルダに移す
+pub fn preserve_file(
+    backend: &dyn StorageBackend,
+    folder_path: &str,
+    lot_name: &str,
+    version: &str,
+    relative_path: &str,
+) -> Result<(), String> {
+    let file_path = join_storage_path(&join_storage_path(folder_path, lot_name), relative_path);
+    let versioned_path = join_storage_path(&version_path(folder_path, lot_name, version), relative_path);
+    if let Some((parent, _)) = versioned_path.rsplit_once('/') {
+        backend.create_dir_all(parent)?;
+    }
+    backend.rename(&file_path, &versioned_path)
+}
+
+/// ロットフォルダごと世代フォルダに移す（重複ロットの統合で、残さないロットを削除する代わりに使う）
+pub fn preserve_lot(backend: &dyn StorageBackend, folder_path: &str, lot_name: &str, version: &str) -> Result<(), String> {
+    let versioned_path = version_path(folder_path, lot_name, version);
+    if let Some((parent, _)) = versioned_path.rsplit_once('/') {
+        backend.create_dir_all(parent)?;
+    }
+    backend.rename(&join_storage_path(folder_path, lot_name), &versioned_path)
+}
+
+/// 世代フォルダに移したファイルを元の場所に戻す（コピーに失敗した場合に使う）
+pub fn revert_file(
+    backend: &dyn StorageBackend,
+    folder_path: &str,
+    lot_name: &str,
+    version: &str,
+    relative_path: &str,
+) -> Result<(), String> {
+    let file_path = join_storage_path(&join_storage_path(folder_path, lot_name), relative_path);
+    let versioned_path = join_storage_path(&version_path(folder_path, lot_name, version), relative_path);
+    backend.rename(&versioned_path, &file_path)
+}
+
+/// ロットの世代名の一覧（古い順）
+fn version_names(backend: &dyn StorageBackend, folder_path: &str, lot_name: &str) -> Result<Vec<String>, String> {
+    let mut versions: Vec<String> = backend
+        .list(&lot_versions_path(folder_path, lot_name))?
+        .into_iter()
+        .filter(|entry| entry.is_dir)
+        .map(|entry| entry.name)
+        .collect();
+    versions.sort();
+    Ok(versions)
+}
+
+/// 古い世代を削除し、max_versions世代まで残す
+/// 戻り値: 削除した世代数
+pub fn prune_versions(
+    backend: &dyn StorageBackend,
+    folder_path: &str,
+    lot_name: &str,
+    max_versions: u32,
+) -> Result<usize, String> {
+    let versions = version_names(backend, folder_path, lot_name)?;
+    let excess = versions.len().saturating_sub(max_versions.max(1) as usize);
+    for version in &versions[..excess] {
+        backend.delete(&version_path(folder_path, lot_name, version))?;
+        log::info!("古い世代を削除しました: {}/{} ({})", folder_path, lot_name, version);
+    }
+    Ok(excess)
+}
+
+/// 保存先フォルダごとに、世代が残っているロットの一覧を取得
+pub fn list_versioned_lots(backend: &dyn StorageBackend, folder_paths: &[String]) -> Result<Vec<VersionedLot>, String> {
+    let mut versioned_lots = Vec::new();
+    for folder_path in folder_paths {
+        let lot_names = backend
+            .list(&join_storage_path(VERSIONS_DIR_NAME, folder_path))?
+            .into_iter()
+            .filter(|entry| entry.is_dir)
+            .map(|entry| entry.name);
+
+        for lot_name in lot_names {
+            let mut versions = Vec::new();
+            for version in version_names(backend, folder_path, &lot_name)?.into_iter().rev() {
+                let files = backend.list_files(&version_path(folder_path, &lot_name, &version))?;
+                versions.push(LotVersion {
+                    version,
+                    file_count: files.len() as u32,
+                    total_size_bytes: files.iter().map(|(_, size)| size).sum(),
+                });
+            }
+            if !versions.is_empty() {
+                versioned_lots.push(VersionedLot {
+                    folder_path: folder_path.clone(),
+                    lot_name,
+                    versions,
+                });
+            }
+        }
+    }
+    Ok(versioned_lots)
+}
+
+/// ロットを世代と同じ内容に戻す（世代に無い現在のファイルはロットから取り除く）
+/// 現在のファイルはすべて新しい世代に移すため、復元前の状態にも戻せる
+/// 戻り値: 書き戻したファイル数
+pub fn restore_version(
+    backend: &dyn StorageBackend,
+    folder_path: &str,
+    lot_name: &str,
+    version: &str,
+    max_versions: u32,
+) -> Result<u32, String> {
+    let source_path = version_path(folder_path, lot_name, version);
+    let files = backend.list_files(&source_path)?;
+    if files.is_empty() {
+        return Err(format!("世代が見つかりません: {}/{} ({})", folder_path, lot_name, version));
+    }
+
+    let lot_path = join_storage_path(folder_path, lot_name);
+    let current_files: HashSet<String> = backend
+        .list_files(&lot_path)?
+        .into_iter()
+        .map(|(relative_path, _)| relative_path)
+        .collect();
+
+    let backup_version = new_version_name();
+    if backup_version == version {
+        return Err("同じ日時の世代があるため、時間をおいてから復元してください".to_string());
+    }
+
+    // 現在のファイルを新しい世代に移す（世代に無いファイルも残さない）
+    for relative_path in &current_files {
+        preserve_file(backend, folder_path, lot_name, &backup_version, relative_path)?;
+    }
+    if !current_files.is_empty() {
+        log::info!("復元前のファイルを世代フォルダに残しました: {} ({}, {} ファイル)", lot_path, backup_version, current_files.len());
+    }
+
+    let mut restored = 0;
+    for (relative_path, size) in files {
+        let file_path = join_storage_path(&lot_path, &relative_path);
+        if let Some((parent, _)) = file_path.rsplit_once('/') {
+            backend.create_dir_all(parent)?;
+        }
+        let mut reader = backend.open(&join_storage_path(&source_path, &relative_path))?;
+        backend.write(&file_path, &mut reader, size)?;
+        restored += 1;
+    }
+    log::info!("世代を復元しました: {} ({}, {} ファイル)", lot_path, version, restored);
+
+    prune_versions(backend, folder_path, lot_name, max_versions)?;
+    Ok(restored)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::storage_backend::LocalBackend;
+
+    /// テストごとの作業フォルダ
+    fn work_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("lot_versions_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write(backend: &LocalBackend, path: &str, data: &[u8]) {
+        if let Some((parent, _)) = path.rsplit_once('/') {
+            backend.create_dir_all(parent).unwrap();
+        }
+        backend.write(path, &mut &data[..], data.len() as u64).unwrap();
+    }
+
+    #[test]
+    fn version_names_are_unique_and_increasing() {
+        let names: Vec<String> = (0..5).map(|_| new_version_name()).collect();
+        assert!(names.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn plain_names_reject_paths() {
+        assert!(is_plain_name("LOT001"));
+        assert!(is_plain_name("20260101_120000_123"));
+        for name in ["", ".", "..", "a/b", "a\\b", "C:", "/abs"] {
+            assert!(!is_plain_name(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn restore_removes_files_not_in_version() {
+        let dir = work_dir("restore");
+        let backend = LocalBackend::new(&dir.to_string_lossy());
+        write(&backend, "line/LOT001/a.jpg", b"old a");
+        write(&backend, "line/LOT001/img/b.jpg", b"old b");
+        let version = new_version_name();
+        preserve_file(&backend, "line", "LOT001", &version, "a.jpg").unwrap();
+        preserve_file(&backend, "line", "LOT001", &version, "img/b.jpg").unwrap();
+        write(&backend, "line/LOT001/a.jpg", b"new a");
+        write(&backend, "line/LOT001/c.jpg", b"new c");
+
+        assert_eq!(restore_version(&backend, "line", "LOT001", &version, 5).unwrap(), 2);
+
+        let mut files = backend.list_files("line/LOT001").unwrap();
+        files.sort();
+        assert_eq!(files, vec![("a.jpg".to_string(), 5), ("img/b.jpg".to_string(), 5)]);
+        assert_eq!(fs::read(dir.join("line/LOT001/a.jpg")).unwrap(), b"old a");
+
+        // 復元前のファイルは新しい世代に残る
+        let versions = version_names(&backend, "line", "LOT001").unwrap();
+        assert_eq!(versions.len(), 2);
+        let mut backup = backend.list_files(&version_path("line", "LOT001", &versions[1])).unwrap();
+        backup.sort();
+        assert_eq!(backup, vec![("a.jpg".to_string(), 5), ("c.jpg".to_string(), 5)]);
+        backend.delete("").unwrap();
+    }
+}
@@ -0,0 +1,438 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::backup_executor::LotScope;
+use crate::source_access::{open_source, join_source_path, SourceAccess};
+use crate::types::InspConfig;
+
+/// ロットフォルダの状態
+struct LotState {
+    file_count: u32,
+    total_size: u64,
+    last_change: Instant,   // 最後に変化を検知した時刻
+    is_pending: bool,       // 変化を検知してからまだバックアップしていないか
+}
+
+impl LotState {
+    /// 今変化を検知したロット
+    fn changed(file_count: u32, total_size: u64, changed_at: Instant) -> Self {
+        Self {
+            file_count,
+            total_size,
+            last_change: changed_at,
+            is_pending: true,
+        }
+    }
+}
+
+/// 監視中のコピー元フォルダ（検査機器のカテゴリごと）
+struct WatchedRoot {
+    watcher: Option<RecommendedWatcher>,            // 変更通知を使えない場合はNone（ポーリングで検知）
+    changes: Arc<Mutex<HashMap<String, Instant>>>,  // 変更通知で検知したロット → 最終変更時刻
+    watch_failed: Arc<AtomicBool>,                  // 変更通知でエラーが発生したか
+    lots: HashMap<String, LotState>,
+}
+
+impl WatchedRoot {
+    /// コピー元フォルダの監視を開始
+    /// 監視開始時点で存在するロットは定期バックアップに任せ、以降に変化したロットのみを対象にする
+    fn start(source: &dyn SourceAccess, path: &str) -> Self {
+        let changes = Arc::new(Mutex::new(HashMap::new()));
+        let watch_failed = Arc::new(AtomicBool::new(false));
+
+        // ローカル・SMBのコピー元は変更通知を試す（SMBサーバーによっては使えない）
+        let watcher = source.local_path(path).and_then(|local_root| {
+            start_watcher(&local_root, changes.clone(), watch_failed.clone())
+                .map_err(|e| {
+                    log::info!(
+                        "連続バックアップ: {} の変更通知を使用できないためポーリングで監視します: {}",
+                        local_root.display(),
+                        e
+                    )
+                })
+                .ok()
+        });
+
+        let mut root = Self {
+            watcher,
+            changes,
+            watch_failed,
+            lots: HashMap::new(),
+        };
+        if root.watcher.is_none() {
+            root.reset_baseline(source, path);
+        }
+        root
+    }
+
+    /// ポーリングの比較基準として現在のロットの状態を記録
+    fn reset_baseline(&mut self, source: &dyn SourceAccess, path: &str) {
+        let now = Instant::now();
+        self.lots = measure_lots(source, path)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(name, (file_count, total_size))| {
+                (name, LotState { file_count, total_size, last_change: now, is_pending: false })
+            })
+            .collect();
+    }
+
+    /// 前回からの変化を反映
+    fn update(&mut self, source: &dyn SourceAccess, path: &str) {
+        // 変更通知でエラーが発生した場合（SMBの切断など）はポーリングに切り替える
+        if self.watcher.is_some() && self.watch_failed.load(Ordering::Relaxed) {
+            log::info!(
+                "連続バックアップ: {} の変更通知を停止し、ポーリングで監視します",
+                source.display_path(path)
+            );
+            self.watcher = None;
+            self.reset_baseline(source, path);
+            return;
+        }
+
+        if self.watcher.is_some() {
+            let changes: Vec<(String, Instant)> = self
+                .changes
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .drain()
+                .collect();
+
+            for (lot_name, changed_at) in changes {
+                let state = self
+                    .lots
+                    .entry(lot_name)
+                    .or_insert_with(|| LotState::changed(0, 0, changed_at));
+                state.last_change = changed_at;
+                state.is_pending = true;
+            }
+            return;
+        }
+
+        // ポーリング: ファイル数・合計サイズが前回から変わったロットを変化ありとする
+        let current = match measure_lots(source, path) {
+            Ok(current) => current,
+            Err(e) => {
+                log::debug!("連続バックアップ: {} を読み込めません: {}", source.display_path(path), e);
+                return;
+            }
+        };
+
+        let now = Instant::now();
+        self.lots.retain(|name, _| current.contains_key(name));
+        for (name, (file_count, total_size)) in current {
+            match self.lots.get_mut(&name) {
+                Some(state) if state.file_count == file_count && state.total_size == total_size => {}
+                Some(state) => *state = LotState::changed(file_count, total_size, now),
+                None => {
+                    self.lots.insert(name, LotState::changed(file_count, total_size, now));
+                }
+            }
+        }
+    }
+
+    /// 変化後に一定時間変化のない（確定した）ロットを取り出す
+    fn take_stable(&mut self, stable_after: Duration) -> Vec<String> {
+        let now = Instant::now();
+        let stable: Vec<String> = self
+            .lots
+            .iter()
+            .filter(|(_, state)| state.is_pending && now.duration_since(state.last_change) >= stable_after)
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        for name in &stable {
+            if self.watcher.is_some() {
+                // 変更通知の場合は次の変化まで状態を持つ必要がない
+                self.lots.remove(name);
+            } else if let Some(state) = self.lots.get_mut(name) {
+                state.is_pending = false;
+            }
+        }
+
+        stable
+    }
+}
+
+/// 連続バックアップ用にコピー元のロットフォルダの変化を検知する
+/// 変更通知を使えるフォルダは通知で、それ以外（SFTP・FTP・通知非対応のSMB）はファイル数・サイズの比較で検知する
+pub struct LotWatcher {
+    roots: HashMap<(u32, String), WatchedRoot>,
+}
+
+impl LotWatcher {
+    /// 新しいLotWatcherインスタンスを作成
+    pub fn new() -> Self {
+        Self { roots: HashMap::new() }
+    }
+
+    /// コピー元の変化を確認し、確定したロットを返す
+    /// insp_configsには連続バックアップ対象の検査機器のみを渡す
+    pub fn poll(&mut self, insp_configs: &[InspConfig], stable_after: Duration) -> LotScope {
+        // 対象外になったフォルダの監視を終了
+        let targets: HashSet<(u32, String)> = insp_configs
+            .iter()
+            .flat_map(|insp| source_paths(insp).into_iter().map(move |path| (insp.id, path)))
+            .collect();
+        self.roots.retain(|key, _| targets.contains(key));
+
+        let mut lot_scope = LotScope::new();
+
+        for insp_config in insp_configs {
+            let source = match open_source(insp_config) {
+                Ok(source) => source,
+                Err(e) => {
+                    log::debug!("連続バックアップ: 検査機器 {} のコピー元を開けません: {}", insp_config.name, e);
+                    continue;
+                }
+            };
+
+            for path in source_paths(insp_config) {
+                let key = (insp_config.id, path.clone());
+                let Some(root) = self.roots.get_mut(&key) else {
+                    self.roots.insert(key, WatchedRoot::start(source.as_ref(), &path));
+                    continue;
+                };
+
+                root.update(source.as_ref(), &path);
+
+                for lot_name in root.take_stable(stable_after) {
+                    log::info!(
+                        "連続バックアップ: ロットが確定しました {}",
+                        source.display_path(&join_source_path(&path, &lot_name))
+                    );
+                    lot_scope
+                        .entry(insp_config.id)
+                        .or_default()
+                        .insert((path.clone(), lot_name));
+                }
+            }
+        }
+
+        lot_scope
+    }
+
+    /// バックアップできなかったロットを次回の確認で再度対象にする
+    pub fn requeue(&mut self, lot_scope: &LotScope) {
+        let now = Instant::now();
+        for (insp_id, lots) in lot_scope {
+            for (path, lot_name) in lots {
+                if let Some(root) = self.roots.get_mut(&(*insp_id, path.clone())) {
+                    root.lots
+                        .entry(lot_name.clone())
+                        .or_insert_with(|| LotState::changed(0, 0, now))
+                        .is_pending = true;
+                }
+            }
+        }
+    }
+}
+
+/// 検査機器のコピー元パス（未設定・重複を除く）
+fn source_paths(insp_config: &InspConfig) -> Vec<String> {
+    let mut paths: Vec<String> = Vec::new();
+    for path in [
+        &insp_config.surface_image_path,
+        &insp_config.back_image_path,
+        &insp_config.surface_result_path,
+        &insp_config.back_result_path,
+    ] {
+        if !path.is_empty() && !paths.contains(path) {
+            paths.push(path.clone());
+        }
+    }
+    paths
+}
+
+/// コピー元フォルダ直下の各ロットのファイル数と合計サイズ
+fn measure_lots(source: &dyn SourceAccess, path: &str) -> Result<HashMap<String, (u32, u64)>, String> {
+    let lots = source
+        .list(path)?
+        .into_iter()
+        .filter(|entry| entry.is_dir)
+        .filter_map(|entry| {
+            let measured = source.measure(&join_source_path(path, &entry.name)).ok()?;
+            Some((entry.name, measured))
+        })
+        .collect();
+    Ok(lots)
+}
+
+/// フォルダの変更通知を開始（変更のあったパスを直下のロット名に変換して記録）
+fn start_watcher(
+    local_root: &Path,
+    changes: Arc<Mutex<HashMap<String, Instant>>>,
+    watch_failed: Arc<AtomicBool>,
+) -> Result<RecommendedWatcher, String> {
+    let root = local_root.to_path_buf();
+    let mut watcher = notify::recommended_watcher(move |result: notify::Result<notify::Event>| {
+        match result {
+            Ok(event) => {
+                // 読み込みのみのイベントはバックアップ自体でも発生するため無視
+                if matches!(event.kind, EventKind::Access(_)) {
+                    return;
+                }
+
+                let now = Instant::now();
+                let mut changes = changes.lock().unwrap_or_else(|e| e.into_inner());
+                for path in &event.paths {
+                    if let Some(lot_name) = path.strip_prefix(&root).ok().and_then(|rel| rel.components().next()) {
+                        changes.insert(lot_name.as_os_str().to_string_lossy().to_string(), now);
+                    }
+                }
+            }
+            Err(e) => {
+                log::warn!("連続バックアップ: 変更通知でエラーが発生しました {}: {}", root.display(), e);
+                watch_failed.store(true, Ordering::Relaxed);
+            }
+        }
+    })
+    .map_err(|e| e.to_string())?;
+
+    watcher
+        .watch(local_root, RecursiveMode::Recursive)
+        .map_err(|e| e.to_string())?;
+
+    Ok(watcher)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::source_access::LocalSource;
+
+    /// テストごとの作業フォルダ
+    fn work_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("lot_watcher_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write(path: &Path, data: &[u8]) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, data).unwrap();
+    }
+
+    /// ポーリングで監視するコピー元フォルダ（現在のロットを比較基準にする）
+    fn polling_root(path: &str) -> WatchedRoot {
+        let mut root = WatchedRoot {
+            watcher: None,
+            changes: Arc::new(Mutex::new(HashMap::new())),
+            watch_failed: Arc::new(AtomicBool::new(false)),
+            lots: HashMap::new(),
+        };
+        root.reset_baseline(&LocalSource, path);
+        root
+    }
+
+    fn sorted(mut names: Vec<String>) -> Vec<String> {
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn take_stable_waits_for_quiet_period() {
+        let dir = work_dir("stable");
+        let mut root = polling_root(&dir.to_string_lossy());
+        let now = Instant::now();
+        let past = now.checked_sub(Duration::from_secs(60)).unwrap();
+        root.lots.insert("OLD".to_string(), LotState::changed(1, 1, past));
+        root.lots.insert("NEW".to_string(), LotState::changed(1, 1, now));
+        root.lots.insert("DONE".to_string(), LotState { is_pending: false, ..LotState::changed(1, 1, past) });
+
+        assert_eq!(root.take_stable(Duration::from_secs(30)), vec!["OLD".to_string()]);
+        // ポーリングでは次の比較のために状態を残し、同じロットを再度取り出さない
+        assert!(!root.lots["OLD"].is_pending);
+        assert!(root.take_stable(Duration::from_secs(30)).is_empty());
+        assert_eq!(root.take_stable(Duration::ZERO), vec!["NEW".to_string()]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn polling_detects_new_and_changed_lots() {
+        let dir = work_dir("polling");
+        let path = dir.to_string_lossy().to_string();
+        write(&dir.join("LOT1/a.jpg"), b"a");
+        write(&dir.join("LOT2/a.jpg"), b"a");
+        let mut root = polling_root(&path);
+
+        // 監視開始時点のロットは対象にしない
+        root.update(&LocalSource, &path);
+        assert!(root.take_stable(Duration::ZERO).is_empty());
+
+        write(&dir.join("LOT1/b.jpg"), b"b");
+        write(&dir.join("LOT3/a.jpg"), b"a");
+        fs::remove_dir_all(dir.join("LOT2")).unwrap();
+        root.update(&LocalSource, &path);
+        assert!(!root.lots.contains_key("LOT2"));
+        assert_eq!(sorted(root.take_stable(Duration::ZERO)), vec!["LOT1".to_string(), "LOT3".to_string()]);
+
+        // 変化が無ければ再度取り出さない
+        root.update(&LocalSource, &path);
+        assert!(root.take_stable(Duration::ZERO).is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn watcher_error_falls_back_to_polling() {
+        let dir = work_dir("fallback");
+        let path = dir.to_string_lossy().to_string();
+        write(&dir.join("LOT1/a.jpg"), b"a");
+        let changes = Arc::new(Mutex::new(HashMap::new()));
+        let watch_failed = Arc::new(AtomicBool::new(false));
+        let mut root = WatchedRoot {
+            watcher: Some(start_watcher(&dir, changes.clone(), watch_failed.clone()).unwrap()),
+            changes: changes.clone(),
+            watch_failed: watch_failed.clone(),
+            lots: HashMap::new(),
+        };
+
+        // 変更通知で検知したロットは取り出した後に状態を持たない
+        changes.lock().unwrap().insert("LOT1".to_string(), Instant::now());
+        root.update(&LocalSource, &path);
+        assert_eq!(root.take_stable(Duration::ZERO), vec!["LOT1".to_string()]);
+        assert!(root.lots.is_empty());
+
+        // エラー後はポーリングに切り替え、その時点のロットを比較基準にする
+        watch_failed.store(true, Ordering::Relaxed);
+        root.update(&LocalSource, &path);
+        assert!(root.watcher.is_none());
+        assert!(root.lots.contains_key("LOT1"));
+        assert!(root.take_stable(Duration::ZERO).is_empty());
+
+        write(&dir.join("LOT1/b.jpg"), b"b");
+        root.update(&LocalSource, &path);
+        assert_eq!(root.take_stable(Duration::ZERO), vec!["LOT1".to_string()]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn requeue_makes_lots_pending_again() {
+        let dir = work_dir("requeue");
+        let path = dir.to_string_lossy().to_string();
+        write(&dir.join("LOT1/a.jpg"), b"a");
+        let mut lot_watcher = LotWatcher::new();
+        lot_watcher.roots.insert((1, path.clone()), polling_root(&path));
+
+        let mut lot_scope = LotScope::new();
+        lot_scope.entry(1).or_default().insert((path.clone(), "LOT1".to_string()));
+        lot_scope.entry(1).or_default().insert((path.clone(), "LOT9".to_string()));
+        // 監視していないフォルダのロットは無視する
+        lot_scope.entry(2).or_default().insert((path.clone(), "LOT1".to_string()));
+        lot_watcher.requeue(&lot_scope);
+
+        let root = lot_watcher.roots.get_mut(&(1, path.clone())).unwrap();
+        assert_eq!(sorted(root.take_stable(Duration::ZERO)), vec!["LOT1".to_string(), "LOT9".to_string()]);
+        assert_eq!(lot_watcher.roots.len(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -9,6 +9,25 @@ mod app_monitor;
 mod settings_monitor;
 mod backup_scheduler;
 mod backup_executor;
+mod progress_tracker;
+mod backup_history;
+mod address;
+mod capacity_history;
+mod drive_mapping;
+mod storage_backend;
+mod source_access;
+mod lot_watcher;
+mod throttle;
+mod nas_pool;
+mod copy_engine;
+mod nas_inventory;
+mod duplicate_lots;
+mod lot_versions;
+mod conflict_policy;
+mod file_audit;
+mod root_files;
+mod file_filter;
+mod dest_template;
 
 use tauri::menu::MenuBuilder;
 use tauri::Manager;
@@ -17,11 +36,23 @@ use tauri_plugin_dialog::{DialogExt,MessageDialogKind};
 use tauri_plugin_log::{fern, Target, TargetKind};
 use tauri_plugin_single_instance::init as single_instance;
 
-use config::{init_info, save_settings, save_insp_settings, save_nas_settings,save_insp_backup_setting};
+use config::{init_info, save_settings, save_insp_settings, save_nas_settings,save_insp_backup_setting,save_insp_continuous_setting};
 use app_monitor::AppMonitor;
 use settings_monitor::SettingsMonitor;
 use backup_scheduler::BackupScheduler;
-use crate::types::{NasConfig, InspConfig, SettingsConfig, BackupStatus,InspInfo,NasInfo};
+use backup_history::BackupHistory;
+use capacity_history::CapacityHistory;
+use nas_inventory::NasInventory;
+use backup_executor::BackupExecutor;
+use duplicate_lots::DuplicateLots;
+use lot_versions::{is_plain_name, list_versioned_lots, restore_version, VERSIONS_DIR_NAME};
+use storage_backend::{open_backend, StorageBackend};
+use drive_mapping::dest_root_path;
+use dest_template::DestTemplate;
+use file_audit::read_file_audit;
+use address::parse_host_address;
+use copy_engine::{run_benchmark, CopyOptions};
+use crate::types::{NasConfig, StorageBackendConfig, InspConfig, SettingsConfig, BackupStatus,InspInfo,NasInfo,BackupHistoryQuery,BackupHistoryPage,CapacityForecast,CopyBenchmarkResult,DuplicateLot,ConsolidationResult,VersionedLot,FileAuditEntry};
 use tauri::{command, State};
 
 
@@ -55,6 +86,9 @@ async fn update_settings(
         return Err("バックアップ実行中は設定を変更できません".to_string());
     }
 
+    // 保存先のテンプレートが不正な場合は保存しない
+    DestTemplate::from_settings(&new_settings)?;
+
     // ファイルに保存
     save_settings(new_settings.clone()).await?;
 
@@ -76,6 +110,10 @@ async fn edit_insp_configs(
         return Err("バックアップ実行中は設定を変更できません".to_string());
     }
 
+    // アドレスの形式をチェック
+    parse_host_address(&new_insp_info.insp_ip)
+        .map_err(|e| format!("検査機器のアドレスが不正です: {}", e))?;
+
     // 先にメモリ上の設定を更新
     app_monitor.update_insp_configs(&new_insp_info).await;
 
@@ -100,6 +138,10 @@ async fn edit_nas_configs(
         return Err("バックアップ実行中は設定を変更できません".to_string());
     }
 
+    // アドレスの形式をチェック
+    parse_host_address(&new_nas_info.nas_ip)
+        .map_err(|e| format!("NASのアドレスが不正です: {}", e))?;
+
     // 先にメモリ上の設定を更新
     app_monitor.update_nas_configs(&new_nas_info).await;
 
@@ -140,6 +182,32 @@ async fn change_insp_backup_settings(
     Ok(insp_configs)
 }
 
+//外観の連続バックアップ切り替え
+#[command]
+async fn change_insp_continuous_settings(
+    app_monitor: State<'_, AppMonitor>,
+    scheduler: State<'_, BackupScheduler>,
+    insp_id:u32
+) -> Result<Vec<InspConfig>, String> {
+    // バックアップ中は設定変更を拒否
+    if scheduler.is_backup_running().await {
+        return Err("バックアップ実行中は設定を変更できません".to_string());
+    }
+
+    // メモリ上の設定を更新
+    app_monitor.switch_insp_continuous_settings(insp_id).await;
+
+    // 更新後の状態を取得してファイルに保存
+    let insp_configs = app_monitor.get_insp_configs().await;
+
+    // 該当IDのis_continuous状態を取得
+    if let Some(insp) = insp_configs.iter().find(|c| c.id == insp_id) {
+        save_insp_continuous_setting(insp_id, insp.is_continuous).await?;
+    }
+
+    Ok(insp_configs)
+}
+
 //外観検査機器の追加
 #[command]
 async fn add_insp_configs(
@@ -157,6 +225,10 @@ async fn add_insp_configs(
         return Err("バックアップ実行中は設定を変更できません".to_string());
     }
 
+    // アドレスの形式をチェック
+    parse_host_address(&insp_ip)
+        .map_err(|e| format!("検査機器のアドレスが不正です: {}", e))?;
+
     // メモリ上の設定を更新
     let new_id=app_monitor.add_insp(name.clone(),insp_ip.clone(),surface_image_path.clone(),back_image_path.clone(),surface_result_path.clone(),back_result_path.clone()).await;
 
@@ -165,7 +237,7 @@ async fn add_insp_configs(
 
     // メモリ上の更新が成功したらメモリの内容をファイルに保存
     //save_insp_settingsに渡すためにInspInfoを作成
-    let add_insp_info:InspInfo=InspInfo { id: new_id, name, insp_ip, surface_image_path, back_image_path, surface_result_path,back_result_path, is_backup:true };
+    let add_insp_info:InspInfo=InspInfo { id: new_id, name, insp_ip, surface_image_path, back_image_path, surface_result_path,back_result_path, is_backup:true, source: Default::default(), is_continuous: false, bandwidth_limit_mbps: 0, file_filter: Default::default() };
     save_insp_settings(add_insp_info,"add").await?;
 
     log::debug!("{:?}",insp_configs);
@@ -187,6 +259,10 @@ async fn add_nas_configs(
         return Err("バックアップ実行中は設定を変更できません".to_string());
     }
 
+    // アドレスの形式をチェック
+    parse_host_address(&nas_ip)
+        .map_err(|e| format!("NASのアドレスが不正です: {}", e))?;
+
     // メモリ上の設定を更新
     let new_id=app_monitor.add_nas(name.clone(),nas_ip.clone(),drive.clone()).await;
 
@@ -195,7 +271,7 @@ async fn add_nas_configs(
 
     // メモリ上の更新が成功したらメモリの内容をファイルに保存
     //save_insp_settingsに渡すためにInspInfoを作成
-    let add_nas_info:NasInfo=NasInfo { id: new_id, name, nas_ip,drive};
+    let add_nas_info:NasInfo=NasInfo { id: new_id, name, nas_ip,drive, backend: Default::default() };
     save_nas_settings(add_nas_info,"add").await?;
 
     log::debug!("{:?}",nas_configs);
@@ -267,6 +343,273 @@ async fn get_backup_status(scheduler: State<'_, BackupScheduler>) -> Result<Back
     Ok(scheduler.get_status().await)
 }
 
+/// バックアップ履歴を取得（ページング・日付/状態/検査機器で絞り込み）
+#[command]
+async fn get_backup_history(
+    history: State<'_, BackupHistory>,
+    query: BackupHistoryQuery,
+) -> Result<BackupHistoryPage, String> {
+    Ok(history.query(query).await)
+}
+
+/// NASごとの容量予測を取得（空き容量が必要空き容量に達するまでの日数）
+#[command]
+async fn get_capacity_forecast(
+    monitor: State<'_, AppMonitor>,
+    settings: State<'_, SettingsMonitor>,
+    capacity_history: State<'_, CapacityHistory>,
+) -> Result<Vec<CapacityForecast>, String> {
+    let nas_configs = monitor.get_nas_configs().await;
+    let required_free_space = settings.get_settings().await.required_free_space;
+    Ok(capacity_history.forecast(&nas_configs, required_free_space).await)
+}
+
+/// NAS上のロット一覧のキャッシュを作り直す（nas_idを指定しない場合は使用中のすべてのNAS）
+/// 戻り値: 数えたロット数
+#[command]
+async fn rescan_nas(
+    nas_id: Option<u32>,
+    monitor: State<'_, AppMonitor>,
+    settings: State<'_, SettingsMonitor>,
+    scheduler: State<'_, BackupScheduler>,
+    nas_inventory: State<'_, NasInventory>,
+) -> Result<usize, String> {
+    if scheduler.is_backup_running().await {
+        return Err("バックアップ処理中は再スキャンできません".to_string());
+    }
+
+    let nas_configs: Vec<NasConfig> = monitor
+        .get_nas_configs()
+        .await
+        .into_iter()
+        .filter(|nas| nas_id.is_none_or(|id| nas.id == id))
+        .filter(|nas| nas.is_use && nas.is_connected)
+        .collect();
+    if nas_configs.is_empty() {
+        return Err("再スキャンできるNASがありません".to_string());
+    }
+
+    let insp_configs = monitor.get_insp_configs().await;
+    let settings = settings.get_settings().await;
+    let nas_inventory = nas_inventory.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        let dest_paths = |backend: &dyn StorageBackend| BackupExecutor::nas_dest_paths(&insp_configs, &settings, backend, "");
+        nas_configs
+            .iter()
+            .map(|nas_config| nas_inventory.rescan(nas_config, &dest_paths))
+            .sum::<Result<usize, String>>()
+    })
+    .await
+    .map_err(|e| format!("NASの再スキャンが異常終了しました: {}", e))?
+}
+
+/// 複数のNASに重複しているロットの一覧を取得
+/// NAS上のロット一覧のキャッシュを更新するため、確認中はバックアップを開始しない
+#[command]
+async fn get_duplicate_lots(
+    monitor: State<'_, AppMonitor>,
+    settings: State<'_, SettingsMonitor>,
+    scheduler: State<'_, BackupScheduler>,
+    nas_inventory: State<'_, NasInventory>,
+) -> Result<Vec<DuplicateLot>, String> {
+    if !scheduler.try_start_backup().await {
+        return Err("バックアップ処理中は重複を確認できません".to_string());
+    }
+
+    let nas_configs = connected_nas_configs(&monitor).await;
+    let insp_configs = monitor.get_insp_configs().await;
+    let settings = settings.get_settings().await;
+    let nas_inventory = nas_inventory.inner().clone();
+    let found = tokio::task::spawn_blocking(move || {
+        let dest_path_groups = |backend: &dyn StorageBackend| BackupExecutor::nas_dest_path_groups(&insp_configs, &settings, backend, "");
+        DuplicateLots::connect(nas_configs, nas_inventory)?.find(&dest_path_groups)
+    })
+    .await;
+    scheduler.finish_backup().await;
+    found.map_err(|e| format!("重複ロットの確認が異常終了しました: {}", e))?
+}
+
+/// 重複ロットを1台のNASにまとめる（folder_path・lot_nameを指定しない場合はすべての重複ロット）
+/// 統合が終わるまでバックアップを開始しない（統合中のロットをコピー・削除しないようにする）
+#[command]
+async fn consolidate_duplicate_lots(
+    folder_path: Option<String>,
+    lot_name: Option<String>,
+    monitor: State<'_, AppMonitor>,
+    settings: State<'_, SettingsMonitor>,
+    scheduler: State<'_, BackupScheduler>,
+    nas_inventory: State<'_, NasInventory>,
+) -> Result<ConsolidationResult, String> {
+    if !scheduler.try_start_backup().await {
+        return Err("バックアップ処理中は統合できません".to_string());
+    }
+
+    let nas_configs = connected_nas_configs(&monitor).await;
+    let settings = settings.get_settings().await;
+    let insp_configs = monitor.get_insp_configs().await;
+    let nas_inventory = nas_inventory.inner().clone();
+    let consolidated = tokio::task::spawn_blocking(move || {
+        let dest_path_groups = |backend: &dyn StorageBackend| BackupExecutor::nas_dest_path_groups(&insp_configs, &settings, backend, "");
+        let duplicate_lots = DuplicateLots::connect(nas_configs, nas_inventory)?;
+        // 統合の直前に重複を確認し直す（一覧の取得後に変わっている場合がある）
+        let duplicates: Vec<DuplicateLot> = duplicate_lots
+            .find(&dest_path_groups)?
+            .into_iter()
+            .filter(|duplicate| folder_path.as_ref().is_none_or(|path| &duplicate.folder_path == path))
+            .filter(|duplicate| lot_name.as_ref().is_none_or(|name| &duplicate.lot_name == name))
+            .collect();
+        Ok(duplicate_lots.consolidate(&duplicates, settings.required_free_space, settings.max_lot_versions))
+    })
+    .await;
+    scheduler.finish_backup().await;
+    consolidated.map_err(|e| format!("重複ロットの統合が異常終了しました: {}", e))?
+}
+
+/// NASに世代が残っているロットの一覧を取得
+#[command]
+async fn get_lot_versions(
+    nas_id: u32,
+    monitor: State<'_, AppMonitor>,
+    settings: State<'_, SettingsMonitor>,
+) -> Result<Vec<VersionedLot>, String> {
+    let nas_config = connected_nas_configs(&monitor)
+        .await
+        .into_iter()
+        .find(|nas| nas.id == nas_id)
+        .ok_or_else(|| "NASに接続されていません".to_string())?;
+    let insp_configs = monitor.get_insp_configs().await;
+    let settings = settings.get_settings().await;
+    tokio::task::spawn_blocking(move || {
+        let backend = open_backend(&nas_config)?;
+        // 世代フォルダ内の保存先フォルダを探す（保存先のテンプレートの日付などの階層は世代フォルダ内を一覧して探す）
+        let mut dest_paths = BackupExecutor::nas_dest_paths(&insp_configs, &settings, backend.as_ref(), VERSIONS_DIR_NAME)?;
+        // カテゴリ直下のファイルの世代も、検査機器名をロット名として一覧に含める
+        dest_paths.extend(BackupExecutor::root_files_folder_paths(&insp_configs, &settings));
+        list_versioned_lots(backend.as_ref(), &dest_paths)
+    })
+    .await
+    .map_err(|e| format!("世代一覧の取得が異常終了しました: {}", e))?
+}
+
+/// ロットを指定した世代に戻す
+/// 復元が終わるまでバックアップを開始しない（復元中のロットをコピーしないようにする）
+/// 戻り値: 書き戻したファイル数
+#[command]
+#[allow(clippy::too_many_arguments)]
+async fn restore_lot_version(
+    nas_id: u32,
+    folder_path: String,
+    lot_name: String,
+    version: String,
+    monitor: State<'_, AppMonitor>,
+    settings: State<'_, SettingsMonitor>,
+    scheduler: State<'_, BackupScheduler>,
+    nas_inventory: State<'_, NasInventory>,
+) -> Result<u32, String> {
+    // 世代フォルダの外を指すパス（..・絶対パス・ドライブ指定）は受け付けない
+    if !is_plain_name(&lot_name) || !is_plain_name(&version) || !folder_path.split('/').all(is_plain_name) {
+        return Err(format!("復元する世代の指定が不正です: {}/{} ({})", folder_path, lot_name, version));
+    }
+
+    let nas_config = connected_nas_configs(&monitor)
+        .await
+        .into_iter()
+        .find(|nas| nas.id == nas_id)
+        .ok_or_else(|| "NASに接続されていません".to_string())?;
+    let insp_configs = monitor.get_insp_configs().await;
+    let settings = settings.get_settings().await;
+
+    if !scheduler.try_start_backup().await {
+        return Err("バックアップ処理中は復元できません".to_string());
+    }
+
+    let nas_inventory = nas_inventory.inner().clone();
+    let restored = tokio::task::spawn_blocking(move || {
+        let backend = open_backend(&nas_config)?;
+        // 世代一覧と同じく、設定から求めた保存先フォルダのロットのみ復元する
+        let mut dest_paths = BackupExecutor::nas_dest_paths(&insp_configs, &settings, backend.as_ref(), VERSIONS_DIR_NAME)?;
+        dest_paths.extend(BackupExecutor::root_files_folder_paths(&insp_configs, &settings));
+        if !dest_paths.contains(&folder_path) {
+            return Err(format!("設定された保存先フォルダではありません: {}", folder_path));
+        }
+
+        let restored = restore_version(backend.as_ref(), &folder_path, &lot_name, &version, settings.max_lot_versions);
+        // 復元でファイル数が変わるため、次回の更新時に数え直す
+        nas_inventory.invalidate_lot(nas_id, &folder_path, &lot_name);
+        nas_inventory.save();
+        restored
+    })
+    .await;
+    scheduler.finish_backup().await;
+    restored.map_err(|e| format!("世代の復元が異常終了しました: {}", e))?
+}
+
+/// ファイル単位のコピー記録を取得（date: YYYY-MM-DD、検査機器名を指定した場合はその検査機器のみ）
+#[command]
+async fn get_file_audit(date: String, device_name: Option<String>) -> Result<Vec<FileAuditEntry>, String> {
+    tokio::task::spawn_blocking(move || read_file_audit(&date, device_name.as_deref()))
+        .await
+        .map_err(|e| format!("コピー記録の読み込みが異常終了しました: {}", e))?
+}
+
+/// 使用中で接続済みのNAS設定一覧
+async fn connected_nas_configs(monitor: &AppMonitor) -> Vec<NasConfig> {
+    monitor
+        .get_nas_configs()
+        .await
+        .into_iter()
+        .filter(|nas| nas.is_use && nas.is_connected)
+        .collect()
+}
+
+/// コピー方式ごとのスループットを計測（source_path内のファイルを計測用のフォルダにコピーして比較）
+/// 計測用のフォルダはOSの一時フォルダ、nas_idを指定した場合はそのNAS（ローカル・SMB）のコピー先ルート直下に作り、計測後に削除する
+#[command]
+async fn run_copy_benchmark(
+    source_path: String,
+    nas_id: Option<u32>,
+    monitor: State<'_, AppMonitor>,
+    settings: State<'_, SettingsMonitor>,
+) -> Result<Vec<CopyBenchmarkResult>, String> {
+    let nas_config = match nas_id {
+        Some(nas_id) => Some(
+            connected_nas_configs(&monitor)
+                .await
+                .into_iter()
+                .find(|nas| nas.id == nas_id)
+                .ok_or_else(|| format!("NAS(ID: {})に接続されていません", nas_id))?,
+        ),
+        None => None,
+    };
+    let options = CopyOptions::from_settings(&settings.get_settings().await);
+
+    tokio::task::spawn_blocking(move || {
+        let dest_root = match &nas_config {
+            Some(nas_config) => {
+                if matches!(nas_config.backend, StorageBackendConfig::S3 { .. }) {
+                    return Err(format!("{} はS3のため、コピーの計測には使えません", nas_config.name));
+                }
+                // SMBの認証情報を使って共有に接続しておく
+                open_backend(nas_config)?;
+                dest_root_path(&nas_config.drive)
+            }
+            None => std::env::temp_dir(),
+        };
+        let dest_dir = dest_root.join(format!("_copy_benchmark_{}", chrono::Local::now().format("%Y%m%d_%H%M%S%3f")));
+
+        let results = run_benchmark(std::path::Path::new(&source_path), &dest_dir, options);
+        // 計測用のフォルダは残さない
+        if dest_dir.exists() {
+            if let Err(e) = std::fs::remove_dir_all(&dest_dir) {
+                log::warn!("計測用のフォルダを削除できません {}: {}", dest_dir.display(), e);
+            }
+        }
+        results
+    })
+    .await
+    .map_err(|e| format!("コピー計測が異常終了しました: {}", e))?
+}
+
 fn main() {
     tauri::Builder::default()
     .invoke_handler(tauri::generate_handler![
@@ -278,11 +621,21 @@ fn main() {
         edit_insp_configs,
         edit_nas_configs,
         change_insp_backup_settings,
+        change_insp_continuous_settings,
         add_insp_configs,
         add_nas_configs,
         delete_insp_configs,
         delete_nas_configs,
         get_backup_status,
+        get_backup_history,
+        get_capacity_forecast,
+        run_copy_benchmark,
+        rescan_nas,
+        get_duplicate_lots,
+        consolidate_duplicate_lots,
+        get_lot_versions,
+        restore_lot_version,
+        get_file_audit,
     ])
     .plugin(tauri_plugin_dialog::init())
     .plugin(single_instance(|app, _args, _cwd| {
@@ -322,22 +675,36 @@ fn main() {
                     let app_monitor = AppMonitor::new(configs.0.nas_configs, configs.0.insp_configs);
                     let settings_monitor = SettingsMonitor::new(configs.1);
 
+                    // バックアップ履歴をファイルから読み込み、検査機器ごとの最終成功日時を復元
+                    let backup_history = BackupHistory::load();
+                    let capacity_history = CapacityHistory::load();
+                    let nas_inventory = NasInventory::load();
+                    app_monitor.restore_backup_results(&backup_history.get_entries().await).await;
+
                     // バックアップスケジューラを作成
                     let backup_scheduler = BackupScheduler::new(
                         settings_monitor.clone(),
-                        app_monitor.clone()
+                        app_monitor.clone(),
+                        backup_history.clone(),
+                        nas_inventory.clone()
                     ).await;
 
                     // グローバル状態として管理
                     app_handle.manage(app_monitor.clone());
                     app_handle.manage(settings_monitor.clone());
                     app_handle.manage(backup_scheduler.clone());
+                    app_handle.manage(backup_history.clone());
+                    app_handle.manage(capacity_history.clone());
+                    app_handle.manage(nas_inventory.clone());
 
                     // 監視スレッドを開始
-                    app_monitor.start_monitoring(app_handle.clone());
+                    app_monitor.start_monitoring(app_handle.clone(), settings_monitor.clone(), capacity_history.clone());
 
                     // バックアップスケジューラを開始
-                    backup_scheduler.start_scheduling(app_handle.clone());
+                    backup_scheduler.clone().start_scheduling(app_handle.clone());
+
+                    // 連続バックアップ（コピー元の監視）を開始
+                    backup_scheduler.start_continuous_watch(app_handle.clone());
 
                     log::info!("Application monitoring and backup scheduler started successfully");
                 }
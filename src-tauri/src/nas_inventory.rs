@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+use std::fs;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::get_data_file_path;
+use crate::conflict_policy::canonical_file_count;
+use crate::storage_backend::{join_storage_path, open_backend, DestPaths, StorageBackend};
+use crate::types::NasConfig;
+
+/// NAS上のロット一覧のキャッシュファイル名（config.jsonと同じディレクトリに保存）
+const NAS_INVENTORY_FILE_NAME: &str = "nas_inventory.json";
+
+/// ロットフォルダ1つ分のキャッシュ
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct LotInventory {
+    file_count: u32,
+    #[serde(default)]
+    tree_modified: Option<u64>,     // 数えた時点のロットフォルダ配下のフォルダの最新の更新日時（UNIX秒、取得できないコピー先はNone）
+}
+
+/// NAS1台分のキャッシュ
+/// 保存先フォルダ（NAS内保存パス/検査機器名） → ロット名 → ロットのキャッシュ
+type NasFolders = HashMap<String, HashMap<String, LotInventory>>;
+
+/// NAS上の既存ロットのファイル数をNAS・保存先フォルダごとにキャッシュする構造体
+/// 保存先フォルダの一覧を取得し、配下のフォルダの更新日時が変わったロットのみファイル数を数え直す
+/// （ファイル数はファイルの追加・削除でのみ変わり、その場合は追加・削除したフォルダの更新日時が変わる）
+#[derive(Clone)]
+pub struct NasInventory {
+    nas_folders: Arc<Mutex<HashMap<u32, NasFolders>>>,
+}
+
+impl NasInventory {
+    /// キャッシュファイルを読み込んでNasInventoryインスタンスを作成
+    pub fn load() -> Self {
+        let nas_folders = match Self::read_inventory_file() {
+            Ok(nas_folders) => nas_folders,
+            Err(e) => {
+                log::warn!("Failed to load NAS inventory, starting empty: {}", e);
+                HashMap::new()
+            }
+        };
+
+        Self {
+            nas_folders: Arc::new(Mutex::new(nas_folders)),
+        }
+    }
+
+    /// キャッシュファイルを使わない空のNasInventoryインスタンスを作成（テスト用）
+    #[cfg(test)]
+    pub fn empty() -> Self {
+        Self {
+            nas_folders: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<u32, NasFolders>> {
+        self.nas_folders.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 保存先フォルダのキャッシュを更新し、ロット名 → ファイル数を返す
+    /// 配下のフォルダの更新日時が変わっていないロットはキャッシュの値を使う（更新日時を取得できないコピー先は毎回数え直す）
+    pub fn refresh(&self, nas_id: u32, backend: &dyn StorageBackend, folder_path: &str) -> HashMap<String, u32> {
+        let entries = match backend.list(folder_path) {
+            Ok(entries) => entries,
+            Err(e) => {
+                log::warn!("コピー先の一覧取得に失敗しました {} ({}): {}", folder_path, backend.display_name(), e);
+                return self.cached(nas_id, folder_path);
+            }
+        };
+
+        let cached_lots = self
+            .lock()
+            .get(&nas_id)
+            .and_then(|folders| folders.get(folder_path))
+            .cloned()
+            .unwrap_or_default();
+
+        let mut lots = HashMap::new();
+        let mut recounted = 0usize;
+        for entry in entries.into_iter().filter(|entry| entry.is_dir) {
+            let lot_path = join_storage_path(folder_path, &entry.name);
+            let tree_modified = match entry.modified {
+                Some(_) => backend.tree_modified(&lot_path).unwrap_or(None),
+                None => None,
+            };
+            let lot = match cached_lots.get(&entry.name) {
+                // 前回数えた時から配下のフォルダが変わっていない
+                Some(cached) if cached.tree_modified.is_some() && cached.tree_modified == tree_modified => cached.clone(),
+                _ => {
+                    // 番号を付けて残したファイルは数えない（コピー元のファイル数と比べるため）
+                    let count = match backend.list_files(&lot_path) {
+                        Ok(files) => canonical_file_count(files.iter().map(|(relative_path, _)| relative_path.as_str())),
+                        Err(_) => continue,
+                    };
+                    recounted += 1;
+                    LotInventory { file_count: count, tree_modified }
+                }
+            };
+            lots.insert(entry.name, lot);
+        }
+
+        log::debug!(
+            "NASのロット一覧を更新しました {} ({}): {} ロット (数え直し {})",
+            folder_path,
+            backend.display_name(),
+            lots.len(),
+            recounted
+        );
+
+        let file_counts = lots.iter().map(|(name, lot)| (name.clone(), lot.file_count)).collect();
+        self.lock()
+            .entry(nas_id)
+            .or_default()
+            .insert(folder_path.to_string(), lots);
+        file_counts
+    }
+
+    /// NASのキャッシュを破棄し、保存先フォルダのすべてのロットを数え直す
+    /// folder_paths: NAS上の保存先フォルダの一覧を取得する関数
+    /// 戻り値: 数えたロット数
+    pub fn rescan(
+        &self,
+        nas_config: &NasConfig,
+        folder_paths: &DestPaths<'_>,
+    ) -> Result<usize, String> {
+        let backend = open_backend(nas_config)?;
+        let folder_paths = folder_paths(backend.as_ref())?;
+        self.lock().remove(&nas_config.id);
+
+        let lots = folder_paths
+            .iter()
+            .map(|folder_path| self.refresh(nas_config.id, backend.as_ref(), folder_path).len())
+            .sum();
+        log::info!("NAS {} のロット一覧を作り直しました: {} ロット", nas_config.name, lots);
+
+        self.save();
+        Ok(lots)
+    }
+
+    /// キャッシュ済みのロット名 → ファイル数（NASを読み込めない場合に使う）
+    fn cached(&self, nas_id: u32, folder_path: &str) -> HashMap<String, u32> {
+        self.lock()
+            .get(&nas_id)
+            .and_then(|folders| folders.get(folder_path))
+            .map(|lots| lots.iter().map(|(name, lot)| (name.clone(), lot.file_count)).collect())
+            .unwrap_or_default()
+    }
+
+    /// バックアップでコピーしたロットを記録（コピー後にフォルダが変わらなければ次回の更新時に数え直さない）
+    pub fn record_lot(&self, nas_id: u32, backend: &dyn StorageBackend, folder_path: &str, lot_name: &str, file_count: u32) {
+        let tree_modified = backend
+            .tree_modified(&join_storage_path(folder_path, lot_name))
+            .unwrap_or(None);
+        self.lock()
+            .entry(nas_id)
+            .or_default()
+            .entry(folder_path.to_string())
+            .or_default()
+            .insert(lot_name.to_string(), LotInventory { file_count, tree_modified });
+    }
+
+    /// コピーに失敗したロットのキャッシュを破棄（次回の更新時に数え直す）
+    pub fn invalidate_lot(&self, nas_id: u32, folder_path: &str, lot_name: &str) {
+        if let Some(lots) = self
+            .lock()
+            .get_mut(&nas_id)
+            .and_then(|folders| folders.get_mut(folder_path))
+        {
+            lots.remove(lot_name);
+        }
+    }
+
+    /// キャッシュをファイルに保存
+    pub fn save(&self) {
+        let content = match serde_json::to_string(&*self.lock()) {
+            Ok(content) => content,
+            Err(e) => {
+                log::error!("Failed to serialize NAS inventory: {}", e);
+                return;
+            }
+        };
+
+        let result = get_data_file_path(NAS_INVENTORY_FILE_NAME).and_then(|inventory_path| {
+            fs::write(&inventory_path, content)
+                .map_err(|e| format!("Failed to write NAS inventory at {:?}: {}", inventory_path, e))
+        });
+        if let Err(e) = result {
+            log::error!("Failed to save NAS inventory: {}", e);
+        }
+    }
+
+    /// キャッシュファイルを読み込む
+    fn read_inventory_file() -> Result<HashMap<u32, NasFolders>, String> {
+        let inventory_path = get_data_file_path(NAS_INVENTORY_FILE_NAME)?;
+
+        if !inventory_path.exists() {
+            return Ok(HashMap::new());
+        }
+
+        let content = fs::read_to_string(&inventory_path)
+            .map_err(|e| format!("Failed to read NAS inventory at {:?}: {}", inventory_path, e))?;
+
+        serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse NAS inventory at {:?}: {}", inventory_path, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use std::time::{Duration, SystemTime};
+
+    use crate::storage_backend::LocalBackend;
+
+    /// フォルダの更新日時を過去に戻す（同じ秒のうちに変更しても更新日時の違いを検出できるようにする）
+    fn set_past_modified(path: &Path) {
+        fs::File::open(path)
+            .unwrap()
+            .set_modified(SystemTime::now() - Duration::from_secs(3600))
+            .unwrap();
+    }
+
+    /// 保存先フォルダ/LOT1/sub/a.txt を作成
+    fn create_lot(name: &str) -> (std::path::PathBuf, LocalBackend) {
+        let root = std::env::temp_dir().join(format!("nas_inventory_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("dest/LOT1/sub")).unwrap();
+        fs::write(root.join("dest/LOT1/sub/a.txt"), b"a").unwrap();
+        set_past_modified(&root.join("dest/LOT1/sub"));
+        set_past_modified(&root.join("dest/LOT1"));
+        let backend = LocalBackend::new(&root.to_string_lossy());
+        (root, backend)
+    }
+
+    #[test]
+    fn refresh_uses_cache_while_folders_are_unchanged() {
+        let (root, backend) = create_lot("unchanged");
+        let inventory = NasInventory::empty();
+        // 数え直した場合に分かるよう、実際とは異なるファイル数を記録する
+        inventory.record_lot(1, &backend, "dest", "LOT1", 5);
+
+        assert_eq!(inventory.refresh(1, &backend, "dest").get("LOT1"), Some(&5));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn refresh_recounts_after_nested_change() {
+        let (root, backend) = create_lot("nested");
+        let inventory = NasInventory::empty();
+        inventory.record_lot(1, &backend, "dest", "LOT1", 1);
+
+        // ロットフォルダ自体の更新日時は変わらない
+        fs::write(root.join("dest/LOT1/sub/b.txt"), b"b").unwrap();
+        assert_eq!(inventory.refresh(1, &backend, "dest").get("LOT1"), Some(&2));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn refresh_recounts_when_modified_is_unknown() {
+        let (root, backend) = create_lot("unknown");
+        let inventory = NasInventory::empty();
+        // 更新日時を取得できなかった記録（以前のキャッシュファイル・オブジェクトストレージ）は信用しない
+        inventory
+            .lock()
+            .entry(1)
+            .or_default()
+            .entry("dest".to_string())
+            .or_default()
+            .insert("LOT1".to_string(), LotInventory { file_count: 5, tree_modified: None });
+
+        assert_eq!(inventory.refresh(1, &backend, "dest").get("LOT1"), Some(&1));
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::backup_executor::BackupError;
+use crate::storage_backend::StorageBackend;
+use crate::types::NasConfig;
+
+/// 並列バックアップでのコピー先NASの割り当てを管理する構造体
+/// NASは優先順に使い、空き容量が足りなくなったら次のNASに切り替える
+/// 複数のワーカーが同じNASに書き込むため、コピー中のロットのサイズを予約して空き容量から差し引く
+pub struct NasPool {
+    nas_configs: Vec<NasConfig>,                        // 使用する順番に並べたNAS
+    backends: HashMap<u32, Box<dyn StorageBackend>>,
+    writers: HashMap<u32, Arc<Semaphore>>,              // NASごとの同時書き込み数の制限
+    state: Mutex<NasPoolState>,
+}
+
+struct NasPoolState {
+    current_index: usize,           // 現在使用中のNASのインデックス
+    reserved: HashMap<u32, u64>,    // NASごとのコピー中のロットの合計サイズ
+}
+
+/// NASへの書き込み権（ドロップ時に予約した容量と同時書き込み数の枠を返す）
+pub struct NasLease {
+    pool: Arc<NasPool>,
+    nas_index: usize,
+    reserved_size: u64,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl NasPool {
+    /// 新しいNasPoolインスタンスを作成
+    pub fn new(
+        nas_configs: Vec<NasConfig>,
+        backends: HashMap<u32, Box<dyn StorageBackend>>,
+        max_writers_per_nas: u32,
+    ) -> Self {
+        let writers = nas_configs
+            .iter()
+            .map(|nas| (nas.id, Arc::new(Semaphore::new(max_writers_per_nas.max(1) as usize))))
+            .collect();
+
+        Self {
+            nas_configs,
+            backends,
+            writers,
+            state: Mutex::new(NasPoolState {
+                current_index: 0,
+                reserved: HashMap::new(),
+            }),
+        }
+    }
+
+    fn lock_state(&self) -> std::sync::MutexGuard<'_, NasPoolState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// コピー先NASのバックエンドを取得
+    pub fn backend(&self, nas_id: u32) -> &dyn StorageBackend {
+        self.backends[&nas_id].as_ref()
+    }
+
+    /// すべてのNASのバックエンド（既存データの収集用）
+    pub fn backends(&self) -> &HashMap<u32, Box<dyn StorageBackend>> {
+        &self.backends
+    }
+
+    /// ロットのコピー先NASを割り当てる
+    /// コピー後も必要な空き容量が残るNASが見つかるまで順に切り替え、すべて不足している場合はDiskFull
+    /// どのNASの空き容量にも収まらない大きさのロットは、NASを切り替えずにそのロットのみエラー（General）
+    pub async fn acquire(self: &Arc<Self>, lot_size: u64, required_free_space: u64) -> Result<NasLease, BackupError> {
+        loop {
+            let nas_index = self.lock_state().current_index;
+            let Some(nas_config) = self.nas_configs.get(nas_index) else {
+                return Err(BackupError::DiskFull("すべてのNASで容量不足です".to_string()));
+            };
+
+            // 同時書き込み数の枠が空くまで待機
+            let permit = self.writers[&nas_config.id]
+                .clone()
+                .acquire_owned()
+                .await
+                .map_err(|e| BackupError::General(format!("NAS {} の書き込み枠を取得できません: {}", nas_config.name, e)))?;
+
+            // 空き容量をリアルタイムで確認（取得失敗時はキャッシュ値で代替）
+            let pool = self.clone();
+            let nas_id = nas_config.id;
+            let current_free = tokio::task::spawn_blocking(move || pool.backend(nas_id).free_space())
+                .await
+                .ok()
+                .and_then(|result| result.ok())
+                .map(|info| info.free)
+                .unwrap_or(nas_config.free_space);
+
+            let mut state = self.lock_state();
+
+            // 待機中に他のワーカーが次のNASに切り替えた場合はやり直す
+            if state.current_index != nas_index {
+                continue;
+            }
+
+            // 他のワーカーがコピー中の容量を差し引いて判定
+            let reserved = state.reserved.get(&nas_id).copied().unwrap_or(0);
+            let available = current_free.saturating_sub(reserved);
+            let required = required_free_space.saturating_add(lot_size);
+            if available >= required {
+                *state.reserved.entry(nas_id).or_insert(0) += lot_size;
+                return Ok(NasLease {
+                    pool: self.clone(),
+                    nas_index,
+                    reserved_size: lot_size,
+                    _permit: permit,
+                });
+            }
+
+            // 使用中のNASに必要な空き容量が残っていて、以降のどのNASにも収まらないロットは、そのロットのみエラーにする
+            // （NASを切り替えると、他のロットもコピーできなくなるため）
+            let fits_later = self.nas_configs[nas_index + 1..].iter().any(|nas| {
+                let reserved = state.reserved.get(&nas.id).copied().unwrap_or(0);
+                nas.free_space.saturating_sub(reserved) >= required
+            });
+            if available >= required_free_space && !fits_later {
+                log::warn!(
+                    "  ロットがどのNASの空き容量にも収まりません: {} + {} (required) (NAS {} の空き容量 {}, コピー中 {})",
+                    lot_size,
+                    required_free_space,
+                    nas_config.name,
+                    current_free,
+                    reserved
+                );
+                return Err(BackupError::General(format!(
+                    "ロットのサイズ ({} バイト) がどのNASの空き容量にも収まらないためコピーしていません",
+                    lot_size
+                )));
+            }
+
+            log::warn!(
+                "  NAS {} の空き容量不足: {} (コピー中 {}) < {} + {} (required). 次のNASに切り替えます...",
+                nas_config.name,
+                current_free,
+                reserved,
+                required_free_space,
+                lot_size
+            );
+            state.current_index += 1;
+        }
+    }
+}
+
+impl NasLease {
+    /// 割り当てられたNAS
+    pub fn nas_config(&self) -> &NasConfig {
+        &self.pool.nas_configs[self.nas_index]
+    }
+
+    /// 割り当てられたNASのバックエンド
+    pub fn backend(&self) -> &dyn StorageBackend {
+        self.pool.backend(self.nas_config().id)
+    }
+}
+
+impl Drop for NasLease {
+    fn drop(&mut self) {
+        let nas_id = self.nas_config().id;
+        let mut state = self.pool.lock_state();
+        if let Some(reserved) = state.reserved.get_mut(&nas_id) {
+            *reserved = reserved.saturating_sub(self.reserved_size);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::storage_backend::LocalBackend;
+
+    /// テストごとの作業フォルダ
+    fn work_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("nas_pool_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// 作業フォルダ内のローカルフォルダをコピー先にしたNAS2台のNasPool
+    fn pool(dir: &std::path::Path) -> Arc<NasPool> {
+        let mut nas_configs = Vec::new();
+        let mut backends: HashMap<u32, Box<dyn StorageBackend>> = HashMap::new();
+        for id in 1..=2 {
+            let root = dir.join(format!("nas{}", id));
+            fs::create_dir_all(&root).unwrap();
+            nas_configs.push(NasConfig {
+                id,
+                name: format!("NAS{}", id),
+                drive: root.to_string_lossy().to_string(),
+                nas_ip: String::new(),
+                is_use: true,
+                is_connected: true,
+                total_space: 0,
+                used_space: 0,
+                free_space: 0,
+                mapping_error: None,
+                connection_error: None,
+                backend: Default::default(),
+            });
+            backends.insert(id, Box::new(LocalBackend::new(&root.to_string_lossy())));
+        }
+        Arc::new(NasPool::new(nas_configs, backends, 2))
+    }
+
+    #[tokio::test]
+    async fn oversized_lot_fails_without_switching_nas() {
+        let dir = work_dir("oversized");
+        let pool = pool(&dir);
+
+        let result = pool.acquire(u64::MAX / 2, 0).await;
+        assert!(matches!(result, Err(BackupError::General(_))));
+        assert_eq!(pool.lock_state().current_index, 0);
+
+        // 以降のロットは同じNASにコピーできる
+        let lease = pool.acquire(1, 0).await.unwrap();
+        assert_eq!(lease.nas_config().id, 1);
+        assert_eq!(pool.lock_state().reserved[&1], 1);
+        drop(lease);
+        assert_eq!(pool.lock_state().reserved[&1], 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn full_nas_switches_until_disk_full() {
+        let dir = work_dir("disk_full");
+        let pool = pool(&dir);
+
+        let result = pool.acquire(1, u64::MAX / 2).await;
+        assert!(matches!(result, Err(BackupError::DiskFull(_))));
+        assert_eq!(pool.lock_state().current_index, 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,191 @@
+use std::time::Instant;
+use tauri::{AppHandle, Emitter};
+use tokio::time::Duration;
+use crate::types::{BackupProgress, DeviceProgress, CategoryProgress};
+
+/// 進捗イベントを送信する最小間隔
+const PROGRESS_EMIT_INTERVAL: Duration = Duration::from_millis(500);
+
+/// バックアップ実行全体の進捗を集計し、一定間隔でフロントエンドに通知する構造体
+pub struct ProgressTracker {
+    app_handle: AppHandle,
+    started_at: Instant,
+    last_emit: Option<Instant>,
+    current_files: u64,
+    total_files: u64,
+    current_size: u64,
+    processed_size: u64,    // 失敗したファイルのサイズも含めた処理済みバイト数（割合計算用）
+    total_size: u64,
+    current_file: String,
+    current_device: String,
+    devices: Vec<DeviceProgress>,
+}
+
+impl ProgressTracker {
+    /// 新しいProgressTrackerインスタンスを作成
+    pub fn new(app_handle: AppHandle) -> Self {
+        Self {
+            app_handle,
+            started_at: Instant::now(),
+            last_emit: None,
+            current_files: 0,
+            total_files: 0,
+            current_size: 0,
+            processed_size: 0,
+            total_size: 0,
+            current_file: String::new(),
+            current_device: String::new(),
+            devices: Vec::new(),
+        }
+    }
+
+    /// コピー予定のファイル数とバイト数を計画に追加
+    pub fn add_planned(&mut self, device_name: &str, category: &str, files: u64, size: u64) {
+        self.total_files += files;
+        self.total_size += size;
+
+        let category_progress = self.category_mut(device_name, category);
+        category_progress.total_files += files;
+        category_progress.total_size += size;
+
+        let device_progress = self.device_mut(device_name);
+        device_progress.total_files += files;
+        device_progress.total_size += size;
+    }
+
+    /// 計画が完了し、コピーを開始する時点で計測開始時刻をリセット
+    pub fn start(&mut self) {
+        self.started_at = Instant::now();
+        log::info!(
+            "Backup plan: {} files, {} bytes",
+            self.total_files,
+            self.total_size
+        );
+        self.emit();
+    }
+
+    /// 1ファイルの処理結果を記録
+    /// copied_size: コピー成功時はコピーしたバイト数、失敗・スキップ時はNone
+    pub fn record_file(
+        &mut self,
+        device_name: &str,
+        category: &str,
+        file_path: &str,
+        file_size: u64,
+        copied_size: Option<u64>,
+    ) {
+        let copied = copied_size.unwrap_or(0);
+
+        self.current_files += 1;
+        self.current_size += copied;
+        self.processed_size += copied_size.unwrap_or(file_size);
+        self.current_file = file_path.to_string();
+        self.current_device = format!("{} - {}", device_name, category);
+
+        let category_progress = self.category_mut(device_name, category);
+        category_progress.current_files += 1;
+        category_progress.current_size += copied;
+
+        let device_progress = self.device_mut(device_name);
+        device_progress.current_files += 1;
+        device_progress.current_size += copied;
+
+        // 前回の通知から一定時間経過した場合のみ通知
+        let should_emit = match self.last_emit {
+            Some(last) => last.elapsed() >= PROGRESS_EMIT_INTERVAL,
+            None => true,
+        };
+        if should_emit {
+            self.emit();
+        }
+    }
+
+    /// 最終状態を通知
+    pub fn finish(&mut self) {
+        self.emit();
+    }
+
+    /// 現在の進捗情報を作成
+    pub fn snapshot(&self) -> BackupProgress {
+        let elapsed = self.started_at.elapsed().as_secs_f64();
+        let bytes_per_sec = if elapsed > 0.0 {
+            self.current_size as f64 / elapsed
+        } else {
+            0.0
+        };
+
+        let eta_secs = if bytes_per_sec > 0.0 {
+            let remaining = self.total_size.saturating_sub(self.processed_size);
+            Some((remaining as f64 / bytes_per_sec).ceil() as u64)
+        } else {
+            None
+        };
+
+        let percentage = if self.total_size > 0 {
+            (self.processed_size as f32 / self.total_size as f32 * 100.0).min(100.0)
+        } else if self.total_files > 0 {
+            (self.current_files as f32 / self.total_files as f32 * 100.0).min(100.0)
+        } else {
+            100.0
+        };
+
+        BackupProgress {
+            current_files: self.current_files,
+            total_files: self.total_files,
+            current_size: self.current_size,
+            total_size: self.total_size,
+            percentage,
+            bytes_per_sec,
+            eta_secs,
+            current_file: self.current_file.clone(),
+            current_device: self.current_device.clone(),
+            devices: self.devices.clone(),
+        }
+    }
+
+    /// フロントエンドに進捗を通知
+    fn emit(&mut self) {
+        self.last_emit = Some(Instant::now());
+        if let Err(e) = self.app_handle.emit("backup-progress", self.snapshot()) {
+            log::error!("Failed to emit backup-progress event: {}", e);
+        }
+    }
+
+    /// 検査機器単位の小計を取得（無ければ作成）
+    fn device_mut(&mut self, device_name: &str) -> &mut DeviceProgress {
+        let pos = match self.devices.iter().position(|d| d.device_name == device_name) {
+            Some(pos) => pos,
+            None => {
+                self.devices.push(DeviceProgress {
+                    device_name: device_name.to_string(),
+                    current_files: 0,
+                    total_files: 0,
+                    current_size: 0,
+                    total_size: 0,
+                    categories: Vec::new(),
+                });
+                self.devices.len() - 1
+            }
+        };
+        &mut self.devices[pos]
+    }
+
+    /// カテゴリ単位の小計を取得（無ければ作成）
+    fn category_mut(&mut self, device_name: &str, category: &str) -> &mut CategoryProgress {
+        let device_progress = self.device_mut(device_name);
+        let pos = match device_progress.categories.iter().position(|c| c.category == category) {
+            Some(pos) => pos,
+            None => {
+                device_progress.categories.push(CategoryProgress {
+                    category: category.to_string(),
+                    current_files: 0,
+                    total_files: 0,
+                    current_size: 0,
+                    total_size: 0,
+                });
+                device_progress.categories.len() - 1
+            }
+        };
+        &mut device_progress.categories[pos]
+    }
+}
@@ -0,0 +1,68 @@
+use std::collections::HashMap;
+
+use crate::source_access::{SourceFile, MTIME_TOLERANCE_SECS};
+use crate::storage_backend::{join_storage_path, StorageBackend};
+
+/// カテゴリ直下のファイル（集計CSV・日次ログなど）の保存先のルート
+/// ロット一覧に混ざらないよう、コピー先ルート直下に保存先フォルダとは分けて置く
+/// 保存先: _root_files/NAS内保存パス/検査機器名/ファイル名
+const ROOT_FILES_DIR_NAME: &str = "_root_files";
+
+/// カテゴリ直下のファイルの保存先フォルダの親（この下に検査機器名のフォルダを置く）
+pub fn root_files_folder_path(nas_base_path: &str) -> String {
+    join_storage_path(ROOT_FILES_DIR_NAME, nas_base_path)
+}
+
+/// カテゴリ直下のファイルのうち、コピーが必要なものを選ぶ
+/// いずれかのNASにサイズ・更新日時が一致するファイルがあればバックアップ済みとする
+/// 戻り値: (コピーするファイル, バックアップ済みのためスキップしたファイル数)
+pub fn find_changed_root_files(
+    files: Vec<SourceFile>,
+    nas_backends: &HashMap<u32, Box<dyn StorageBackend>>,
+    folder_path: &str,
+    device_name: &str,
+) -> (Vec<SourceFile>, u64) {
+    let dest_path = join_storage_path(folder_path, device_name);
+
+    // NASごとの保存済みファイル（ファイル名 → (サイズ, 更新日時)）
+    let mut backed_up: Vec<HashMap<String, (u64, Option<u64>)>> = Vec::new();
+    for backend in nas_backends.values() {
+        match backend.list(&dest_path) {
+            Ok(entries) => backed_up.push(
+                entries
+                    .into_iter()
+                    .filter(|entry| !entry.is_dir)
+                    .map(|entry| (entry.name, (entry.size, entry.modified)))
+                    .collect(),
+            ),
+            // 読み込めないNASは保存済みのファイルが無いものとして扱う（再コピーになるだけで取りこぼさない）
+            Err(e) => log::warn!("カテゴリ直下のファイルの保存先を読み込めません {} ({}): {}", dest_path, backend.display_name(), e),
+        }
+    }
+
+    let total = files.len() as u64;
+    let changed: Vec<SourceFile> = files
+        .into_iter()
+        .filter(|file| {
+            !backed_up.iter().any(|nas_files| {
+                nas_files.get(&file.relative_path).is_some_and(|&(size, modified)| {
+                    is_same_file(file, size, modified)
+                })
+            })
+        })
+        .collect();
+
+    let skipped = total - changed.len() as u64;
+    (changed, skipped)
+}
+
+/// コピー元のファイルとNAS上のファイルのサイズ・更新日時が一致するか（更新日時を取得できない場合は一致しない）
+fn is_same_file(file: &SourceFile, size: u64, modified: Option<u64>) -> bool {
+    if file.size != size {
+        return false;
+    }
+    match (file.attributes.modified_secs(), modified) {
+        (Some(source_modified), Some(modified)) => source_modified.abs_diff(modified) <= MTIME_TOLERANCE_SECS,
+        _ => false,
+    }
+}
@@ -0,0 +1,1001 @@
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use chrono::NaiveDateTime;
+use walkdir::WalkDir;
+
+use crate::address::{parse_host_address, resolve_socket_addrs, unc_host};
+use crate::conflict_policy::canonical_file_count;
+use crate::types::{InspConfig, SourceAccessConfig};
+
+/// SMB（UNCパス）のポート
+const SMB_PORT: u16 = 445;
+/// SFTPのデフォルトポート
+const SFTP_PORT: u16 = 22;
+/// FTPのデフォルトポート
+const FTP_PORT: u16 = 21;
+/// SFTP・FTPの接続・応答タイムアウト
+pub const REMOTE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// コピー元のエントリ情報
+#[derive(Debug, Clone)]
+pub struct SourceEntry {
+    pub name: String,
+    pub is_dir: bool,
+    pub size: u64,
+    pub attributes: FileAttributes,
+}
+
+/// 更新日時を比較する際の許容差（秒）
+/// FAT・SMBでは更新日時が2秒単位で丸められるため
+pub const MTIME_TOLERANCE_SECS: u64 = 2;
+
+/// ファイルの日時・属性（コピー先に引き継ぐ）
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FileAttributes {
+    pub modified: Option<SystemTime>,   // 更新日時（取得できないコピー元はNone）
+    pub created: Option<SystemTime>,    // 作成日時（取得できないコピー元はNone）
+    pub readonly: bool,                 // 読み取り専用属性
+}
+
+impl FileAttributes {
+    /// ローカルのファイルシステムのメタデータから取得
+    pub fn from_metadata(metadata: &fs::Metadata) -> Self {
+        Self {
+            modified: metadata.modified().ok(),
+            created: metadata.created().ok(),
+            readonly: metadata.permissions().readonly(),
+        }
+    }
+
+    /// UNIX秒の更新日時
+    pub fn modified_secs(&self) -> Option<u64> {
+        self.modified?
+            .duration_since(UNIX_EPOCH)
+            .ok()
+            .map(|duration| duration.as_secs())
+    }
+
+    /// コピー先に引き継いだ日時・属性と比べて、引き継げていない項目を列挙
+    /// 作成日時はWindowsのみ設定できるため、それ以外では比較しない
+    pub fn differences(&self, copied: &FileAttributes) -> Vec<String> {
+        let mut differences = Vec::new();
+        if !times_match(self.modified, copied.modified) {
+            differences.push(format!("更新日時 (コピー元: {:?}, コピー先: {:?})", self.modified, copied.modified));
+        }
+        if cfg!(windows) && !times_match(self.created, copied.created) {
+            differences.push(format!("作成日時 (コピー元: {:?}, コピー先: {:?})", self.created, copied.created));
+        }
+        if self.readonly != copied.readonly {
+            differences.push(format!("読み取り専用 (コピー元: {}, コピー先: {})", self.readonly, copied.readonly));
+        }
+        differences
+    }
+}
+
+/// コピー元の日時がコピー先に引き継がれているか（コピー元の日時が不明な場合は比較しない）
+fn times_match(source: Option<SystemTime>, copied: Option<SystemTime>) -> bool {
+    match (source, copied) {
+        (None, _) => true,
+        (Some(source), Some(copied)) => {
+            let difference = source.duration_since(copied).or_else(|_| copied.duration_since(source));
+            difference.is_ok_and(|difference| difference.as_secs() <= MTIME_TOLERANCE_SECS)
+        }
+        (Some(_), None) => false,
+    }
+}
+
+/// ロットフォルダ配下のファイル
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    pub relative_path: String,  // ロットフォルダからの相対パス（区切り文字は /）
+    pub size: u64,
+    pub attributes: FileAttributes,
+}
+
+/// ロットフォルダ1つ分の走査結果（差分の判定・進捗の集計・コピーで共有し、フォルダを何度も走査しない）
+#[derive(Debug, Clone, Default)]
+pub struct LotScan {
+    pub files: Vec<SourceFile>,
+    pub dirs: Vec<String>,      // 配下のフォルダ（ロットフォルダからの相対パス、親フォルダが先）
+}
+
+impl LotScan {
+    /// ファイル数
+    pub fn file_count(&self) -> u32 {
+        self.files.len() as u32
+    }
+
+    /// NASのロットと比べるファイル数（番号を付けて残す名前と重なるファイルは数えない）
+    pub fn canonical_file_count(&self) -> u32 {
+        canonical_file_count(self.files.iter().map(|file| file.relative_path.as_str()))
+    }
+
+    /// 合計サイズ
+    pub fn total_size(&self) -> u64 {
+        self.files.iter().map(|file| file.size).sum()
+    }
+}
+
+/// 検査機器側（コピー元）へのアクセスを抽象化するトレイト
+/// パスは検査機器の設定に登録されたパス（UNCの場合は共有名から、それ以外は絶対パス）
+pub trait SourceAccess: Send + Sync {
+    /// ログ・結果表示用のパス
+    fn display_path(&self, path: &str) -> String;
+
+    /// ディレクトリ直下のエントリ一覧を取得
+    fn list(&self, path: &str) -> Result<Vec<SourceEntry>, String>;
+
+    /// ファイルを読み込み用に開く
+    fn open(&self, path: &str) -> Result<Box<dyn Read + '_>, String>;
+
+    /// ローカルのファイルシステムとしてアクセスできる場合はそのパス
+    /// （コピー先へのコピーにOSのコピー機能を使うため）
+    fn local_path(&self, _path: &str) -> Option<PathBuf> {
+        None
+    }
+
+    /// ディレクトリ配下のファイル数と合計サイズを再帰的に取得
+    fn measure(&self, path: &str) -> Result<(u32, u64), String> {
+        let scan = self.scan(path)?;
+        Ok((scan.file_count(), scan.total_size()))
+    }
+
+    /// ディレクトリ配下のファイルとフォルダを再帰的に取得
+    fn scan(&self, path: &str) -> Result<LotScan, String> {
+        let mut scan = LotScan::default();
+        let mut pending = vec![String::new()];
+        while let Some(relative_dir) = pending.pop() {
+            for entry in self.list(&join_relative_source_path(path, &relative_dir))? {
+                let relative_path = if relative_dir.is_empty() {
+                    entry.name
+                } else {
+                    format!("{}/{}", relative_dir, entry.name)
+                };
+                if entry.is_dir {
+                    scan.dirs.push(relative_path.clone());
+                    pending.push(relative_path);
+                } else {
+                    scan.files.push(SourceFile {
+                        relative_path,
+                        size: entry.size,
+                        attributes: entry.attributes,
+                    });
+                }
+            }
+        }
+        Ok(scan)
+    }
+}
+
+/// 検査機器の設定からコピー元へのアクセス手段を作成（SFTP・FTPはここで接続する）
+pub fn open_source(insp_config: &InspConfig) -> Result<Box<dyn SourceAccess>, String> {
+    match &insp_config.source {
+        SourceAccessConfig::Unc => Ok(Box::new(UncSource { insp_ip: insp_config.insp_ip.clone() })),
+        SourceAccessConfig::Local => Ok(Box::new(LocalSource)),
+        SourceAccessConfig::Sftp { username, password, private_key_path } => Ok(Box::new(SftpSource::connect(
+            &insp_config.insp_ip,
+            username,
+            password.as_deref(),
+            private_key_path.as_deref(),
+        )?)),
+        SourceAccessConfig::Ftp { username, password } => Ok(Box::new(FtpSource::connect(
+            &insp_config.insp_ip,
+            username.as_deref().unwrap_or("anonymous"),
+            password.as_deref().unwrap_or(""),
+        )?)),
+    }
+}
+
+/// 接続チェックに使うポート（ローカルの場合は接続チェック不要のためNone）
+pub fn source_port(source: &SourceAccessConfig) -> Option<u16> {
+    match source {
+        SourceAccessConfig::Unc => Some(SMB_PORT),
+        SourceAccessConfig::Local => None,
+        SourceAccessConfig::Sftp { .. } => Some(SFTP_PORT),
+        SourceAccessConfig::Ftp { .. } => Some(FTP_PORT),
+    }
+}
+
+/// コピー元のパスに子要素を結合
+pub fn join_source_path(parent: &str, child: &str) -> String {
+    let separator = if parent.contains('\\') && !parent.contains('/') { "\\" } else { "/" };
+    format!("{}{}{}", parent.trim_end_matches(['\\', '/']), separator, child)
+}
+
+/// コピー元のパスに相対パス（区切り文字は /）を結合
+pub fn join_relative_source_path(parent: &str, relative_path: &str) -> String {
+    relative_path
+        .split('/')
+        .filter(|component| !component.is_empty())
+        .fold(parent.to_string(), |path, component| join_source_path(&path, component))
+}
+
+/// 検査機器側のソースパス（UNCパス）を構築
+pub fn build_unc_source_path(insp_ip: &str, source_relative_path: &str) -> PathBuf {
+    // source_relative_pathの先頭の/や\を取り除く
+    let clean_relative_path = source_relative_path
+        .trim_start_matches('/')
+        .trim_start_matches('\\');
+
+    let mut source_path = PathBuf::new();
+    source_path.push(format!("\\\\{}", unc_host(insp_ip))); // UNCパス形式
+    source_path.push(clean_relative_path);
+    source_path
+}
+
+/// ローカルのファイルシステム上のディレクトリ一覧を取得
+fn list_local_dir(dir_path: &Path) -> Result<Vec<SourceEntry>, String> {
+    let entries = fs::read_dir(dir_path)
+        .map_err(|e| format!("ディレクトリ読み込みエラー {}: {}", dir_path.display(), e))?;
+
+    Ok(entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            Some(SourceEntry {
+                name: entry.file_name().to_string_lossy().to_string(),
+                is_dir: metadata.is_dir(),
+                size: metadata.len(),
+                attributes: FileAttributes::from_metadata(&metadata),
+            })
+        })
+        .collect())
+}
+
+/// ローカルのファイルシステム上のディレクトリ配下のファイル数と合計サイズを取得
+fn measure_local_dir(dir_path: &Path) -> (u32, u64) {
+    WalkDir::new(dir_path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.metadata().ok())
+        .filter(|m| m.is_file())
+        .fold((0, 0), |(count, size), m| (count + 1, size + m.len()))
+}
+
+/// ローカルのファイルシステム上のディレクトリ配下のファイルとフォルダを取得
+fn scan_local_dir(dir_path: &Path) -> Result<LotScan, String> {
+    let mut scan = LotScan::default();
+    for entry in WalkDir::new(dir_path).min_depth(1) {
+        let entry = entry.map_err(|e| format!("ディレクトリ読み込みエラー {}: {}", dir_path.display(), e))?;
+        let relative_path = entry
+            .path()
+            .strip_prefix(dir_path)
+            .map_err(|e| format!("ディレクトリ読み込みエラー {}: {}", entry.path().display(), e))?
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        if entry.file_type().is_dir() {
+            scan.dirs.push(relative_path);
+        } else {
+            let metadata = entry
+                .metadata()
+                .map_err(|e| format!("ファイル情報取得エラー {}: {}", entry.path().display(), e))?;
+            scan.files.push(SourceFile {
+                relative_path,
+                size: metadata.len(),
+                attributes: FileAttributes::from_metadata(&metadata),
+            });
+        }
+    }
+    Ok(scan)
+}
+
+fn open_local_file(file_path: &Path) -> Result<Box<dyn Read>, String> {
+    let file = fs::File::open(file_path)
+        .map_err(|e| format!("ファイル読み込みエラー {}: {}", file_path.display(), e))?;
+    Ok(Box::new(file))
+}
+
+/// 検査機器のSMB共有（\\insp_ip\path）
+pub struct UncSource {
+    insp_ip: String,
+}
+
+impl SourceAccess for UncSource {
+    fn display_path(&self, path: &str) -> String {
+        build_unc_source_path(&self.insp_ip, path).to_string_lossy().to_string()
+    }
+
+    fn list(&self, path: &str) -> Result<Vec<SourceEntry>, String> {
+        list_local_dir(&build_unc_source_path(&self.insp_ip, path))
+    }
+
+    fn open(&self, path: &str) -> Result<Box<dyn Read + '_>, String> {
+        open_local_file(&build_unc_source_path(&self.insp_ip, path))
+    }
+
+    fn local_path(&self, path: &str) -> Option<PathBuf> {
+        Some(build_unc_source_path(&self.insp_ip, path))
+    }
+
+    fn measure(&self, path: &str) -> Result<(u32, u64), String> {
+        Ok(measure_local_dir(&build_unc_source_path(&self.insp_ip, path)))
+    }
+
+    fn scan(&self, path: &str) -> Result<LotScan, String> {
+        scan_local_dir(&build_unc_source_path(&self.insp_ip, path))
+    }
+}
+
+/// バックアップPC上で動作する検査アプリ（ローカルパス）
+pub struct LocalSource;
+
+impl SourceAccess for LocalSource {
+    fn display_path(&self, path: &str) -> String {
+        path.to_string()
+    }
+
+    fn list(&self, path: &str) -> Result<Vec<SourceEntry>, String> {
+        list_local_dir(Path::new(path))
+    }
+
+    fn open(&self, path: &str) -> Result<Box<dyn Read + '_>, String> {
+        open_local_file(Path::new(path))
+    }
+
+    fn local_path(&self, path: &str) -> Option<PathBuf> {
+        Some(PathBuf::from(path))
+    }
+
+    fn measure(&self, path: &str) -> Result<(u32, u64), String> {
+        Ok(measure_local_dir(Path::new(path)))
+    }
+
+    fn scan(&self, path: &str) -> Result<LotScan, String> {
+        scan_local_dir(Path::new(path))
+    }
+}
+
+/// TCP接続（アドレスにポートが指定されていない場合はdefault_portを使用）
+fn connect_tcp(address: &str, default_port: u16) -> Result<TcpStream, String> {
+    let addrs = resolve_socket_addrs(address, default_port)?;
+    let mut last_error = String::new();
+    for addr in &addrs {
+        match TcpStream::connect_timeout(addr, REMOTE_TIMEOUT) {
+            Ok(stream) => {
+                stream.set_read_timeout(Some(REMOTE_TIMEOUT)).ok();
+                stream.set_write_timeout(Some(REMOTE_TIMEOUT)).ok();
+                return Ok(stream);
+            }
+            Err(e) => last_error = e.to_string(),
+        }
+    }
+    Err(format!("{} に接続できません: {}", address, last_error))
+}
+
+/// プールに残しておく待機中のSFTPセッションの上限
+const MAX_IDLE_SFTP_SESSIONS: usize = 4;
+
+/// SFTPのセッション
+/// 1つのセッション内の操作は順番に処理されるため、転送ごとにセッションを使い分けて並行してコピーする
+struct SftpSession {
+    sftp: ssh2::Sftp,
+    _session: ssh2::Session,
+}
+
+impl SftpSession {
+    /// 接続してログイン
+    fn login(
+        insp_ip: &str,
+        username: &str,
+        password: Option<&str>,
+        private_key_path: Option<&str>,
+    ) -> Result<Self, String> {
+        let stream = connect_tcp(insp_ip, SFTP_PORT)?;
+
+        let mut session = ssh2::Session::new().map_err(|e| format!("SSHセッション作成エラー: {}", e))?;
+        session.set_timeout(REMOTE_TIMEOUT.as_millis() as u32);
+        session.set_tcp_stream(stream);
+        session
+            .handshake()
+            .map_err(|e| format!("SSHハンドシェイクエラー {}: {}", insp_ip, e))?;
+
+        match (private_key_path, password) {
+            (Some(key_path), passphrase) => session
+                .userauth_pubkey_file(username, None, Path::new(key_path), passphrase)
+                .map_err(|e| format!("SSH公開鍵認証エラー {}@{}: {}", username, insp_ip, e))?,
+            (None, Some(password)) => session
+                .userauth_password(username, password)
+                .map_err(|e| format!("SSHパスワード認証エラー {}@{}: {}", username, insp_ip, e))?,
+            (None, None) => session
+                .userauth_agent(username)
+                .map_err(|e| format!("SSHエージェント認証エラー {}@{}: {}", username, insp_ip, e))?,
+        }
+
+        let sftp = session.sftp().map_err(|e| format!("SFTP開始エラー {}: {}", insp_ip, e))?;
+
+        Ok(Self { sftp, _session: session })
+    }
+
+    /// セッションが使えるか確認（プールで待機中にサーバーから切断されている場合がある）
+    fn is_alive(&self) -> bool {
+        self.sftp.realpath(Path::new(".")).is_ok()
+    }
+}
+
+/// SFTPで公開されている検査機器
+/// ローカルのパスとしてはアクセスできないため、ファイルは常に先読み付きのストリームでコピーする
+/// セッションは転送ごとに使い、使い終わったらプールに戻す（ファイルの読み込み中も他のロットの一覧取得・読み込みができる）
+pub struct SftpSource {
+    host: String,
+    username: String,
+    password: Option<String>,
+    private_key_path: Option<String>,
+    idle: Mutex<Vec<SftpSession>>,  // 待機中のセッション
+}
+
+impl SftpSource {
+    pub fn connect(
+        insp_ip: &str,
+        username: &str,
+        password: Option<&str>,
+        private_key_path: Option<&str>,
+    ) -> Result<Self, String> {
+        let session = SftpSession::login(insp_ip, username, password, private_key_path)?;
+        Ok(Self {
+            host: insp_ip.to_string(),
+            username: username.to_string(),
+            password: password.map(str::to_string),
+            private_key_path: private_key_path.map(str::to_string),
+            idle: Mutex::new(vec![session]),
+        })
+    }
+
+    /// 待機中のセッションを取り出す（使えるセッションが無い場合は新しくログインする）
+    fn checkout(&self) -> Result<SftpSession, String> {
+        loop {
+            let idle = self.idle.lock().unwrap_or_else(|e| e.into_inner()).pop();
+            match idle {
+                Some(session) if session.is_alive() => return Ok(session),
+                Some(_) => continue,
+                None => {
+                    return SftpSession::login(
+                        &self.host,
+                        &self.username,
+                        self.password.as_deref(),
+                        self.private_key_path.as_deref(),
+                    )
+                }
+            }
+        }
+    }
+
+    /// 使い終わったセッションをプールに戻す（上限を超える分は切断する）
+    fn checkin(&self, session: SftpSession) {
+        let mut idle = self.idle.lock().unwrap_or_else(|e| e.into_inner());
+        if idle.len() < MAX_IDLE_SFTP_SESSIONS {
+            idle.push(session);
+        }
+    }
+}
+
+impl SourceAccess for SftpSource {
+    fn display_path(&self, path: &str) -> String {
+        format!("sftp://{}/{}", self.host, path.trim_start_matches('/'))
+    }
+
+    fn list(&self, path: &str) -> Result<Vec<SourceEntry>, String> {
+        let session = self.checkout()?;
+        let entries = session
+            .sftp
+            .readdir(Path::new(path))
+            .map_err(|e| format!("ディレクトリ読み込みエラー {}: {}", self.display_path(path), e))?;
+        self.checkin(session);
+
+        Ok(entries
+            .into_iter()
+            .filter_map(|(entry_path, stat)| {
+                let name = entry_path.file_name()?.to_string_lossy().to_string();
+                Some(SourceEntry {
+                    name,
+                    is_dir: stat.is_dir(),
+                    size: stat.size.unwrap_or(0),
+                    // SFTPでは作成日時を取得できない、書き込み権限が無いファイルを読み取り専用とみなす
+                    attributes: FileAttributes {
+                        modified: stat.mtime.map(|mtime| UNIX_EPOCH + Duration::from_secs(mtime)),
+                        created: None,
+                        readonly: stat.perm.is_some_and(|perm| perm & 0o222 == 0),
+                    },
+                })
+            })
+            .collect())
+    }
+
+    fn open(&self, path: &str) -> Result<Box<dyn Read + '_>, String> {
+        let session = self.checkout()?;
+        let file = session
+            .sftp
+            .open(Path::new(path))
+            .map_err(|e| format!("ファイル読み込みエラー {}: {}", self.display_path(path), e))?;
+        Ok(Box::new(SftpFileReader {
+            file: Some(file),
+            session: Some(session),
+            source: self,
+        }))
+    }
+}
+
+/// SFTPのファイル（閉じたらセッションをプールに戻す）
+struct SftpFileReader<'a> {
+    file: Option<ssh2::File>,
+    session: Option<SftpSession>,
+    source: &'a SftpSource,
+}
+
+impl Read for SftpFileReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self.file.as_mut() {
+            Some(file) => file.read(buf),
+            None => Ok(0),
+        }
+    }
+}
+
+impl Drop for SftpFileReader<'_> {
+    fn drop(&mut self) {
+        // ファイルを閉じてからセッションを戻す
+        self.file.take();
+        if let Some(session) = self.session.take() {
+            self.source.checkin(session);
+        }
+    }
+}
+
+/// プールに残しておく待機中のFTP制御接続の上限
+const MAX_IDLE_FTP_CONNECTIONS: usize = 4;
+
+/// FTPの制御接続
+struct FtpControl {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    supports_mlsd: bool,
+    supports_mdtm: bool,
+}
+
+impl FtpControl {
+    /// 接続してログイン
+    fn login(insp_ip: &str, username: &str, password: &str) -> Result<Self, String> {
+        let stream = connect_tcp(insp_ip, FTP_PORT)?;
+        let writer = stream.try_clone().map_err(|e| format!("FTP接続エラー: {}", e))?;
+        let mut control = FtpControl {
+            reader: BufReader::new(stream),
+            writer,
+            supports_mlsd: false,
+            supports_mdtm: false,
+        };
+
+        let (code, message) = control.read_reply()?;
+        if code != 220 {
+            return Err(format!("FTPサーバーの応答が不正です: {}", message));
+        }
+
+        let (code, message) = control.command(&format!("USER {}", username))?;
+        match code {
+            230 => {}
+            331 => {
+                control.expect(&format!("PASS {}", password), &[230, 202])?;
+            }
+            _ => return Err(format!("FTPログインエラー {}@{}: {}", username, insp_ip, message)),
+        }
+
+        control.expect("TYPE I", &[200])?;
+        // UTF-8のファイル名に対応していれば有効にする（未対応でも続行）
+        control.command("OPTS UTF8 ON")?;
+
+        let (code, features) = control.command("FEAT")?;
+        let has_feature = |name: &str| {
+            code == 211
+                && features
+                    .lines()
+                    .any(|line| line.trim().to_uppercase().starts_with(name))
+        };
+        control.supports_mlsd = has_feature("MLSD") || has_feature("MLST");
+        control.supports_mdtm = has_feature("MDTM");
+
+        Ok(control)
+    }
+
+    /// 応答を読み込む（複数行応答に対応）
+    fn read_reply(&mut self) -> Result<(u16, String), String> {
+        let mut line = String::new();
+        self.reader
+            .read_line(&mut line)
+            .map_err(|e| format!("FTP応答の読み込みエラー: {}", e))?;
+        let code: u16 = line
+            .get(..3)
+            .and_then(|c| c.parse().ok())
+            .ok_or(format!("FTP応答が不正です: {}", line.trim_end()))?;
+        let mut message = line.clone();
+
+        // "123-" で始まる場合は "123 " の行まで続く
+        if line.as_bytes().get(3) == Some(&b'-') {
+            let terminator = format!("{} ", code);
+            loop {
+                line.clear();
+                let read = self
+                    .reader
+                    .read_line(&mut line)
+                    .map_err(|e| format!("FTP応答の読み込みエラー: {}", e))?;
+                if read == 0 {
+                    break;
+                }
+                message.push_str(&line);
+                if line.starts_with(&terminator) {
+                    break;
+                }
+            }
+        }
+
+        Ok((code, message.trim_end().to_string()))
+    }
+
+    /// コマンドを送信して応答を読み込む
+    fn command(&mut self, command: &str) -> Result<(u16, String), String> {
+        self.writer
+            .write_all(format!("{}\r\n", command).as_bytes())
+            .map_err(|e| format!("FTPコマンド送信エラー: {}", e))?;
+        self.read_reply()
+    }
+
+    /// コマンドを送信し、期待する応答コードでなければエラー
+    fn expect(&mut self, command: &str, expected: &[u16]) -> Result<String, String> {
+        let (code, message) = self.command(command)?;
+        if expected.contains(&code) {
+            Ok(message)
+        } else {
+            // パスワードはログに残さない
+            let command_name = command.split_whitespace().next().unwrap_or_default();
+            Err(format!("FTPコマンド {} が失敗しました: {}", command_name, message))
+        }
+    }
+
+    /// パッシブモードでデータ接続を開く
+    fn open_data_connection(&mut self) -> Result<TcpStream, String> {
+        let peer_ip = self
+            .writer
+            .peer_addr()
+            .map_err(|e| format!("FTP接続先の取得エラー: {}", e))?
+            .ip();
+
+        // EPSV: 229 Entering Extended Passive Mode (|||port|)
+        let (code, message) = self.command("EPSV")?;
+        let port = if code == 229 {
+            message
+                .split('|')
+                .nth(3)
+                .and_then(|p| p.parse::<u16>().ok())
+                .ok_or(format!("EPSV応答が不正です: {}", message))?
+        } else {
+            // PASV: 227 Entering Passive Mode (h1,h2,h3,h4,p1,p2)
+            let message = self.expect("PASV", &[227])?;
+            let numbers: Vec<u16> = message
+                .split(['(', ')'])
+                .nth(1)
+                .ok_or(format!("PASV応答が不正です: {}", message))?
+                .split(',')
+                .filter_map(|n| n.trim().parse().ok())
+                .collect();
+            if numbers.len() != 6 {
+                return Err(format!("PASV応答が不正です: {}", message));
+            }
+            numbers[4] * 256 + numbers[5]
+        };
+
+        // NAT越しでも接続できるよう、応答のアドレスではなく制御接続の接続先を使う
+        let stream = TcpStream::connect_timeout(&(peer_ip, port).into(), REMOTE_TIMEOUT)
+            .map_err(|e| format!("FTPデータ接続エラー: {}", e))?;
+        stream.set_read_timeout(Some(REMOTE_TIMEOUT)).ok();
+        Ok(stream)
+    }
+
+    /// ディレクトリ直下のエントリ一覧を取得
+    /// MLSDに対応していないサーバーでは、LISTの日時は形式・精度がサーバーごとに異なるため使わず、ファイルの更新日時をMDTMで取得する
+    fn list(&mut self, path: &str) -> Result<Vec<SourceEntry>, String> {
+        let mut data = self.open_data_connection()?;
+
+        let command = if self.supports_mlsd { "MLSD" } else { "LIST" };
+        self.expect(&format!("{} {}", command, path), &[125, 150])?;
+
+        let mut listing = String::new();
+        data.read_to_string(&mut listing)
+            .map_err(|e| format!("FTP一覧の読み込みエラー {}: {}", path, e))?;
+        drop(data);
+        self.read_reply()?;
+
+        let mut entries: Vec<SourceEntry> = listing
+            .lines()
+            .filter_map(|line| {
+                if self.supports_mlsd {
+                    parse_mlsd_line(line)
+                } else {
+                    parse_list_line(line)
+                }
+            })
+            .filter(|entry| entry.name != "." && entry.name != "..")
+            .collect();
+
+        if !self.supports_mlsd && self.supports_mdtm {
+            for entry in entries.iter_mut().filter(|entry| !entry.is_dir) {
+                let (code, message) = self.command(&format!("MDTM {}", join_source_path(path, &entry.name)))?;
+                if code == 213 {
+                    entry.attributes.modified = message.get(4..).and_then(parse_ftp_time);
+                }
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// 接続が使えるか確認（プールで待機中にサーバーから切断されている場合がある）
+    fn is_alive(&mut self) -> bool {
+        matches!(self.command("NOOP"), Ok((200, _)))
+    }
+}
+
+/// FTPで公開されている検査機器
+/// 制御接続は転送ごとに使い、使い終わったらプールに戻す（ファイルの読み込み中も他のロットの一覧取得・読み込みができる）
+pub struct FtpSource {
+    host: String,
+    address: String,
+    username: String,
+    password: String,
+    idle: Mutex<Vec<FtpControl>>,   // 待機中の制御接続
+}
+
+impl FtpSource {
+    pub fn connect(insp_ip: &str, username: &str, password: &str) -> Result<Self, String> {
+        let control = FtpControl::login(insp_ip, username, password)?;
+        if !control.supports_mlsd && !control.supports_mdtm {
+            // 更新日時で比べるカテゴリ直下のファイルは毎回コピーし、日付の基準がフォルダの更新日時の場合はバックアップ日時を使う
+            log::warn!("FTPサーバー {} はMLSD・MDTMに対応していないため、コピー元の更新日時を取得できません", insp_ip);
+        }
+
+        let host = parse_host_address(insp_ip)
+            .map(|address| address.host)
+            .unwrap_or_else(|_| insp_ip.to_string());
+
+        Ok(Self {
+            host,
+            address: insp_ip.to_string(),
+            username: username.to_string(),
+            password: password.to_string(),
+            idle: Mutex::new(vec![control]),
+        })
+    }
+
+    /// 待機中の制御接続を取り出す（使える接続が無い場合は新しくログインする）
+    fn checkout(&self) -> Result<FtpControl, String> {
+        loop {
+            let idle = self.idle.lock().unwrap_or_else(|e| e.into_inner()).pop();
+            match idle {
+                Some(mut control) => {
+                    if control.is_alive() {
+                        return Ok(control);
+                    }
+                }
+                None => return FtpControl::login(&self.address, &self.username, &self.password),
+            }
+        }
+    }
+
+    /// 使い終わった制御接続をプールに戻す（上限を超える分は切断する）
+    fn checkin(&self, control: FtpControl) {
+        let mut idle = self.idle.lock().unwrap_or_else(|e| e.into_inner());
+        if idle.len() < MAX_IDLE_FTP_CONNECTIONS {
+            idle.push(control);
+        }
+    }
+}
+
+impl SourceAccess for FtpSource {
+    fn display_path(&self, path: &str) -> String {
+        format!("ftp://{}/{}", self.host, path.trim_start_matches('/'))
+    }
+
+    fn list(&self, path: &str) -> Result<Vec<SourceEntry>, String> {
+        let mut control = self.checkout()?;
+        let entries = control.list(path)?;
+        // 失敗した接続は状態が分からないためプールに戻さない
+        self.checkin(control);
+        Ok(entries)
+    }
+
+    fn open(&self, path: &str) -> Result<Box<dyn Read + '_>, String> {
+        let mut control = self.checkout()?;
+        let data = control.open_data_connection()?;
+        control.expect(&format!("RETR {}", path), &[125, 150])?;
+        Ok(Box::new(FtpFileReader {
+            data: Some(data),
+            control: Some(control),
+            source: self,
+        }))
+    }
+}
+
+/// RETRのデータ接続（読み終わったら転送完了の応答を読み込み、制御接続をプールに戻す）
+struct FtpFileReader<'a> {
+    data: Option<TcpStream>,
+    control: Option<FtpControl>,
+    source: &'a FtpSource,
+}
+
+impl Read for FtpFileReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self.data.as_mut() {
+            Some(data) => data.read(buf),
+            None => Ok(0),
+        }
+    }
+}
+
+impl Drop for FtpFileReader<'_> {
+    fn drop(&mut self) {
+        if let Some(data) = self.data.take() {
+            data.shutdown(Shutdown::Both).ok();
+        }
+        let Some(mut control) = self.control.take() else {
+            return;
+        };
+        // 226（転送完了）または途中で閉じた場合の426を読み捨てる
+        match control.read_reply() {
+            Ok(_) => self.source.checkin(control),
+            Err(e) => log::warn!("{}", e),
+        }
+    }
+}
+
+/// FTPの日時（YYYYMMDDHHMMSS[.sss]、UTC）を解析
+fn parse_ftp_time(value: &str) -> Option<SystemTime> {
+    let value = value.trim();
+    NaiveDateTime::parse_from_str(value.split('.').next().unwrap_or(value), "%Y%m%d%H%M%S")
+        .ok()
+        .and_then(|time| u64::try_from(time.and_utc().timestamp()).ok())
+        .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+/// MLSDの1行を解析（例: "type=dir;modify=20240101000000; LOT001"）
+fn parse_mlsd_line(line: &str) -> Option<SourceEntry> {
+    let (facts, name) = line.split_once(' ')?;
+    let mut is_dir = false;
+    let mut is_file = false;
+    let mut size = 0;
+    let mut modified = None;
+    for fact in facts.split(';') {
+        match fact.split_once('=') {
+            Some((key, value)) if key.eq_ignore_ascii_case("type") => {
+                is_dir = value.eq_ignore_ascii_case("dir");
+                is_file = value.eq_ignore_ascii_case("file");
+            }
+            Some((key, value)) if key.eq_ignore_ascii_case("size") => {
+                size = value.parse().unwrap_or(0);
+            }
+            Some((key, value)) if key.eq_ignore_ascii_case("modify") => {
+                modified = parse_ftp_time(value);
+            }
+            _ => {}
+        }
+    }
+    // cdir・pdirなどは除外
+    if !is_dir && !is_file {
+        return None;
+    }
+    Some(SourceEntry {
+        name: name.to_string(),
+        is_dir,
+        size,
+        attributes: FileAttributes { modified, ..FileAttributes::default() },
+    })
+}
+
+/// LISTの1行を解析（UNIX形式・DOS形式）
+/// UNIX: "drwxr-xr-x 2 user group 4096 Jan  1 00:00 LOT001"
+/// DOS:  "01-01-24  12:00AM       <DIR>          LOT001"
+/// 日時は形式・精度がサーバーごとに異なるため更新日時は取得しない（ファイルはMDTMで取得する）
+fn parse_list_line(line: &str) -> Option<SourceEntry> {
+    let mut rest = line.trim_start();
+    let mut fields = Vec::with_capacity(8);
+
+    let field_count = if rest.starts_with(|c: char| c.is_ascii_digit()) { 3 } else { 8 };
+    for _ in 0..field_count {
+        let end = rest.find(char::is_whitespace)?;
+        fields.push(&rest[..end]);
+        rest = rest[end..].trim_start();
+    }
+    let name = rest.to_string();
+    if name.is_empty() {
+        return None;
+    }
+
+    if field_count == 3 {
+        let is_dir = fields[2] == "<DIR>";
+        let size = if is_dir { 0 } else { fields[2].parse().ok()? };
+        Some(SourceEntry { name, is_dir, size, attributes: FileAttributes::default() })
+    } else {
+        let is_dir = fields[0].starts_with('d');
+        if !is_dir && !fields[0].starts_with('-') {
+            // シンボリックリンクなどは除外
+            return None;
+        }
+        let size = fields[4].parse().unwrap_or(0);
+        Some(SourceEntry { name, is_dir, size, attributes: FileAttributes::default() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(time: Option<SystemTime>) -> Option<u64> {
+        time.and_then(|time| time.duration_since(UNIX_EPOCH).ok()).map(|duration| duration.as_secs())
+    }
+
+    #[test]
+    fn parses_mlsd_dir_and_file() {
+        let dir = parse_mlsd_line("type=dir;modify=20240101000000; LOT001").unwrap();
+        assert_eq!(dir.name, "LOT001");
+        assert!(dir.is_dir);
+        assert_eq!(secs(dir.attributes.modified), Some(1_704_067_200));
+
+        let file = parse_mlsd_line("Type=file;Size=1234;Modify=20240101000001.500;perm=r; img 0001.jpg").unwrap();
+        assert_eq!(file.name, "img 0001.jpg");
+        assert!(!file.is_dir);
+        assert_eq!(file.size, 1234);
+        assert_eq!(secs(file.attributes.modified), Some(1_704_067_201));
+    }
+
+    #[test]
+    fn skips_mlsd_cdir_and_pdir() {
+        assert!(parse_mlsd_line("type=cdir;modify=20240101000000; .").is_none());
+        assert!(parse_mlsd_line("type=pdir;modify=20240101000000; ..").is_none());
+        assert!(parse_mlsd_line("no-facts").is_none());
+    }
+
+    #[test]
+    fn parses_unix_list_lines() {
+        let dir = parse_list_line("drwxr-xr-x 2 user group 4096 Jan  1 00:00 LOT001").unwrap();
+        assert_eq!(dir.name, "LOT001");
+        assert!(dir.is_dir);
+
+        let file = parse_list_line("-rw-r--r-- 1 user group 52428800 Jan  1  2024 image 0001.jpg").unwrap();
+        assert_eq!(file.name, "image 0001.jpg");
+        assert!(!file.is_dir);
+        assert_eq!(file.size, 52_428_800);
+        // LISTの日時は使わない
+        assert_eq!(file.attributes.modified, None);
+    }
+
+    #[test]
+    fn parses_dos_list_lines() {
+        let dir = parse_list_line("01-01-24  12:00AM       <DIR>          LOT001").unwrap();
+        assert_eq!(dir.name, "LOT001");
+        assert!(dir.is_dir);
+        assert_eq!(dir.size, 0);
+
+        let file = parse_list_line("01-01-24  12:00AM              1234 result.csv").unwrap();
+        assert_eq!(file.name, "result.csv");
+        assert!(!file.is_dir);
+        assert_eq!(file.size, 1234);
+    }
+
+    #[test]
+    fn skips_symlinks_and_malformed_list_lines() {
+        assert!(parse_list_line("lrwxrwxrwx 1 user group 7 Jan  1 00:00 link -> LOT001").is_none());
+        assert!(parse_list_line("total 12").is_none());
+        assert!(parse_list_line("").is_none());
+        assert!(parse_list_line("01-01-24  12:00AM  notasize result.csv").is_none());
+    }
+
+    #[test]
+    fn parses_mdtm_time() {
+        assert_eq!(secs(parse_ftp_time("20240101000000")), Some(1_704_067_200));
+        assert_eq!(secs(parse_ftp_time(" 20240101000000.123 ")), Some(1_704_067_200));
+        assert_eq!(parse_ftp_time("2024-01-01"), None);
+    }
+}
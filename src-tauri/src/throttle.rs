@@ -0,0 +1,406 @@
+use std::io::Read;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::{Local, NaiveTime};
+
+use crate::types::{InspConfig, SettingsConfig};
+
+/// 一度に待機する最大時間（上限の変更がすぐに反映されるようにする）
+const MAX_WAIT: Duration = Duration::from_millis(500);
+/// 適応制御で速度を変更する最短間隔
+const ADAPT_INTERVAL: Duration = Duration::from_secs(1);
+/// 読み込み遅延の移動平均の重み
+const LATENCY_EWMA_WEIGHT: f64 = 0.2;
+/// 読み込み遅延が基準のこの倍率を超えたら速度を下げる
+const BACKOFF_LATENCY_RATIO: f64 = 3.0;
+/// 読み込み遅延が基準のこの倍率を下回ったら速度を戻す
+const RECOVER_LATENCY_RATIO: f64 = 1.5;
+/// 適応制御で下げる速度の下限（上限に対する割合）
+const MIN_ADAPTIVE_FACTOR: f64 = 0.125;
+
+/// Mbps（メガビット毎秒）をバイト毎秒に変換（0は無制限）
+pub fn mbps_to_bytes_per_sec(mbps: u64) -> u64 {
+    mbps.saturating_mul(1_000_000) / 8
+}
+
+/// 転送速度の上限（トークンバケット方式）
+pub struct RateLimiter {
+    state: Mutex<LimiterState>,
+}
+
+struct LimiterState {
+    bytes_per_sec: u64,     // 0は無制限
+    available: f64,         // 待たずに転送できるバイト数
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    /// 新しいRateLimiterインスタンスを作成
+    pub fn new(bytes_per_sec: u64) -> Self {
+        Self {
+            state: Mutex::new(LimiterState {
+                bytes_per_sec,
+                available: 0.0,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    /// 上限を変更
+    pub fn set_rate(&self, bytes_per_sec: u64) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.bytes_per_sec != bytes_per_sec {
+            state.bytes_per_sec = bytes_per_sec;
+            state.available = 0.0;
+            state.last_refill = Instant::now();
+        }
+    }
+
+    /// 現在の上限（0は無制限）
+    pub fn rate(&self) -> u64 {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).bytes_per_sec
+    }
+
+    /// 上限が設定されているか
+    pub fn is_limited(&self) -> bool {
+        self.rate() > 0
+    }
+
+    /// 指定バイト数を転送できるまで待機
+    pub fn acquire(&self, bytes: u64) {
+        let mut remaining = bytes as f64;
+
+        loop {
+            let wait = {
+                let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+                if state.bytes_per_sec == 0 {
+                    return;
+                }
+
+                // 経過時間分を補充（バーストは1秒分まで）
+                let rate = state.bytes_per_sec as f64;
+                let now = Instant::now();
+                let refill = now.duration_since(state.last_refill).as_secs_f64() * rate;
+                state.available = (state.available + refill).min(rate);
+                state.last_refill = now;
+
+                let taken = remaining.min(state.available);
+                state.available -= taken;
+                remaining -= taken;
+                if remaining <= 0.0 {
+                    return;
+                }
+
+                Duration::from_secs_f64(remaining.min(rate) / rate).min(MAX_WAIT)
+            };
+
+            thread::sleep(wait);
+        }
+    }
+}
+
+/// 読み込み遅延に応じた速度の自動調整の状態
+struct AdaptiveState {
+    latency_ewma: Option<f64>,      // 1バイトあたりの読み込み時間の移動平均（秒）
+    baseline: Option<f64>,          // 観測した移動平均の最小値（負荷がない時の遅延とみなす）
+    factor: f64,                    // 上限に掛ける割合（MIN_ADAPTIVE_FACTOR〜1.0）
+    peak_bytes_per_sec: f64,        // 上限が無制限の場合の基準にする観測最大速度
+    window_start: Instant,
+    window_bytes: u64,
+    last_adjusted: Instant,
+}
+
+impl AdaptiveState {
+    fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            latency_ewma: None,
+            baseline: None,
+            factor: 1.0,
+            peak_bytes_per_sec: 0.0,
+            window_start: now,
+            window_bytes: 0,
+            last_adjusted: now,
+        }
+    }
+}
+
+/// 検査機器1台分の転送速度の制御（全体の上限・検査機器ごとの上限・読み込み遅延による自動調整）
+pub struct Throttle {
+    global: Arc<RateLimiter>,
+    device: RateLimiter,
+    adaptive: Option<(RateLimiter, Mutex<AdaptiveState>)>,
+    device_bytes_per_sec: u64,
+    production_bytes_per_sec: u64,
+    off_hours_bytes_per_sec: u64,
+    production_hours: Option<(NaiveTime, NaiveTime)>,
+}
+
+impl Throttle {
+    /// 設定から検査機器の転送速度の制御を作成（globalは全検査機器で共有する）
+    pub fn new(settings: &SettingsConfig, insp_config: &InspConfig, global: Arc<RateLimiter>) -> Self {
+        let parse_time = |time: &str| NaiveTime::parse_from_str(time, "%H:%M").ok();
+        let production_hours = match (
+            parse_time(&settings.production_start_time),
+            parse_time(&settings.production_end_time),
+        ) {
+            (Some(start), Some(end)) => Some((start, end)),
+            _ => {
+                log::warn!(
+                    "生産時間の設定が不正です ({} - {})。生産時間内の上限を常に使用します",
+                    settings.production_start_time,
+                    settings.production_end_time
+                );
+                None
+            }
+        };
+
+        let device_bytes_per_sec = mbps_to_bytes_per_sec(insp_config.bandwidth_limit_mbps);
+        let adaptive = settings
+            .adaptive_throttle
+            .then(|| (RateLimiter::new(0), Mutex::new(AdaptiveState::new())));
+
+        let throttle = Self {
+            global,
+            device: RateLimiter::new(device_bytes_per_sec),
+            adaptive,
+            device_bytes_per_sec,
+            production_bytes_per_sec: mbps_to_bytes_per_sec(settings.bandwidth_limit_mbps),
+            off_hours_bytes_per_sec: mbps_to_bytes_per_sec(settings.off_hours_bandwidth_limit_mbps),
+            production_hours,
+        };
+        throttle.refresh();
+        throttle
+    }
+
+    /// 現在時刻が生産時間内か
+    fn is_production_hours(&self) -> bool {
+        self.is_production_time(Local::now().time())
+    }
+
+    /// 時刻が生産時間内か（終了時刻が開始時刻より前の場合は日をまたぐ）
+    fn is_production_time(&self, now: NaiveTime) -> bool {
+        let Some((start, end)) = self.production_hours else {
+            return true;
+        };
+        if start <= end {
+            start <= now && now < end
+        } else {
+            now >= start || now < end
+        }
+    }
+
+    /// 全体の上限を現在時刻（生産時間内・外）に合わせて更新
+    pub fn refresh(&self) {
+        let global_bytes_per_sec = if self.is_production_hours() {
+            self.production_bytes_per_sec
+        } else {
+            self.off_hours_bytes_per_sec
+        };
+        self.global.set_rate(global_bytes_per_sec);
+    }
+
+    /// 転送速度の制御（全体・検査機器ごとの上限、読み込み遅延による自動調整）がかかっているか
+    /// かかっている場合はOSのコピー機能を使わず、読み込みを制御できるストリームで転送する
+    pub fn is_limited(&self) -> bool {
+        self.adaptive.is_some() || self.global.is_limited() || self.device.is_limited()
+    }
+
+    /// 読み込みに転送速度の制御をかける
+    pub fn reader<'a>(&'a self, inner: Box<dyn Read + 'a>) -> ThrottledReader<'a> {
+        ThrottledReader { inner, throttle: self }
+    }
+
+    /// 読み込み1回分の結果を反映し、上限を超えないように待機
+    fn on_read(&self, bytes: usize, latency: Duration) {
+        if bytes == 0 {
+            return;
+        }
+        if let Some((limiter, state)) = &self.adaptive {
+            self.adapt(limiter, state, bytes as u64, latency);
+            limiter.acquire(bytes as u64);
+        }
+        self.global.acquire(bytes as u64);
+        self.device.acquire(bytes as u64);
+    }
+
+    /// コピー元の読み込み遅延が増えたら速度を下げ、戻ったら速度を戻す
+    fn adapt(&self, limiter: &RateLimiter, state: &Mutex<AdaptiveState>, bytes: u64, latency: Duration) {
+        let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+
+        // 1バイトあたりの読み込み時間の移動平均
+        let sample = latency.as_secs_f64() / bytes as f64;
+        let ewma = match state.latency_ewma {
+            Some(ewma) => ewma + LATENCY_EWMA_WEIGHT * (sample - ewma),
+            None => sample,
+        };
+        state.latency_ewma = Some(ewma);
+        let baseline = state.baseline.map_or(ewma, |baseline| baseline.min(ewma));
+        state.baseline = Some(baseline);
+
+        // 無制限の場合の基準にする速度を計測
+        state.window_bytes += bytes;
+        let window_secs = now.duration_since(state.window_start).as_secs_f64();
+        if window_secs >= 1.0 {
+            state.peak_bytes_per_sec = state.peak_bytes_per_sec.max(state.window_bytes as f64 / window_secs);
+            state.window_start = now;
+            state.window_bytes = 0;
+        }
+
+        if now.duration_since(state.last_adjusted) < ADAPT_INTERVAL {
+            return;
+        }
+
+        let previous_factor = state.factor;
+        if ewma > baseline * BACKOFF_LATENCY_RATIO {
+            state.factor = (state.factor * 0.5).max(MIN_ADAPTIVE_FACTOR);
+        } else if ewma < baseline * RECOVER_LATENCY_RATIO {
+            state.factor = (state.factor * 1.25).min(1.0);
+        }
+        state.last_adjusted = now;
+
+        if state.factor == previous_factor {
+            return;
+        }
+
+        // 設定された上限（未設定の場合は観測した最大速度）に割合を掛けたものを上限にする
+        let configured = [self.device_bytes_per_sec, self.global.rate()]
+            .into_iter()
+            .filter(|&rate| rate > 0)
+            .min()
+            .map_or(state.peak_bytes_per_sec, |rate| rate as f64);
+        let adaptive_rate = if state.factor >= 1.0 { 0 } else { (configured * state.factor) as u64 };
+
+        log::info!(
+            "コピー元の読み込み遅延により転送速度を調整しました: {:.0}% ({} B/s)",
+            state.factor * 100.0,
+            adaptive_rate
+        );
+        limiter.set_rate(adaptive_rate);
+    }
+}
+
+/// 転送速度の制御をかけた読み込み
+pub struct ThrottledReader<'a> {
+    inner: Box<dyn Read + 'a>,
+    throttle: &'a Throttle,
+}
+
+impl Read for ThrottledReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let started = Instant::now();
+        let bytes = self.inner.read(buf)?;
+        self.throttle.on_read(bytes, started.elapsed());
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(time: &str) -> NaiveTime {
+        NaiveTime::parse_from_str(time, "%H:%M").unwrap()
+    }
+
+    /// 上限・生産時間を指定した転送速度の制御
+    fn throttle(global: u64, device: u64, adaptive: bool, production_hours: Option<(&str, &str)>) -> Throttle {
+        Throttle {
+            global: Arc::new(RateLimiter::new(global)),
+            device: RateLimiter::new(device),
+            adaptive: adaptive.then(|| (RateLimiter::new(0), Mutex::new(AdaptiveState::new()))),
+            device_bytes_per_sec: device,
+            production_bytes_per_sec: global,
+            off_hours_bytes_per_sec: 0,
+            production_hours: production_hours.map(|(start, end)| (time(start), time(end))),
+        }
+    }
+
+    #[test]
+    fn mbps_converts_to_bytes() {
+        assert_eq!(mbps_to_bytes_per_sec(0), 0);
+        assert_eq!(mbps_to_bytes_per_sec(8), 1_000_000);
+        assert_eq!(mbps_to_bytes_per_sec(u64::MAX), u64::MAX / 8);
+    }
+
+    #[test]
+    fn unlimited_limiter_does_not_wait() {
+        let limiter = RateLimiter::new(0);
+        let started = Instant::now();
+        limiter.acquire(u64::MAX);
+        assert!(started.elapsed() < Duration::from_millis(50));
+        assert!(!limiter.is_limited());
+    }
+
+    #[test]
+    fn limiter_waits_for_tokens() {
+        // 1MB/s で 200KB は約0.2秒
+        let limiter = RateLimiter::new(1_000_000);
+        let started = Instant::now();
+        limiter.acquire(200_000);
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(150), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_millis(600), "{:?}", elapsed);
+    }
+
+    #[test]
+    fn limiter_refill_is_capped_at_one_second() {
+        let limiter = RateLimiter::new(1_000_000);
+        thread::sleep(Duration::from_millis(1200));
+        // 1秒分（1MB）までは待たずに転送でき、超えた分は待つ
+        let started = Instant::now();
+        limiter.acquire(1_000_000);
+        assert!(started.elapsed() < Duration::from_millis(100));
+        limiter.acquire(100_000);
+        assert!(started.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[test]
+    fn set_rate_changes_limit() {
+        let limiter = RateLimiter::new(1_000);
+        limiter.set_rate(0);
+        assert_eq!(limiter.rate(), 0);
+        let started = Instant::now();
+        limiter.acquire(1_000_000);
+        assert!(started.elapsed() < Duration::from_millis(50));
+    }
+
+    #[test]
+    fn production_hours_within_a_day() {
+        let throttle = throttle(0, 0, false, Some(("08:00", "17:00")));
+        assert!(!throttle.is_production_time(time("07:59")));
+        assert!(throttle.is_production_time(time("08:00")));
+        assert!(throttle.is_production_time(time("16:59")));
+        assert!(!throttle.is_production_time(time("17:00")));
+    }
+
+    #[test]
+    fn production_hours_across_midnight() {
+        let throttle = throttle(0, 0, false, Some(("22:00", "06:00")));
+        assert!(throttle.is_production_time(time("22:00")));
+        assert!(throttle.is_production_time(time("23:59")));
+        assert!(throttle.is_production_time(time("00:00")));
+        assert!(throttle.is_production_time(time("05:59")));
+        assert!(!throttle.is_production_time(time("06:00")));
+        assert!(!throttle.is_production_time(time("21:59")));
+        assert!(!throttle.is_production_time(time("12:00")));
+    }
+
+    #[test]
+    fn invalid_production_hours_are_always_production() {
+        let throttle = throttle(0, 0, false, None);
+        assert!(throttle.is_production_time(time("03:00")));
+    }
+
+    #[test]
+    fn limited_when_any_control_is_active() {
+        assert!(!throttle(0, 0, false, None).is_limited());
+        assert!(throttle(1_000, 0, false, None).is_limited());
+        assert!(throttle(0, 1_000, false, None).is_limited());
+        // 読み込み遅延による自動調整もストリームで転送する必要がある
+        assert!(throttle(0, 0, true, None).is_limited());
+    }
+}
@@ -1,7 +1,9 @@
-use serde::{Deserialize,Serialize};
+use serde::{Deserialize,Serialize,Serializer};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+use crate::file_filter::{active_patterns, glob_match};
+
 /*jsonファイル読み込み用 */
 /// NAS設定基本情報
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -10,6 +12,80 @@ pub struct NasInfo {
     pub name: String,
     pub drive: String,
     pub nas_ip: String,
+    #[serde(default)]
+    pub backend: StorageBackendConfig,
+}
+
+/// NASのコピー先の種類（config.jsonで設定）
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StorageBackendConfig {
+    /// driveに設定したドライブ・UNCパス・マウントポイント・ディレクトリ
+    #[default]
+    Local,
+    /// driveに設定したSMB共有（UNCパス）にOS標準のSMBクライアントで資格情報を指定して接続（Windowsのみ）
+    Smb {
+        #[serde(default)]
+        username: Option<String>,
+        #[serde(default)]
+        password: Option<String>,
+    },
+    /// S3互換オブジェクトストレージ（nas_ipは接続チェック用に endpoint のホスト:ポートを設定）
+    S3 {
+        endpoint: String,       // 例: http://192.168.0.20:9000
+        #[serde(default)]
+        region: String,
+        bucket: String,
+        #[serde(default)]
+        prefix: String,
+        access_key: String,
+        secret_key: String,
+        #[serde(default)]
+        quota_bytes: Option<u64>,   // 容量の上限（未設定の場合は無制限）
+    },
+}
+
+impl StorageBackendConfig {
+    /// パスワード・シークレットキーを除いた設定（画面に送る場合に使う）
+    pub fn redacted(&self) -> Self {
+        match self {
+            Self::Local => Self::Local,
+            Self::Smb { username, .. } => Self::Smb { username: username.clone(), password: None },
+            Self::S3 { endpoint, region, bucket, prefix, access_key, quota_bytes, .. } => Self::S3 {
+                endpoint: endpoint.clone(),
+                region: region.clone(),
+                bucket: bucket.clone(),
+                prefix: prefix.clone(),
+                access_key: access_key.clone(),
+                secret_key: String::new(),
+                quota_bytes: *quota_bytes,
+            },
+        }
+    }
+
+    /// 画面から戻ってきた設定（パスワード・シークレットキーを除いたもの）に、現在の設定の認証情報を補う
+    /// 画面で入力し直した場合はその値を使う
+    pub fn with_secrets_from(self, current: &Self) -> Self {
+        match (self, current) {
+            (Self::Smb { username, password: None }, Self::Smb { password: current_password, .. }) => Self::Smb {
+                username,
+                password: current_password.clone(),
+            },
+            (
+                Self::S3 { endpoint, region, bucket, prefix, access_key, secret_key, quota_bytes },
+                Self::S3 { secret_key: current_secret_key, .. },
+            ) if secret_key.is_empty() => Self::S3 {
+                endpoint,
+                region,
+                bucket,
+                prefix,
+                access_key,
+                secret_key: current_secret_key.clone(),
+                quota_bytes,
+            },
+            (backend, _) => backend,
+        }
+    }
 }
 
 /// 外観設定基本情報
@@ -24,6 +100,85 @@ pub struct InspInfo {
     pub back_result_path: String,
     #[serde(default = "default_is_backup")]
     pub is_backup: bool,
+    #[serde(default)]
+    pub source: SourceAccessConfig,
+    #[serde(default)]
+    pub is_continuous: bool,
+    #[serde(default)]
+    pub bandwidth_limit_mbps: u64,
+    #[serde(default)]
+    pub file_filter: FileFilter,
+}
+
+/// 検査機器のコピー元へのアクセス方法（config.jsonで設定）
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SourceAccessConfig {
+    /// SMB共有（\\insp_ip\path）
+    #[default]
+    Unc,
+    /// バックアップPC上のパス（検査アプリがバックアップPC上で動作している場合）
+    Local,
+    /// SFTP（パスは検査機器上の絶対パス）
+    Sftp {
+        username: String,
+        #[serde(default)]
+        password: Option<String>,           // 公開鍵認証の場合は鍵のパスフレーズ
+        #[serde(default)]
+        private_key_path: Option<String>,   // 未設定かつpasswordも未設定の場合はSSHエージェントで認証
+    },
+    /// FTP（パスはFTPサーバー上の絶対パス）
+    Ftp {
+        #[serde(default)]
+        username: Option<String>,           // 未設定の場合はanonymous
+        #[serde(default)]
+        password: Option<String>,
+    },
+}
+
+impl SourceAccessConfig {
+    /// パスワードを除いた設定（画面に送る場合に使う）
+    pub fn redacted(&self) -> Self {
+        match self {
+            Self::Unc => Self::Unc,
+            Self::Local => Self::Local,
+            Self::Sftp { username, private_key_path, .. } => Self::Sftp {
+                username: username.clone(),
+                password: None,
+                private_key_path: private_key_path.clone(),
+            },
+            Self::Ftp { username, .. } => Self::Ftp { username: username.clone(), password: None },
+        }
+    }
+
+    /// 画面から戻ってきた設定（パスワードを除いたもの）に、現在の設定のパスワードを補う
+    /// 画面で入力し直した場合はその値を使う
+    pub fn with_secrets_from(self, current: &Self) -> Self {
+        match (self, current) {
+            (
+                Self::Sftp { username, password: None, private_key_path },
+                Self::Sftp { password: current_password, .. },
+            ) => Self::Sftp {
+                username,
+                password: current_password.clone(),
+                private_key_path,
+            },
+            (Self::Ftp { username, password: None }, Self::Ftp { password: current_password, .. }) => Self::Ftp {
+                username,
+                password: current_password.clone(),
+            },
+            (source, _) => source,
+        }
+    }
+}
+
+/// NAS設定・検査機器設定を画面に送る場合は認証情報を含めない（config.jsonへの保存はNasInfo・InspInfoで行う）
+fn serialize_redacted_backend<S: Serializer>(backend: &StorageBackendConfig, serializer: S) -> Result<S::Ok, S::Error> {
+    backend.redacted().serialize(serializer)
+}
+
+fn serialize_redacted_source<S: Serializer>(source: &SourceAccessConfig, serializer: S) -> Result<S::Ok, S::Error> {
+    source.redacted().serialize(serializer)
 }
 
 // デフォルト値としてtrueを返す関数
@@ -56,13 +211,19 @@ pub struct Configs {
 pub struct NasConfig {
     pub id: u32,
     pub name: String,
-    pub drive: String,
+    pub drive: String,      // コピー先ルート: ドライブレター（例: P）、UNCパス（例: \\192.168.0.10\share）、マウントポイント・ディレクトリ（例: /mnt/nas）
     pub nas_ip: String,
     pub is_use: bool,
     pub is_connected: bool,
     pub total_space: u64,
     pub used_space: u64,
     pub free_space: u64,
+    #[serde(default)]
+    pub mapping_error: Option<String>,  // ドライブの接続先が設定されたNASと異なる場合のエラー内容
+    #[serde(default)]
+    pub connection_error: Option<String>,   // アドレスが不正なため接続を確認できない場合のエラー内容
+    #[serde(default, serialize_with = "serialize_redacted_backend")]
+    pub backend: StorageBackendConfig,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -75,6 +236,33 @@ pub struct InspConfig {
     pub surface_result_path: String,
     pub back_result_path: String,
     pub is_backup: bool,
+    #[serde(default)]
+    pub last_successful_backup: Option<String>,         // 全カテゴリがエラーなしで完了した最終日時
+    #[serde(default)]
+    pub category_backups: Vec<CategoryBackupStatus>,    // カテゴリ単位の最終成功日時
+    #[serde(default)]
+    pub is_backup_stale: bool,                          // 最終成功から設定日数以上経過しているか
+    #[serde(default)]
+    pub is_connected: bool,                             // コピー元のポート（SMB・SFTP・FTP）に接続できるか
+    #[serde(default)]
+    pub connection_error: Option<String>,               // アドレスが不正なため接続を確認できない場合のエラー内容
+    #[serde(default)]
+    pub unreadable_paths: Vec<String>,                  // 読み込めなかったコピー元パス
+    #[serde(default, serialize_with = "serialize_redacted_source")]
+    pub source: SourceAccessConfig,                     // コピー元へのアクセス方法
+    #[serde(default)]
+    pub is_continuous: bool,                            // 連続バックアップ（ロットが確定し次第バックアップ）を行うか
+    #[serde(default)]
+    pub bandwidth_limit_mbps: u64,                      // この検査機器からの転送速度の上限(Mbps, 0で無制限)
+    #[serde(default)]
+    pub file_filter: FileFilter,                        // この検査機器のすべてのカテゴリに適用するファイルの絞り込み
+}
+
+/// カテゴリ単位の最終バックアップ成功日時
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CategoryBackupStatus {
+    pub category: String,
+    pub last_successful_backup: Option<String>,
 }
 
 /* ----------------------------- */
@@ -85,7 +273,192 @@ pub struct SettingsConfig{
     pub back_image_path:String,
     pub surface_result_file_path:String,
     pub back_result_file_path:String,
-    pub required_free_space:u64
+    pub required_free_space:u64,
+    #[serde(default = "default_stale_backup_days")]
+    pub stale_backup_days:u32,      // 最終成功からこの日数を超えたら警告(0で無効)
+    #[serde(default = "default_monitor_interval_secs")]
+    pub monitor_interval_secs:u64,  // NAS・検査機器の状態監視間隔(秒)
+    #[serde(default = "default_continuous_poll_secs")]
+    pub continuous_poll_secs:u64,   // 連続バックアップでコピー元の変化を確認する間隔(秒)
+    #[serde(default = "default_continuous_stable_secs")]
+    pub continuous_stable_secs:u64, // ロットがこの秒数変化しなければ確定したとみなす
+    #[serde(default)]
+    pub bandwidth_limit_mbps:u64,   // 生産時間内の全体の転送速度の上限(Mbps, 0で無制限)
+    #[serde(default)]
+    pub off_hours_bandwidth_limit_mbps:u64, // 生産時間外の全体の転送速度の上限(Mbps, 0で無制限)
+    #[serde(default = "default_production_start_time")]
+    pub production_start_time:String,   // 生産時間の開始時刻(HH:MM)
+    #[serde(default = "default_production_end_time")]
+    pub production_end_time:String,     // 生産時間の終了時刻(HH:MM, 開始より前の場合は翌日)
+    #[serde(default)]
+    pub adaptive_throttle:bool,     // コピー元の読み込み遅延が増えたら転送速度を自動で下げるか
+    #[serde(default = "default_max_parallel_devices")]
+    pub max_parallel_devices:u32,   // 同時にバックアップする検査機器の台数
+    #[serde(default = "default_max_parallel_lots_per_device")]
+    pub max_parallel_lots_per_device:u32,   // 1台の検査機器から同時にコピーするロット数
+    #[serde(default = "default_max_writers_per_nas")]
+    pub max_writers_per_nas:u32,    // 1台のNASに同時に書き込むロット数
+    #[serde(default = "default_copy_buffer_size_kib")]
+    pub copy_buffer_size_kib:u32,   // ストリームコピーで1回に読み込むサイズ(KiB)
+    #[serde(default = "default_copy_read_ahead_buffers")]
+    pub copy_read_ahead_buffers:u32,    // ストリームコピーで書き込みを待たずに先読みするバッファ数
+    #[serde(default)]
+    pub lot_versioning:bool,        // ロットを再コピーする際、上書きするファイルを世代フォルダに残すか
+    #[serde(default = "default_max_lot_versions")]
+    pub max_lot_versions:u32,       // ロットごとに残す世代数（古い世代から削除）
+    #[serde(default)]
+    pub surface_image_conflict_policy:ConflictPolicy,   // 表面画像のコピー先に同名ファイルがある場合の処理
+    #[serde(default)]
+    pub back_image_conflict_policy:ConflictPolicy,      // 裏面画像のコピー先に同名ファイルがある場合の処理
+    #[serde(default)]
+    pub surface_result_conflict_policy:ConflictPolicy,  // 表面結果ファイルのコピー先に同名ファイルがある場合の処理
+    #[serde(default)]
+    pub back_result_conflict_policy:ConflictPolicy,     // 裏面結果ファイルのコピー先に同名ファイルがある場合の処理
+    #[serde(default)]
+    pub identical_check:IdenticalCheck,  // 同名ファイルが同一かの判定方法
+    #[serde(default)]
+    pub root_file_handling:RootFileHandling,  // コピー元のカテゴリ直下（ロットフォルダの外）にあるファイルの扱い
+    #[serde(default)]
+    pub surface_image_file_filter:FileFilter,   // 表面画像のファイルの絞り込み
+    #[serde(default)]
+    pub back_image_file_filter:FileFilter,      // 裏面画像のファイルの絞り込み
+    #[serde(default)]
+    pub surface_result_file_filter:FileFilter,  // 表面結果ファイルのファイルの絞り込み
+    #[serde(default)]
+    pub back_result_file_filter:FileFilter,     // 裏面結果ファイルのファイルの絞り込み
+    #[serde(default = "default_dest_path_template")]
+    pub dest_path_template:String,          // 保存先のパスのテンプレート（例: {root}/{category}/{device}/{yyyy}/{mm}/{lot}）
+    #[serde(default)]
+    pub dest_date_source:DestDateSource,    // テンプレートの日付（{yyyy}・{mm}・{dd}）の基準
+    #[serde(default)]
+    pub lot_name_pattern:String             // ロット名から値を取り出す正規表現（グループを {名前}・{番号} としてテンプレートで使う）
+}
+
+/// コピー先に同名のファイルがある場合の処理
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    /// 常に上書き
+    #[default]
+    Overwrite,
+    /// 同一ならスキップ、異なれば上書き
+    SkipIfIdentical,
+    /// 同一ならスキップ、異なれば末尾に番号を付けた名前で両方残す
+    KeepBoth,
+    /// 同一ならスキップ、異なればそのファイルのコピーを失敗にする
+    Fail,
+}
+
+/// 同名のファイルが同一かの判定方法
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum IdenticalCheck {
+    /// サイズと更新日時が一致
+    #[default]
+    SizeAndMtime,
+    /// サイズとSHA-256が一致（コピー元・コピー先を読み込む）
+    Hash,
+}
+
+/// バックアップするファイルの絞り込み（検査機器・カテゴリごとに設定し、すべてを満たすファイルのみバックアップ）
+/// パターンはglob形式（/ を含まないパターンはファイル名、含むパターンはロットフォルダからの相対パスと比べる）
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct FileFilter {
+    #[serde(default)]
+    pub include: Vec<String>,       // いずれかに一致するファイルのみ対象（空の場合はすべて対象）
+    #[serde(default)]
+    pub exclude: Vec<String>,       // いずれかに一致するファイルは対象外（例: *.tmp, thumbs/**）
+    #[serde(default)]
+    pub max_file_size_mb: u64,      // これより大きいファイルは対象外(MB, 0で無制限)
+}
+
+impl FileFilter {
+    /// ファイル（ロットフォルダからの相対パス, サイズ）がバックアップ対象か
+    pub fn matches(&self, relative_path: &str, size: u64) -> bool {
+        if self.max_file_size_mb > 0 && size > self.max_file_size_mb.saturating_mul(1024 * 1024) {
+            return false;
+        }
+
+        let mut include = active_patterns(&self.include).peekable();
+        if include.peek().is_some() && !include.any(|pattern| glob_match(pattern, relative_path)) {
+            return false;
+        }
+        !active_patterns(&self.exclude).any(|pattern| glob_match(pattern, relative_path))
+    }
+}
+
+/// 保存先のテンプレートの日付（{yyyy}・{mm}・{dd}）の基準
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DestDateSource {
+    /// バックアップした日
+    #[default]
+    BackupDate,
+    /// コピー元のロットフォルダの更新日時（取得できない場合はバックアップした日）
+    FolderMtime,
+}
+
+/// コピー元のカテゴリ直下（ロットフォルダの外）にあるファイル（集計CSV・日次ログなど）の扱い
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RootFileHandling {
+    /// バックアップしない
+    #[default]
+    Ignore,
+    /// ロットフォルダとは別の保存先に、サイズ・更新日時が変わったファイルのみバックアップ
+    Backup,
+}
+
+fn default_stale_backup_days() -> u32 {
+    3
+}
+
+fn default_monitor_interval_secs() -> u64 {
+    10
+}
+
+fn default_continuous_poll_secs() -> u64 {
+    30
+}
+
+fn default_continuous_stable_secs() -> u64 {
+    300
+}
+
+fn default_max_parallel_devices() -> u32 {
+    4
+}
+
+fn default_max_parallel_lots_per_device() -> u32 {
+    1
+}
+
+fn default_max_writers_per_nas() -> u32 {
+    4
+}
+
+fn default_copy_buffer_size_kib() -> u32 {
+    1024
+}
+
+fn default_copy_read_ahead_buffers() -> u32 {
+    4
+}
+
+fn default_max_lot_versions() -> u32 {
+    5
+}
+
+fn default_dest_path_template() -> String {
+    crate::dest_template::DEFAULT_DEST_PATH_TEMPLATE.to_string()
+}
+
+fn default_production_start_time() -> String {
+    "08:00".to_string()
+}
+
+fn default_production_end_time() -> String {
+    "20:00".to_string()
 }
 
 /* バックアップ関連の型定義 */
@@ -96,16 +469,40 @@ pub struct BackupStatus {
     pub last_backup_date: Option<String>,
 }
 
-/// バックアップの進捗情報
+/// バックアップの進捗情報（実行全体）
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct BackupProgress {
+    pub current_files: u64,     // 処理済みファイル数（コピー成功+失敗）
+    pub total_files: u64,       // 実行開始時に計画したファイル数
+    pub current_size: u64,      // コピー済みバイト数
+    pub total_size: u64,        // 実行開始時に計画したバイト数
+    pub percentage: f32,
+    pub bytes_per_sec: f64,     // 転送速度
+    pub eta_secs: Option<u64>,  // 残り時間の見込み（速度が出るまではNone）
+    pub current_file: String,
+    pub current_device: String,
+    pub devices: Vec<DeviceProgress>,
+}
+
+/// 検査機器単位の進捗小計
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DeviceProgress {
+    pub device_name: String,
+    pub current_files: u64,
+    pub total_files: u64,
+    pub current_size: u64,
+    pub total_size: u64,
+    pub categories: Vec<CategoryProgress>,
+}
+
+/// カテゴリ（表面画像など）単位の進捗小計
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CategoryProgress {
+    pub category: String,
     pub current_files: u64,
     pub total_files: u64,
     pub current_size: u64,
     pub total_size: u64,
-    pub percentage: f32,
-    pub current_file: String,
-    pub current_device: String,
 }
 
 /// バックアップの結果
@@ -118,4 +515,264 @@ pub struct BackupResult {
     pub total_size_bytes: u64,
     pub duration_secs: u64,
     pub errors: Vec<String>,
-}
\ No newline at end of file
+    #[serde(default)]
+    pub devices: Vec<DeviceBackupResult>,
+}
+
+/// 検査機器単位のバックアップ結果
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DeviceBackupResult {
+    pub device_name: String,
+    pub success: bool,
+    #[serde(default)]
+    pub skipped_reason: Option<String>,             // 検査機器ごとスキップした場合の理由
+    pub total_files: u64,
+    pub copied_files: u64,
+    pub failed_files: u64,
+    pub total_size_bytes: u64,
+    pub errors: Vec<String>,
+    #[serde(default)]
+    pub duration_secs: u64,
+    #[serde(default)]
+    pub nas_used: Vec<String>,                      // 書き込みに使用したNAS名（容量不足で切り替えた場合は複数）
+    #[serde(default)]
+    pub categories: Vec<CategoryBackupResult>,
+}
+
+/// カテゴリ（表面画像など）単位のバックアップ結果
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CategoryBackupResult {
+    pub category: String,
+    pub source_path: String,
+    pub skipped_reason: Option<String>,             // カテゴリごとスキップした場合の理由
+    pub lots_found: u64,                            // コピー元にあったロットフォルダ数
+    pub lots_skipped: u64,                          // NASに既にあるためスキップしたロット数
+    pub lots_copied: u64,                           // エラーなしでコピーできたロット数
+    pub lots_failed: u64,                           // コピーに失敗したファイルを含むロット数
+    #[serde(default)]
+    pub root_files_found: u64,                      // コピー元のカテゴリ直下にあったファイル数
+    #[serde(default)]
+    pub root_files_skipped: u64,                    // NASにサイズ・更新日時が一致するファイルがあるためスキップしたカテゴリ直下のファイル数
+    pub total_files: u64,
+    pub copied_files: u64,
+    #[serde(default)]
+    pub skipped_files: u64,                         // コピー先に同一のファイルがあるためスキップしたファイル数
+    pub failed_files: u64,
+    pub total_size_bytes: u64,
+    pub duration_secs: u64,
+    pub nas_used: Option<String>,
+    pub errors: Vec<String>,
+}
+
+/* バックアップ履歴関連の型定義 */
+/// バックアップの実行契機
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupTrigger {
+    /// 設定時刻による定期実行
+    Scheduled,
+    /// 連続バックアップ（確定したロットのみ）
+    Continuous,
+}
+
+/// バックアップ実行の最終状態
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupRunStatus {
+    /// エラーなしで完了
+    Success,
+    /// 完了したが一部でエラーあり
+    Partial,
+    /// 途中で中断
+    Failed,
+}
+
+/// バックアップ履歴1件分
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BackupHistoryEntry {
+    pub id: u64,
+    pub start_time: String,
+    pub end_time: String,
+    pub trigger: BackupTrigger,
+    pub status: BackupRunStatus,
+    pub devices: Vec<String>,               // バックアップ対象だった検査機器名
+    pub result: Option<BackupResult>,       // 中断した場合はNone
+    pub errors: Vec<String>,
+}
+
+/// バックアップ履歴の検索条件
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BackupHistoryQuery {
+    #[serde(default)]
+    pub page: usize,
+    #[serde(default = "default_history_page_size")]
+    pub page_size: usize,
+    pub from_date: Option<String>,          // YYYY-MM-DD（開始日時がこの日以降）
+    pub to_date: Option<String>,            // YYYY-MM-DD（開始日時がこの日以前）
+    pub status: Option<BackupRunStatus>,
+    pub device_name: Option<String>,
+}
+
+fn default_history_page_size() -> usize {
+    20
+}
+
+/// バックアップ履歴の検索結果
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BackupHistoryPage {
+    pub total: usize,                       // 条件に一致した全件数
+    pub entries: Vec<BackupHistoryEntry>,   // 新しい順
+}
+
+/// NAS容量のサンプル（一定間隔で記録）
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CapacitySample {
+    pub timestamp: String,  // YYYY-MM-DD HH:MM:SS
+    pub total_space: u64,
+    pub used_space: u64,
+    pub free_space: u64,
+}
+
+/// NASの容量予測
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CapacityForecast {
+    pub nas_id: u32,
+    pub nas_name: String,
+    pub free_space: u64,
+    pub required_free_space: u64,
+    pub daily_growth_bytes: Option<f64>,    // 1日あたりの使用量の増加（推定不可の場合はNone）
+    pub days_until_full: Option<f64>,       // 空き容量がrequired_free_spaceに達するまでの日数（増加していない場合はNone）
+    pub sample_count: usize,                // 予測に使用したサンプル数
+    pub last_sample_time: Option<String>,
+}
+
+/// コピー方式ごとのスループット計測結果
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CopyBenchmarkResult {
+    pub method: String,                 // os_copy / buffered_read_ahead
+    pub files: u64,
+    pub total_size_bytes: u64,
+    pub duration_secs: f64,
+    pub throughput_mb_per_sec: f64,     // MB/s（1MB = 1,000,000バイト）
+}
+
+/// 複数のNASにある同じロットのうち1台分
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DuplicateLotCopy {
+    pub nas_id: u32,
+    pub nas_name: String,
+    pub folder_path: String,            // ロットがある保存先フォルダ（保存先のテンプレートの日付などでNASごとに異なる場合がある）
+    pub file_count: u32,
+}
+
+/// 複数のNASに重複しているロット
+/// 同じ検査機器・カテゴリの保存先フォルダ（保存先のテンプレートを展開したすべてのフォルダ）にある同名のロット
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DuplicateLot {
+    pub folder_path: String,            // 統合時に残すロットの保存先フォルダ（保存先のテンプレートの {lot} より前のパス）
+    pub lot_name: String,
+    pub copies: Vec<DuplicateLotCopy>,
+    pub keep_nas_id: u32,               // 統合時に残すNAS（ファイル数が最も多いNAS）
+}
+
+/// 重複ロットの統合結果
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ConsolidationResult {
+    pub lots_consolidated: u32,         // 1台のNASにまとめたロット数
+    pub copies_removed: u32,            // 世代フォルダに移した重複ロット数
+    pub files_merged: u32,              // 残すNASに不足していたためコピーしたファイル数
+    pub errors: Vec<String>,
+}
+
+/// ロットの世代（上書き前のファイルを退避したフォルダ）
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LotVersion {
+    pub version: String,                // 世代名（退避した日時のミリ秒まで YYYYMMDD_HHMMSS_mmm）
+    pub file_count: u32,
+    pub total_size_bytes: u64,
+}
+
+/// 世代が残っているロット
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VersionedLot {
+    pub folder_path: String,            // 保存先フォルダ（保存先のテンプレートの {lot} より前のパス）
+    pub lot_name: String,
+    pub versions: Vec<LotVersion>,      // 新しい順
+}
+
+/// ファイル1つ分のコピーの処理内容
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FileAction {
+    /// コピー先に無いためコピー
+    Copied,
+    /// コピー先のファイルを上書き
+    Overwritten,
+    /// コピー先に同一のファイルがあるためスキップ
+    SkippedIdentical,
+    /// コピー先のファイルを残し、番号を付けた名前でコピー
+    KeptBoth,
+    /// コピー先に異なるファイルがあるため失敗（競合時の処理が失敗）
+    Conflict,
+    /// コピーエラー
+    Failed,
+}
+
+/// ファイル単位のコピー記録（監査用）
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FileAuditEntry {
+    pub timestamp: String,              // YYYY-MM-DD HH:MM:SS
+    pub device_name: String,
+    pub category: String,
+    pub lot_name: String,
+    pub source_path: String,
+    pub dest_path: String,              // コピー先ルートからのパス（番号を付けた場合は付けた後の名前）
+    pub nas: String,                    // コピー先の名前
+    pub action: FileAction,
+    #[serde(default)]
+    pub attributes_verified: Option<bool>,  // 日時・読み取り専用属性を引き継げたか（コピーしていない・コピー先が対応していない場合はNone）
+    pub detail: Option<String>,         // エラー内容・引き継げなかった属性など
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacted_secrets_are_restored_from_current_settings() {
+        let s3 = |secret_key: &str| StorageBackendConfig::S3 {
+            endpoint: "http://192.168.0.20:9000".to_string(),
+            region: String::new(),
+            bucket: "images".to_string(),
+            prefix: String::new(),
+            access_key: "key".to_string(),
+            secret_key: secret_key.to_string(),
+            quota_bytes: None,
+        };
+        assert_eq!(s3("secret").redacted().with_secrets_from(&s3("secret")), s3("secret"));
+        assert_eq!(s3("new").with_secrets_from(&s3("secret")), s3("new"));
+
+        let smb = |password: Option<&str>| StorageBackendConfig::Smb {
+            username: Some("user".to_string()),
+            password: password.map(str::to_string),
+        };
+        assert_eq!(smb(None).with_secrets_from(&smb(Some("pass"))), smb(Some("pass")));
+        // 種類を変えた場合は現在の認証情報を引き継がない
+        assert_eq!(smb(None).with_secrets_from(&s3("secret")), smb(None));
+
+        let sftp = |password: Option<&str>| SourceAccessConfig::Sftp {
+            username: "user".to_string(),
+            password: password.map(str::to_string),
+            private_key_path: None,
+        };
+        assert_eq!(sftp(Some("pass")).redacted().with_secrets_from(&sftp(Some("pass"))), sftp(Some("pass")));
+        assert_eq!(sftp(Some("new")).with_secrets_from(&sftp(Some("pass"))), sftp(Some("new")));
+
+        let ftp = |password: Option<&str>| SourceAccessConfig::Ftp {
+            username: None,
+            password: password.map(str::to_string),
+        };
+        assert_eq!(ftp(None).with_secrets_from(&ftp(Some("pass"))), ftp(Some("pass")));
+        assert_eq!(ftp(None).with_secrets_from(&SourceAccessConfig::Unc), ftp(None));
+    }
+}
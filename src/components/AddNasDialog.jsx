@@ -108,13 +108,13 @@ export default function AddNasDialog({ isOpen, onClose}) {
             </div>
 
             <div>
-                <label className="block text-sm text-gray-700 mb-1">NAS ネットワークドライブ名</label>
+                <label className="block text-sm text-gray-700 mb-1">NAS ネットワークドライブ名 / UNCパス / マウントパス</label>
                 <input
                 type="text"
                 value={formData.drive}
                 onChange={(e) => setFormData({ ...formData, drive: e.target.value })}
                 className="w-full px-3 py-2 bg-gray-200 text-black rounded border border-gray-600 focus:border-blue-500 focus:outline-none"
-                placeholder="Z"
+                placeholder="例: Z、\\192.168.1.100\share、/mnt/nas"
                 required
                 />
             </div>
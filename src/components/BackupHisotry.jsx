@@ -99,6 +99,30 @@ export default function BackupHisotry() {
                                 </div>
                             </div>
 
+                            {/* 検査機器・カテゴリ別の内訳 */}
+                            {history.devices && history.devices.length > 0 && (
+                                <div className="mb-4 space-y-2">
+                                    {history.devices.map((device, deviceIndex) => (
+                                        <div key={deviceIndex} className="bg-gray-50 rounded p-3">
+                                            <div className="flex justify-between text-sm font-medium text-gray-800">
+                                                <span>{device.device_name}</span>
+                                                <span className={device.success ? 'text-green-700' : 'text-red-700'}>
+                                                    {device.copied_files} コピー / {device.failed_files} 失敗
+                                                    {device.nas_used && device.nas_used.length > 0 && ` (${device.nas_used.join(', ')})`}
+                                                </span>
+                                            </div>
+                                            {device.categories && device.categories.map((category, categoryIndex) => (
+                                                <p key={categoryIndex} className="text-xs text-gray-600 ml-2">
+                                                    {category.category}: {category.skipped_reason
+                                                        ? `スキップ (${category.skipped_reason})`
+                                                        : `ロット ${category.lots_found}件中 コピー${category.lots_copied} / スキップ${category.lots_skipped} / 失敗${category.lots_failed}${category.skipped_files ? ` (同一ファイルのスキップ ${category.skipped_files}件)` : ''}${category.root_files_found ? `, 直下のファイル ${category.root_files_found}件中 スキップ${category.root_files_skipped ?? 0}` : ''}, ${formatBytes(category.total_size_bytes)}`}
+                                                </p>
+                                            ))}
+                                        </div>
+                                    ))}
+                                </div>
+                            )}
+
                             {/* エラー内容（エラーがある場合のみ表示） */}
                             {history.errors && history.errors.length > 0 && (
                                 <div className="bg-red-50 rounded p-4 border border-red-200">
@@ -41,16 +41,30 @@ function BackupProgress() {
             {/* 詳細情報 */}
             <div className="space-y-2 text-sm">
               <div className="flex justify-between">
-                <span className="text-gray-600">フォルダ単位処理ファイル数</span>
+                <span className="text-gray-600">処理ファイル数</span>
                 <span className="font-semibold text-gray-900">
                   {backupProgress.current_files} / {backupProgress.total_files}
                 </span>
               </div>
 
               <div className="flex justify-between">
-                <span className="text-gray-600">フォルダ単位転送済データ量</span>
+                <span className="text-gray-600">転送済データ量</span>
                 <span className="font-semibold text-gray-900">
-                  {formatBytes(backupProgress.current_size)}
+                  {formatBytes(backupProgress.current_size)} / {formatBytes(backupProgress.total_size)}
+                </span>
+              </div>
+
+              <div className="flex justify-between">
+                <span className="text-gray-600">転送速度</span>
+                <span className="font-semibold text-gray-900">
+                  {(backupProgress.bytes_per_sec / (1024 * 1024)).toFixed(1)} MB/s
+                </span>
+              </div>
+
+              <div className="flex justify-between">
+                <span className="text-gray-600">残り時間</span>
+                <span className="font-semibold text-gray-900">
+                  {formatDuration(backupProgress.eta_secs)}
                 </span>
               </div>
 
@@ -92,4 +106,15 @@ function formatBytes(bytes) {
   return `${(bytes / Math.pow(k, i)).toFixed(2)} ${sizes[i]}`;
 }
 
+// 秒数を「時間:分:秒」形式に変換
+function formatDuration(secs) {
+  if (secs === null || secs === undefined) return '計算中...';
+
+  const h = Math.floor(secs / 3600);
+  const m = Math.floor((secs % 3600) / 60);
+  const s = secs % 60;
+
+  return `${h}:${String(m).padStart(2, '0')}:${String(s).padStart(2, '0')}`;
+}
+
 export default BackupProgress;
@@ -0,0 +1,153 @@
+import React, { useState } from "react";
+import { Copy, RefreshCw, Merge } from "lucide-react";
+import { useNASContext } from "../contexts/NASContext";
+import { invoke } from "@tauri-apps/api/core";
+import { ask } from "@tauri-apps/plugin-dialog";
+
+/**
+ * 複数のNASに重複しているロットの一覧・統合コンポーネント
+ */
+export default function DuplicateLots() {
+  const { isBackupRunning } = useNASContext(); // グローバルなNAS・外観検査機一覧
+  const [duplicates,setDuplicates]=useState(null); //重複ロット一覧（未確認の場合はnull）
+  const [isChecking,setIsChecking]=useState(false); //重複の確認中
+  const [isConsolidating,setIsConsolidating]=useState(false); //統合中
+
+  const isBusy=isBackupRunning || isChecking || isConsolidating;
+
+  //重複ロットを確認
+  const handleCheck=async ()=>{
+    if(isBackupRunning){
+      alert("バックアップ処理中は重複を確認できません");
+      return;
+    }
+
+    setIsChecking(true);
+    try {
+        const lots = await invoke("get_duplicate_lots");
+        setDuplicates(lots);
+    } catch (error) {
+        console.error("Failed to get duplicate lots:", error);
+        alert(`重複ロットの確認に失敗しました : ${error}`);
+    } finally {
+        setIsChecking(false);
+    }
+  }
+
+  //重複ロットを統合（lotを指定しない場合はすべて）
+  const handleConsolidate=async (lot)=>{
+    if(isBackupRunning){
+      alert("バックアップ処理中は統合できません");
+      return;
+    }
+
+    const target = lot ? `${lot.folder_path}/${lot.lot_name}` : `${duplicates.length}件の重複ロット`;
+    const result = await ask(`${target}をファイル数が最も多いNASにまとめ、他のNASのロットを世代フォルダ(_versions)に移しますか？\n両方にあるファイルの内容が異なるロットは統合しません。`, {
+      title: "統合の確認",
+      kind: "warning"
+    });
+    if (!result) {
+      return;
+    }
+
+    setIsConsolidating(true);
+    try {
+        const consolidation = await invoke("consolidate_duplicate_lots",{
+          folderPath:lot ? lot.folder_path : null,
+          lotName:lot ? lot.lot_name : null,
+        });
+        const errors = consolidation.errors.length > 0 ? `\nエラー : ${consolidation.errors.join("\n")}` : "";
+        alert(`${consolidation.lots_consolidated}ロットを統合しました（世代フォルダへの移動 ${consolidation.copies_removed}件・不足ファイルのコピー ${consolidation.files_merged}件）${errors}`);
+        setDuplicates(await invoke("get_duplicate_lots"));
+    } catch (error) {
+        console.error("Failed to consolidate duplicate lots:", error);
+        alert(`重複ロットの統合に失敗しました : ${error}`);
+    } finally {
+        setIsConsolidating(false);
+    }
+  }
+
+  return (
+    <div className="mt-6">
+      <div className="flex items-center justify-between mb-2">
+        <h3 className="text-lg text-black font-semibold font-mono flex items-center gap-2">
+          <Copy size={18} />
+          NAS間の重複ロット
+        </h3>
+        <div className="flex gap-2">
+          <button
+            disabled={isBusy}
+            onClick={()=>handleCheck()}
+            className={`flex items-center gap-2 px-4 py-1 rounded-lg transition-colors ${
+              isBusy
+                ? "bg-gray-700 text-gray-500 cursor-not-allowed"
+                : "bg-blue-700 hover:bg-blue-600 text-white"
+            }`}
+          >
+            <RefreshCw size={16} className={isChecking ? "animate-spin" : ""} />
+            {isChecking ? "確認中..." : "重複を確認"}
+          </button>
+          {duplicates && duplicates.length > 0 ? (
+            <button
+              disabled={isBusy}
+              onClick={()=>handleConsolidate(null)}
+              className={`flex items-center gap-2 px-4 py-1 rounded-lg transition-colors ${
+                isBusy
+                  ? "bg-gray-700 text-gray-500 cursor-not-allowed"
+                  : "bg-red-700 hover:bg-red-600 text-white"
+              }`}
+            >
+              <Merge size={16} />
+              {isConsolidating ? "統合中..." : "すべて統合"}
+            </button>
+          ) : null}
+        </div>
+      </div>
+
+      {duplicates === null ? null : duplicates.length === 0 ? (
+        <p className="text-gray-400 text-center py-4">重複しているロットはありません</p>
+      ) : (
+        <div className="space-y-2">
+          {duplicates.map((lot)=>(
+            <div key={`${lot.folder_path}/${lot.lot_name}`} className="bg-gray-800 rounded-lg p-3 flex items-center justify-between">
+              <div>
+                <p className="text-white font-mono">{lot.lot_name}</p>
+                <p className="text-xs text-gray-400 font-mono">{lot.folder_path}</p>
+                <div className="flex flex-wrap gap-2 mt-1">
+                  {lot.copies.map((copy)=>{
+                    const isKeep = copy.nas_id === lot.keep_nas_id && copy.folder_path === lot.folder_path;
+                    return (
+                      <span
+                        key={`${copy.nas_id}/${copy.folder_path}`}
+                        title={copy.folder_path}
+                        className={`px-2 py-0.5 rounded text-xs font-mono ${
+                          isKeep
+                            ? "bg-green-800 text-green-100"
+                            : "bg-gray-700 text-gray-300"
+                        }`}
+                      >
+                        {copy.nas_name}{copy.folder_path !== lot.folder_path ? ` (${copy.folder_path})` : ""}: {copy.file_count}ファイル{isKeep ? "（残す）" : ""}
+                      </span>
+                    );
+                  })}
+                </div>
+              </div>
+              <button
+                disabled={isBusy}
+                onClick={()=>handleConsolidate(lot)}
+                className={`flex items-center gap-2 px-3 py-1 rounded-lg transition-colors ${
+                  isBusy
+                    ? "bg-gray-700 text-gray-500 cursor-not-allowed"
+                    : "bg-red-700 hover:bg-red-600 text-white"
+                }`}
+              >
+                <Merge size={16} />
+                統合
+              </button>
+            </div>
+          ))}
+        </div>
+      )}
+    </div>
+  );
+}
@@ -2,6 +2,7 @@ import React, { useState,useEffect } from "react";
 import { X } from "lucide-react";
 import { useNASContext } from "../contexts/NASContext";
 import { invoke } from "@tauri-apps/api/core";
+import FileFilterFields from "./FileFilterFields";
 
 /**
  * 追加ダイアログコンポーネント
@@ -20,6 +21,10 @@ export default function EditInspDialog({ insp,isOpen, onClose}) {
     surface_result_path: insp.surface_result_path,
     back_result_path: insp.back_result_path,
     is_backup: insp.is_backup,
+    bandwidth_limit_mbps: insp.bandwidth_limit_mbps ?? 0,
+    file_filter: insp.file_filter,
+    source: insp.source, //パスワードは含まれない（バックエンドで現在の設定から補う）
+    is_continuous: insp.is_continuous ?? false,
   });
 const { inspList,setInspList } = useNASContext(); // グローバルなNAS・外観検査機一覧
 
@@ -34,6 +39,10 @@ const { inspList,setInspList } = useNASContext(); // グローバルなNAS・外
         surface_result_path: insp.surface_result_path,
         back_result_path: insp.back_result_path,
         is_backup: insp.is_backup,
+        bandwidth_limit_mbps: insp.bandwidth_limit_mbps ?? 0,
+        file_filter: insp.file_filter,
+        source: insp.source,
+        is_continuous: insp.is_continuous ?? false,
       });
     }
   }, [insp]);
@@ -156,6 +165,26 @@ const { inspList,setInspList } = useNASContext(); // グローバルなNAS・外
                 />
             </div>
 
+            <div>
+                <label className="block text-sm text-gray-700 mb-1">転送速度の上限(Mbps、0で無制限)</label>
+                <input
+                type="number"
+                min="0"
+                value={formData.bandwidth_limit_mbps}
+                onChange={(e) => setFormData({ ...formData, bandwidth_limit_mbps: parseInt(e.target.value) || 0 })}
+                className="w-full px-3 py-2 bg-gray-100 text-black rounded border border-gray-600 focus:border-blue-500 focus:outline-none"
+                placeholder="0"
+                />
+            </div>
+
+            <div>
+                <label className="block text-sm text-gray-700 mb-1">バックアップするファイルの絞り込み(glob形式、全カテゴリに適用)</label>
+                <FileFilterFields
+                filter={formData.file_filter}
+                onChange={(filter) => setFormData({ ...formData, file_filter: filter })}
+                />
+            </div>
+
           <div className="flex gap-3 pt-4">
             <button
               type="button"
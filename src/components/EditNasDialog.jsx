@@ -16,6 +16,7 @@ export default function EditNasDialog({ nas,isOpen, onClose}) {
     name: nas.name,
     nas_ip: nas.nas_ip,
     drive: nas.drive,
+    backend: nas.backend, //パスワード・シークレットキーは含まれない（バックエンドで現在の設定から補う）
   });
   const [isSubmitting, setIsSubmitting] = useState(false);
 const { nasList,setNasList } = useNASContext(); // グローバルなNAS・外観検査機一覧
@@ -27,6 +28,7 @@ const { nasList,setNasList } = useNASContext(); // グローバルなNAS・外
         name: nas.name,
         nas_ip: nas.nas_ip,
         drive: nas.drive,
+        backend: nas.backend,
       });
     }
   }, [nas]);
@@ -121,7 +123,7 @@ const { nasList,setNasList } = useNASContext(); // グローバルなNAS・外
             </div>
 
             <div>
-                <label className="block text-sm text-gray-400 mb-1">NAS ネットワークドライブ名</label>
+                <label className="block text-sm text-gray-400 mb-1">NAS ネットワークドライブ名 / UNCパス / マウントパス</label>
                 <input
                 type="text"
                 value={formData.drive}
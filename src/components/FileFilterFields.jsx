@@ -0,0 +1,61 @@
+import React from "react";
+
+/**
+ * バックアップするファイルの絞り込み（含める・除外するglobパターン、最大ファイルサイズ）の入力欄
+ * @param {Object} props
+ * @param {Object} props.filter - 絞り込み設定 { include, exclude, max_file_size_mb }
+ * @param {Function} props.onChange - 変更後の絞り込み設定を受け取るハンドラー
+ * @param {boolean} props.disabled - 入力不可にするか
+ */
+export default function FileFilterFields({ filter, onChange, disabled }) {
+  const current = {
+    include: filter?.include ?? [],
+    exclude: filter?.exclude ?? [],
+    max_file_size_mb: filter?.max_file_size_mb ?? 0,
+  };
+
+  //パターンは1行に1つ(空行はバックエンドで無視する)
+  const toLines=(patterns)=>patterns.join("\n");
+  const fromLines=(text)=>text.split("\n");
+
+  const inputClassName="w-full px-3 py-2 border border-gray-300 rounded-lg focus:ring-2 focus:ring-blue-500 focus:border-blue-500 outline-none transition-all text-gray-900 text-sm font-mono placeholder-gray-400 disabled:bg-gray-100 disabled:cursor-not-allowed";
+
+  return (
+    <div className="grid grid-cols-3 gap-2">
+      <div>
+        <span className="block text-xs text-gray-600 mb-1">含める(1行に1つ、空ですべて)</span>
+        <textarea
+          rows={2}
+          value={toLines(current.include)}
+          onChange={(e) => onChange({ ...current, include: fromLines(e.target.value) })}
+          disabled={disabled}
+          placeholder="*.jpg"
+          className={inputClassName}
+        />
+      </div>
+      <div>
+        <span className="block text-xs text-gray-600 mb-1">除外する(1行に1つ)</span>
+        <textarea
+          rows={2}
+          value={toLines(current.exclude)}
+          onChange={(e) => onChange({ ...current, exclude: fromLines(e.target.value) })}
+          disabled={disabled}
+          placeholder={"*.tmp\nthumbs/**"}
+          className={inputClassName}
+        />
+      </div>
+      <div>
+        <span className="block text-xs text-gray-600 mb-1">最大サイズ(MB、0で無制限)</span>
+        <input
+          type="number"
+          min="0"
+          value={current.max_file_size_mb}
+          onChange={(e) => onChange({ ...current, max_file_size_mb: parseInt(e.target.value) || 0 })}
+          disabled={disabled}
+          placeholder="0"
+          className={inputClassName}
+        />
+      </div>
+    </div>
+  );
+}
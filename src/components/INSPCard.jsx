@@ -16,6 +16,7 @@ export default function INSPCard({insp}) {
   const { isBackupRunning,inspList,setInspList } = useNASContext(); // グローバルなNAS・外観検査機一覧
   const [isEditDialogOpen,setIsEditDialogOpen]=useState(false); //外観検査機器の編集ダイアログの制御
   const isBackup = insp.is_backup === true; //バックアップを実施するかどうか
+  const isContinuous = insp.is_continuous === true; //連続バックアップを実施するかどうか
 
   //編集ボタンクリック時のハンドラ
   const handleEdit=(e)=>{
@@ -61,6 +62,40 @@ export default function INSPCard({insp}) {
     }
   }
 
+  //連続バックアップ設定変更ボタンクリック時のハンドラ(連続バックアップの有効・無効の切り替え)
+  const handleChangeContinuousSettings=async (e)=>{
+    e.stopPropagation();
+    if(isBackupRunning){
+      alert("バックアップ処理中は切り替えできません");
+      return;
+    }
+
+    try {
+        //バックエンドで更新を実施
+        const backend_insp_configs = await invoke("change_insp_continuous_settings",{inspId:insp.id});
+        console.log("backend_insp_configs",backend_insp_configs);
+
+        //受け取ったbackup_insp_configsと現在のinspListを合体
+        const new_insp_list=backend_insp_configs.map((backend_insp)=>{
+            let last_backuped="-";
+            inspList.forEach((current_insp)=>{
+                if(backend_insp.id===current_insp.id) last_backuped=current_insp.lastBackuped;
+            });
+            return {
+                ...backend_insp,
+                lastBackuped: last_backuped
+            };
+        });
+
+        //insplistを更新
+        setInspList(new_insp_list);
+        isContinuous ? alert(`${insp.name}の連続バックアップの無効化が完了しました`) : alert(`${insp.name}の連続バックアップの有効化が完了しました`)
+    } catch (error) {
+        console.error("Failed to Edit insp info:", error);
+        isContinuous ? alert(`連続バックアップの無効化に失敗しました : ${error}`) : alert(`連続バックアップの有効化に失敗しました : ${error}`)
+    }
+  }
+
   //検査装置を削除
   const handleDeleteSettings=async (e)=>{
     e.stopPropagation();
@@ -151,6 +186,21 @@ export default function INSPCard({insp}) {
             <div>
               <p className="text-sm text-gray-400 mb-1">IPアドレス</p>
               <p className="text-black font-mono">{insp.insp_ip}</p>
+              {insp.connection_error && (
+                <p className="text-sm text-red-600">{insp.connection_error}</p>
+              )}
+            </div>
+
+            <div>
+              <p className="text-sm text-gray-400 mb-1">接続状態</p>
+              <p className={insp.is_connected ? "text-green-700" : "text-red-600"}>
+                {insp.is_connected ? "接続中" : "未接続"}
+              </p>
+              {insp.unreadable_paths && insp.unreadable_paths.length > 0 && (
+                <p className="text-sm text-red-600">
+                  読み込めないパス: {insp.unreadable_paths.join(", ")}
+                </p>
+              )}
             </div>
 
             <div>
@@ -173,6 +223,21 @@ export default function INSPCard({insp}) {
               <p className="text-black font-mono">{insp.back_result_path}</p>
             </div>
 
+            <div>
+              <p className="text-sm text-gray-400 mb-1">最終バックアップ成功日時</p>
+              <p className={insp.is_backup_stale ? "text-red-600 font-mono" : "text-black font-mono"}>
+                {insp.last_successful_backup ?? "なし"}
+                {insp.is_backup_stale && " (バックアップが古くなっています)"}
+              </p>
+            </div>
+
+            <div>
+              <p className="text-sm text-gray-400 mb-1">連続バックアップ</p>
+              <p className={isContinuous ? "text-green-700" : "text-black"}>
+                {isContinuous ? "有効（ロットが確定し次第バックアップ）" : "無効（設定時刻のみ）"}
+              </p>
+            </div>
+
             {/* 情報編集ボタン */}
             <div className="border-t border-gray-700 pt-4">
               <button
@@ -205,6 +270,22 @@ export default function INSPCard({insp}) {
               </button>
             </div>
 
+            {/* 連続バックアップ切り替えボタン */}
+            <div className="border-t border-gray-700 pt-4">
+              <button
+                disabled={isBackupRunning}
+                onClick={(e)=>handleChangeContinuousSettings(e)}
+                className={`w-full flex items-center justify-center gap-2 px-4 py-1 rounded-lg transition-colors ${
+                  isBackupRunning
+                    ? "bg-gray-700 text-gray-500 cursor-not-allowed"
+                    : isContinuous ? "bg-orange-700 hover:bg-orange-600 text-white" : "bg-blue-700 hover:bg-blue-600 text-white"
+                }`}
+              >
+                <Cog size={16} />
+                {isBackupRunning ? "バックアップ処理中は編集できません" : isContinuous ? "この検査装置の連続バックアップを停止": "この検査装置の連続バックアップを開始"}
+              </button>
+            </div>
+
             {/* 削除ボタン */}
             <div className="border-t border-gray-700 pt-4">
               <button
@@ -0,0 +1,134 @@
+import React, { useState, useEffect } from "react";
+import { X, History } from "lucide-react";
+import { useNASContext } from "../contexts/NASContext";
+import { invoke } from "@tauri-apps/api/core";
+import { ask } from "@tauri-apps/plugin-dialog";
+
+/**
+ * ロットの世代一覧・復元ダイアログコンポーネント
+ * @param {Object} props
+ * @param {Object} props.nas - NAS情報
+ * @param {boolean} props.isOpen - ダイアログの開閉状態
+ * @param {Function} props.onClose - 閉じるハンドラー
+ */
+export default function LotVersionsDialog({ nas, isOpen, onClose }) {
+  const { isBackupRunning } = useNASContext(); // グローバルなNAS・外観検査機一覧
+  const [versionedLots,setVersionedLots]=useState([]); //世代が残っているロット一覧
+  const [isLoading,setIsLoading]=useState(false);
+  const [isRestoring,setIsRestoring]=useState(false);
+
+  const bytesToMB=(bytes)=>{
+    return (bytes/1024/1024).toFixed(1);
+  }
+
+  //世代名(YYYYMMDD_HHMMSS_mmm、ミリ秒の無い以前の世代名も可)を表示用に変換
+  const formatVersion=(version)=>{
+    const m = /^(\d{4})(\d{2})(\d{2})_(\d{2})(\d{2})(\d{2})(?:_(\d{3}))?$/.exec(version);
+    return m ? `${m[1]}/${m[2]}/${m[3]} ${m[4]}:${m[5]}:${m[6]}${m[7] ? `.${m[7]}` : ""}` : version;
+  }
+
+  const loadVersions=async ()=>{
+    setIsLoading(true);
+    try {
+        setVersionedLots(await invoke("get_lot_versions",{nasId:nas.id}));
+    } catch (error) {
+        console.error("Failed to get lot versions:", error);
+        alert(`世代一覧の取得に失敗しました : ${error}`);
+    } finally {
+        setIsLoading(false);
+    }
+  }
+
+  //開いた時に世代一覧を取得
+  useEffect(()=>{
+    if(!isOpen) return;
+    loadVersions();
+  },[isOpen,nas.id]);
+
+  //世代を復元
+  const handleRestore=async (lot,version)=>{
+    if(isBackupRunning){
+      alert("バックアップ処理中は復元できません");
+      return;
+    }
+
+    const result = await ask(`${lot.lot_name}を${formatVersion(version.version)}の状態に戻しますか？（世代に無いファイルはロットから取り除かれます。現在のファイルは新しい世代として残ります）`, {
+      title: "復元の確認",
+      kind: "warning"
+    });
+    if (!result) {
+      return;
+    }
+
+    setIsRestoring(true);
+    try {
+        const restored = await invoke("restore_lot_version",{
+          nasId:nas.id,
+          folderPath:lot.folder_path,
+          lotName:lot.lot_name,
+          version:version.version,
+        });
+        alert(`${lot.lot_name}を復元しました（${restored}ファイル）`);
+        await loadVersions();
+    } catch (error) {
+        console.error("Failed to restore lot version:", error);
+        alert(`${lot.lot_name}の復元に失敗しました : ${error}`);
+    } finally {
+        setIsRestoring(false);
+    }
+  }
+
+  if (!isOpen) return null;
+
+  return (
+    <div className="fixed inset-0 bg-black bg-opacity-50 flex items-center justify-center z-50">
+      <div className="bg-gray-100 rounded-lg p-6 w-full max-w-2xl max-h-[80vh] overflow-y-auto shadow-xl">
+        <div className="flex items-center justify-between mb-4">
+          <h2 className="text-xl font-bold font-mono text-black">{nas.name}のロットの世代</h2>
+          <button
+            onClick={onClose}
+            className="p-1 hover:bg-gray-700 rounded transition-colors"
+          >
+            <X size={24} className="text-gray-400" />
+          </button>
+        </div>
+
+        {isLoading ? (
+          <p className="text-gray-500 text-center py-8">読み込み中...</p>
+        ) : versionedLots.length === 0 ? (
+          <p className="text-gray-500 text-center py-8">世代が残っているロットはありません</p>
+        ) : (
+          <div className="space-y-3">
+            {versionedLots.map((lot)=>(
+              <div key={`${lot.folder_path}/${lot.lot_name}`} className="bg-white rounded-lg p-3 shadow-sm">
+                <p className="text-black font-mono">{lot.lot_name}</p>
+                <p className="text-xs text-gray-500 font-mono mb-2">{lot.folder_path}</p>
+                <div className="space-y-1">
+                  {lot.versions.map((version)=>(
+                    <div key={version.version} className="flex items-center justify-between text-sm text-gray-700">
+                      <span className="font-mono">
+                        {formatVersion(version.version)} / {version.file_count}ファイル / {bytesToMB(version.total_size_bytes)} MB
+                      </span>
+                      <button
+                        disabled={isBackupRunning || isRestoring}
+                        onClick={()=>handleRestore(lot,version)}
+                        className={`flex items-center gap-1 px-3 py-1 rounded-lg transition-colors ${
+                          isBackupRunning || isRestoring
+                            ? "bg-gray-300 text-gray-500 cursor-not-allowed"
+                            : "bg-blue-700 hover:bg-blue-600 text-white"
+                        }`}
+                      >
+                        <History size={14} />
+                        復元
+                      </button>
+                    </div>
+                  ))}
+                </div>
+              </div>
+            ))}
+          </div>
+        )}
+      </div>
+    </div>
+  );
+}
@@ -1,7 +1,8 @@
-import React, { useState } from "react";
-import { ChevronDown, CheckCircle, XCircle, Wifi, Database, Square, Cog, Trash2,Circle, CircleOff } from "lucide-react";
+import React, { useState, useEffect } from "react";
+import { ChevronDown, CheckCircle, XCircle, Wifi, Database, Square, Cog, Trash2,Circle, CircleOff, RefreshCw, History } from "lucide-react";
 import { useNASContext } from "../contexts/NASContext";
 import EditNasDialog from "./EditNasDialog";
+import LotVersionsDialog from "./LotVersionsDialog";
 import { invoke } from "@tauri-apps/api/core";
 import { ask } from "@tauri-apps/plugin-dialog";
 
@@ -15,13 +16,27 @@ export default function NASCard({nas}) {
   const [isExpanded, setIsExpanded] = useState(false);
   const [isConnecting, setIsConnecting] = useState(false);
   const isConnected = nas.is_connected === true;
+  const hasMappingError = isConnected && !!nas.mapping_error; //ドライブの接続先が別のNAS・ローカルディスク
+  const isDriveLetter = /^[A-Za-z]:?\\?$/.test(nas.drive); //ドライブレター以外はUNCパス・マウントポイント・ディレクトリ
   const { isBackupRunning,nasList,setNasList } = useNASContext(); // グローバルなNAS・外観検査機一覧
   const [isEditDialogOpen,setIsEditDialogOpen]=useState(false); //外観検査機器の編集ダイアログの制御
 
+  const [forecast,setForecast]=useState(null); //容量予測
+  const [isRescanning,setIsRescanning]=useState(false); //ロット一覧の再スキャン中
+  const [isVersionsDialogOpen,setIsVersionsDialogOpen]=useState(false); //ロットの世代ダイアログの制御
+
   const bytesToGB=(bytes)=>{
     return (bytes/1024/1024/1024).toFixed(2);
   }
 
+  //展開時に容量予測を取得
+  useEffect(()=>{
+    if(!isExpanded) return;
+    invoke("get_capacity_forecast")
+      .then((forecasts)=>setForecast(forecasts.find((f)=>f.nas_id===nas.id) ?? null))
+      .catch((error)=>console.error("Failed to get capacity forecast:", error));
+  },[isExpanded,nas.id]);
+
   //NASを編集
   const handleEdit=(e)=>{
     e.stopPropagation();
@@ -32,6 +47,26 @@ export default function NASCard({nas}) {
     setIsEditDialogOpen(true);
   }
 
+  //NAS上のロット一覧を再スキャン
+  const handleRescan=async (e)=>{
+    e.stopPropagation();
+    if(isBackupRunning){
+      alert("バックアップ処理中は再スキャンできません");
+      return;
+    }
+
+    setIsRescanning(true);
+    try {
+        const lots = await invoke("rescan_nas",{nasId:nas.id});
+        alert(`${nas.name}のロット一覧を再スキャンしました（${lots}ロット）`);
+    } catch (error) {
+        console.error("Failed to rescan nas:", error);
+        alert(`${nas.name}の再スキャンに失敗しました : ${error}`);
+    } finally {
+        setIsRescanning(false);
+    }
+  }
+
   //NASを削除
   const handleDeleteSettings=async (e)=>{
     e.stopPropagation();
@@ -102,12 +137,17 @@ export default function NASCard({nas}) {
           </div>
           <span
             className={`flex items-center gap-1 px-3 py-1 rounded-full text-sm font-medium ${
-              isConnected
+              isConnected && !hasMappingError
                 ? "bg-green-900/20 text-green-700"
                 : "bg-red-900/20 text-red-600"
             }`}
           >
-            {isConnected ? (
+            {hasMappingError ? (
+              <>
+                <XCircle size={16} />
+                接続先不一致
+              </>
+            ) : isConnected ? (
               <>
                 <CheckCircle size={16} />
                 接続中
@@ -136,15 +176,24 @@ export default function NASCard({nas}) {
             <div>
               <p className="text-sm text-gray-400 mb-1">IPアドレス</p>
               <p className="text-black font-mono">{nas.nas_ip}</p>
+              {nas.connection_error && (
+                <p className="text-red-600 text-sm mt-1">{nas.connection_error}</p>
+              )}
             </div>
 
             <div>
-              <p className="text-sm text-gray-400 mb-1">ネットワークドライブ</p>
-              <p className="text-black font-mono">{nas.drive}\:</p>
+              <p className="text-sm text-gray-400 mb-1">{isDriveLetter ? "ネットワークドライブ" : "保存先ルート"}</p>
+              <p className="text-black font-mono">{isDriveLetter ? `${nas.drive[0].toUpperCase()}:\\` : nas.drive}</p>
+              {hasMappingError && (
+                <p className="text-red-600 text-sm mt-1">{nas.mapping_error}</p>
+              )}
             </div>
 
             <div>
               <p className="text-sm text-gray-400 mb-1">容量</p>
+              {isConnected && nas.total_space === 0 && nas.backend?.type === "s3" ? (
+                <p className="text-black font-mono text-sm">容量制限なし（オブジェクトストレージ）</p>
+              ) : (
               <div className="space-y-2">
                 <div className="flex items-center justify-between">
                   <p className="text-black font-mono text-sm">
@@ -168,6 +217,21 @@ export default function NASCard({nas}) {
                   ></div>
                 </div>
               </div>
+              )}
+            </div>
+
+            <div>
+              <p className="text-sm text-gray-400 mb-1">容量予測</p>
+              {forecast && forecast.days_until_full !== null ? (
+                <p className={`font-mono text-sm ${forecast.days_until_full < 7 ? "text-red-600 font-semibold" : "text-black"}`}>
+                  必要空き容量まで約{Math.floor(forecast.days_until_full)}日
+                  （1日あたり+{bytesToGB(forecast.daily_growth_bytes ?? 0)} GB）
+                </p>
+              ) : forecast && forecast.daily_growth_bytes !== null ? (
+                <p className="text-black font-mono text-sm">使用量は増加していません</p>
+              ) : (
+                <p className="text-gray-500 text-sm">容量の記録が不足しているため予測できません</p>
+              )}
             </div>
 
             {/* 情報編集ボタン */}
@@ -186,6 +250,38 @@ export default function NASCard({nas}) {
               </button>
             </div>
 
+            {/* ロット一覧の再スキャンボタン */}
+            <div className="border-t border-gray-700 pt-4">
+              <button
+                disabled={isBackupRunning || isRescanning || !isConnected}
+                onClick={(e)=>handleRescan(e)}
+                className={`w-full flex items-center justify-center gap-2 px-4 py-1 rounded-lg transition-colors ${
+                  isBackupRunning || isRescanning || !isConnected
+                    ? "bg-gray-700 text-gray-500 cursor-not-allowed"
+                    : "bg-blue-700 hover:bg-blue-600 text-white"
+                }`}
+              >
+                <RefreshCw size={16} className={isRescanning ? "animate-spin" : ""} />
+                {isRescanning ? "再スキャン中..." : "NAS上のロット一覧を再スキャン"}
+              </button>
+            </div>
+
+            {/* ロットの世代ボタン */}
+            <div className="border-t border-gray-700 pt-4">
+              <button
+                disabled={!isConnected}
+                onClick={(e)=>{ e.stopPropagation(); setIsVersionsDialogOpen(true); }}
+                className={`w-full flex items-center justify-center gap-2 px-4 py-1 rounded-lg transition-colors ${
+                  !isConnected
+                    ? "bg-gray-700 text-gray-500 cursor-not-allowed"
+                    : "bg-blue-700 hover:bg-blue-600 text-white"
+                }`}
+              >
+                <History size={16} />
+                ロットの世代から復元
+              </button>
+            </div>
+
             {/* 削除ボタン */}
             <div className="border-t border-gray-700 pt-4">
               <button
@@ -210,6 +306,12 @@ export default function NASCard({nas}) {
         isOpen={isEditDialogOpen}
         onClose={() => setIsEditDialogOpen(false)}
       />
+      {/* ロットの世代ダイアログ */}
+      <LotVersionsDialog
+        nas={nas}
+        isOpen={isVersionsDialogOpen}
+        onClose={() => setIsVersionsDialogOpen(false)}
+      />
     </div>
   );
 }
@@ -1,7 +1,8 @@
-import { useEffect } from 'react'
+import { useEffect, useState } from 'react'
 import { Save } from 'lucide-react'
 import { useNASContext } from "../contexts/NASContext";
 import { invoke } from '@tauri-apps/api/core';
+import FileFilterFields from './FileFilterFields';
 
 function Settings() {
 
@@ -21,11 +22,15 @@ function Settings() {
     setRequiredFreeSpace,
   } = useNASContext(); // グローバルなNAS・外観検査機一覧
 
+  // 画面に個別の入力欄が無い設定も保存時に失われないよう、読み込んだ設定を保持する
+  const [loadedSettings, setLoadedSettings] = useState({})
+
   // コンポーネントマウント時に設定を読み込む
   useEffect(() => {
     const loadSettings = async () => {
       try {
         const settings = await invoke('get_settings')
+        setLoadedSettings(settings)
         setBackupStartTime(settings.backup_time)
         setSurfaceImageFolderPath(settings.surface_image_path)
         setBackImageFolderPath(settings.back_image_path)
@@ -43,6 +48,7 @@ function Settings() {
   const handleSave = async () => {
     try {
       const settingsToSave = {
+        ...loadedSettings,
         backup_time: backupStartTime,
         surface_image_path: surfaceImageFolderPath,
         back_image_path: backImageFolderPath,
@@ -162,6 +168,382 @@ function Settings() {
           />
         </div>
 
+        {/* バックアップ未成功の警告日数 */}
+        <div className="space-y-2">
+          <label className="block text-sm font-semibold text-gray-700">
+            バックアップ未成功の警告日数(0で無効)
+          </label>
+          <input
+            type="number"
+            value={loadedSettings.stale_backup_days ?? 3}
+            onChange={(e) => setLoadedSettings({ ...loadedSettings, stale_backup_days: parseInt(e.target.value) || 0 })}
+            disabled={isBackupRunning}
+            placeholder="3"
+            className="w-full px-4 py-2 border border-gray-300 rounded-lg focus:ring-2 focus:ring-blue-500 focus:border-blue-500 outline-none transition-all text-gray-900 placeholder-gray-400 disabled:bg-gray-100 disabled:cursor-not-allowed"
+          />
+        </div>
+
+        {/* 状態監視間隔 */}
+        <div className="space-y-2">
+          <label className="block text-sm font-semibold text-gray-700">
+            NAS・検査機器の状態監視間隔(秒)
+          </label>
+          <input
+            type="number"
+            value={loadedSettings.monitor_interval_secs ?? 10}
+            onChange={(e) => setLoadedSettings({ ...loadedSettings, monitor_interval_secs: parseInt(e.target.value) || 1 })}
+            disabled={isBackupRunning}
+            placeholder="10"
+            className="w-full px-4 py-2 border border-gray-300 rounded-lg focus:ring-2 focus:ring-blue-500 focus:border-blue-500 outline-none transition-all text-gray-900 placeholder-gray-400 disabled:bg-gray-100 disabled:cursor-not-allowed"
+          />
+        </div>
+
+        {/* 連続バックアップの確認間隔 */}
+        <div className="space-y-2">
+          <label className="block text-sm font-semibold text-gray-700">
+            連続バックアップの確認間隔(秒)
+          </label>
+          <input
+            type="number"
+            value={loadedSettings.continuous_poll_secs ?? 30}
+            onChange={(e) => setLoadedSettings({ ...loadedSettings, continuous_poll_secs: parseInt(e.target.value) || 1 })}
+            disabled={isBackupRunning}
+            placeholder="30"
+            className="w-full px-4 py-2 border border-gray-300 rounded-lg focus:ring-2 focus:ring-blue-500 focus:border-blue-500 outline-none transition-all text-gray-900 placeholder-gray-400 disabled:bg-gray-100 disabled:cursor-not-allowed"
+          />
+        </div>
+
+        {/* ロット確定までの待ち時間 */}
+        <div className="space-y-2">
+          <label className="block text-sm font-semibold text-gray-700">
+            ロット確定とみなす無変化時間(秒)
+          </label>
+          <input
+            type="number"
+            value={loadedSettings.continuous_stable_secs ?? 300}
+            onChange={(e) => setLoadedSettings({ ...loadedSettings, continuous_stable_secs: parseInt(e.target.value) || 0 })}
+            disabled={isBackupRunning}
+            placeholder="300"
+            className="w-full px-4 py-2 border border-gray-300 rounded-lg focus:ring-2 focus:ring-blue-500 focus:border-blue-500 outline-none transition-all text-gray-900 placeholder-gray-400 disabled:bg-gray-100 disabled:cursor-not-allowed"
+          />
+        </div>
+
+        {/* 同時にバックアップする検査機器の台数 */}
+        <div className="space-y-2">
+          <label className="block text-sm font-semibold text-gray-700">
+            同時にバックアップする検査機器の台数
+          </label>
+          <input
+            type="number"
+            min="1"
+            value={loadedSettings.max_parallel_devices ?? 4}
+            onChange={(e) => setLoadedSettings({ ...loadedSettings, max_parallel_devices: parseInt(e.target.value) || 1 })}
+            disabled={isBackupRunning}
+            placeholder="4"
+            className="w-full px-4 py-2 border border-gray-300 rounded-lg focus:ring-2 focus:ring-blue-500 focus:border-blue-500 outline-none transition-all text-gray-900 placeholder-gray-400 disabled:bg-gray-100 disabled:cursor-not-allowed"
+          />
+        </div>
+
+        {/* 検査機器ごとの同時コピー数 */}
+        <div className="space-y-2">
+          <label className="block text-sm font-semibold text-gray-700">
+            1台の検査機器から同時にコピーするロット数
+          </label>
+          <input
+            type="number"
+            min="1"
+            value={loadedSettings.max_parallel_lots_per_device ?? 1}
+            onChange={(e) => setLoadedSettings({ ...loadedSettings, max_parallel_lots_per_device: parseInt(e.target.value) || 1 })}
+            disabled={isBackupRunning}
+            placeholder="1"
+            className="w-full px-4 py-2 border border-gray-300 rounded-lg focus:ring-2 focus:ring-blue-500 focus:border-blue-500 outline-none transition-all text-gray-900 placeholder-gray-400 disabled:bg-gray-100 disabled:cursor-not-allowed"
+          />
+        </div>
+
+        {/* NASごとの同時書き込み数 */}
+        <div className="space-y-2">
+          <label className="block text-sm font-semibold text-gray-700">
+            1台のNASに同時に書き込むロット数
+          </label>
+          <input
+            type="number"
+            min="1"
+            value={loadedSettings.max_writers_per_nas ?? 4}
+            onChange={(e) => setLoadedSettings({ ...loadedSettings, max_writers_per_nas: parseInt(e.target.value) || 1 })}
+            disabled={isBackupRunning}
+            placeholder="4"
+            className="w-full px-4 py-2 border border-gray-300 rounded-lg focus:ring-2 focus:ring-blue-500 focus:border-blue-500 outline-none transition-all text-gray-900 placeholder-gray-400 disabled:bg-gray-100 disabled:cursor-not-allowed"
+          />
+        </div>
+
+        {/* ストリームコピーのバッファサイズ */}
+        <div className="space-y-2">
+          <label className="block text-sm font-semibold text-gray-700">
+            コピーのバッファサイズ(KiB)
+          </label>
+          <input
+            type="number"
+            min="4"
+            value={loadedSettings.copy_buffer_size_kib ?? 1024}
+            onChange={(e) => setLoadedSettings({ ...loadedSettings, copy_buffer_size_kib: parseInt(e.target.value) || 1024 })}
+            disabled={isBackupRunning}
+            placeholder="1024"
+            className="w-full px-4 py-2 border border-gray-300 rounded-lg focus:ring-2 focus:ring-blue-500 focus:border-blue-500 outline-none transition-all text-gray-900 placeholder-gray-400 disabled:bg-gray-100 disabled:cursor-not-allowed"
+          />
+        </div>
+
+        {/* ストリームコピーの先読み数 */}
+        <div className="space-y-2">
+          <label className="block text-sm font-semibold text-gray-700">
+            コピーの先読みバッファ数
+          </label>
+          <input
+            type="number"
+            min="1"
+            value={loadedSettings.copy_read_ahead_buffers ?? 4}
+            onChange={(e) => setLoadedSettings({ ...loadedSettings, copy_read_ahead_buffers: parseInt(e.target.value) || 1 })}
+            disabled={isBackupRunning}
+            placeholder="4"
+            className="w-full px-4 py-2 border border-gray-300 rounded-lg focus:ring-2 focus:ring-blue-500 focus:border-blue-500 outline-none transition-all text-gray-900 placeholder-gray-400 disabled:bg-gray-100 disabled:cursor-not-allowed"
+          />
+        </div>
+
+        {/* コピー先に同名ファイルがある場合の処理 */}
+        <div className="space-y-2">
+          <label className="block text-sm font-semibold text-gray-700">
+            コピー先に同名ファイルがある場合の処理
+          </label>
+          <div className="grid grid-cols-2 gap-2">
+            {[
+              ["surface_image_conflict_policy", "表面画像"],
+              ["back_image_conflict_policy", "裏面画像"],
+              ["surface_result_conflict_policy", "表面結果ファイル"],
+              ["back_result_conflict_policy", "裏面結果ファイル"],
+            ].map(([key, label]) => (
+              <div key={key}>
+                <span className="block text-xs text-gray-600 mb-1">{label}</span>
+                <select
+                  value={loadedSettings[key] ?? "overwrite"}
+                  onChange={(e) => setLoadedSettings({ ...loadedSettings, [key]: e.target.value })}
+                  disabled={isBackupRunning}
+                  className="w-full px-4 py-2 border border-gray-300 rounded-lg focus:ring-2 focus:ring-blue-500 focus:border-blue-500 outline-none transition-all text-gray-900 disabled:bg-gray-100 disabled:cursor-not-allowed"
+                >
+                  <option value="overwrite">上書き</option>
+                  <option value="skip_if_identical">同一ならスキップ（異なれば上書き）</option>
+                  <option value="keep_both">同一ならスキップ（異なれば番号を付けて両方残す）</option>
+                  <option value="fail">同一ならスキップ（異なれば失敗）</option>
+                </select>
+              </div>
+            ))}
+          </div>
+        </div>
+
+        {/* 同一ファイルの判定方法 */}
+        <div className="space-y-2">
+          <label className="block text-sm font-semibold text-gray-700">
+            同一ファイルの判定方法
+          </label>
+          <select
+            value={loadedSettings.identical_check ?? "size_and_mtime"}
+            onChange={(e) => setLoadedSettings({ ...loadedSettings, identical_check: e.target.value })}
+            disabled={isBackupRunning}
+            className="w-full px-4 py-2 border border-gray-300 rounded-lg focus:ring-2 focus:ring-blue-500 focus:border-blue-500 outline-none transition-all text-gray-900 disabled:bg-gray-100 disabled:cursor-not-allowed"
+          >
+            <option value="size_and_mtime">サイズと更新日時</option>
+            <option value="hash">サイズとハッシュ(SHA-256、コピー元・コピー先を読み込むため低速)</option>
+          </select>
+        </div>
+
+        {/* カテゴリごとのファイルの絞り込み */}
+        <div className="space-y-2">
+          <label className="block text-sm font-semibold text-gray-700">
+            バックアップするファイルの絞り込み(glob形式、検査機器ごとの絞り込みと合わせて適用)
+          </label>
+          {[
+            ["surface_image_file_filter", "表面画像"],
+            ["back_image_file_filter", "裏面画像"],
+            ["surface_result_file_filter", "表面結果ファイル"],
+            ["back_result_file_filter", "裏面結果ファイル"],
+          ].map(([key, label]) => (
+            <div key={key}>
+              <span className="block text-xs font-semibold text-gray-600 mb-1">{label}</span>
+              <FileFilterFields
+                filter={loadedSettings[key]}
+                onChange={(filter) => setLoadedSettings({ ...loadedSettings, [key]: filter })}
+                disabled={isBackupRunning}
+              />
+            </div>
+          ))}
+        </div>
+
+        {/* 保存先のパスのテンプレート */}
+        <div className="space-y-2">
+          <label className="block text-sm font-semibold text-gray-700">
+            保存先のパスのテンプレート
+          </label>
+          <input
+            type="text"
+            value={loadedSettings.dest_path_template ?? "{root}/{device}/{lot}"}
+            onChange={(e) => setLoadedSettings({ ...loadedSettings, dest_path_template: e.target.value })}
+            disabled={isBackupRunning}
+            placeholder="{root}/{device}/{lot}"
+            className="w-full px-4 py-2 border border-gray-300 rounded-lg focus:ring-2 focus:ring-blue-500 focus:border-blue-500 outline-none transition-all text-gray-900 font-mono placeholder-gray-400 disabled:bg-gray-100 disabled:cursor-not-allowed"
+          />
+          <p className="text-xs text-gray-500">
+            {"{root} NAS内保存パス、{category} カテゴリ名、{device} 検査機器名、{yyyy} {mm} {dd} 日付、{名前}・{番号} ロット名の正規表現のグループ。末尾は {lot} にしてください。"}
+            変更すると、変更前の階層に保存済みのロットはバックアップ済みとして認識されません。
+          </p>
+        </div>
+
+        {/* 保存先の日付の基準 */}
+        <div className="space-y-2">
+          <label className="block text-sm font-semibold text-gray-700">
+            保存先の日付({"{yyyy} {mm} {dd}"})の基準
+          </label>
+          <select
+            value={loadedSettings.dest_date_source ?? "backup_date"}
+            onChange={(e) => setLoadedSettings({ ...loadedSettings, dest_date_source: e.target.value })}
+            disabled={isBackupRunning}
+            className="w-full px-4 py-2 border border-gray-300 rounded-lg focus:ring-2 focus:ring-blue-500 focus:border-blue-500 outline-none transition-all text-gray-900 disabled:bg-gray-100 disabled:cursor-not-allowed"
+          >
+            <option value="backup_date">バックアップ日時</option>
+            <option value="folder_mtime">コピー元のロットフォルダの更新日時</option>
+          </select>
+        </div>
+
+        {/* ロット名の正規表現 */}
+        <div className="space-y-2">
+          <label className="block text-sm font-semibold text-gray-700">
+            ロット名の正規表現(テンプレートで使うグループを取り出す)
+          </label>
+          <input
+            type="text"
+            value={loadedSettings.lot_name_pattern ?? ""}
+            onChange={(e) => setLoadedSettings({ ...loadedSettings, lot_name_pattern: e.target.value })}
+            disabled={isBackupRunning}
+            placeholder="^(?P<model>[A-Z]+)-(\d+)"
+            className="w-full px-4 py-2 border border-gray-300 rounded-lg focus:ring-2 focus:ring-blue-500 focus:border-blue-500 outline-none transition-all text-gray-900 font-mono placeholder-gray-400 disabled:bg-gray-100 disabled:cursor-not-allowed"
+          />
+          <p className="text-xs text-gray-500">
+            一致しないロットは _unmatched フォルダに保存します。
+          </p>
+        </div>
+
+        {/* カテゴリ直下のファイルの扱い */}
+        <div className="space-y-2">
+          <label className="block text-sm font-semibold text-gray-700">
+            コピー元のカテゴリ直下のファイル（集計CSV・日次ログなど）
+          </label>
+          <select
+            value={loadedSettings.root_file_handling ?? "ignore"}
+            onChange={(e) => setLoadedSettings({ ...loadedSettings, root_file_handling: e.target.value })}
+            disabled={isBackupRunning}
+            className="w-full px-4 py-2 border border-gray-300 rounded-lg focus:ring-2 focus:ring-blue-500 focus:border-blue-500 outline-none transition-all text-gray-900 disabled:bg-gray-100 disabled:cursor-not-allowed"
+          >
+            <option value="ignore">バックアップしない</option>
+            <option value="backup">サイズ・更新日時が変わったファイルを _root_files フォルダにバックアップ</option>
+          </select>
+        </div>
+
+        {/* ロットの世代管理 */}
+        <div className="space-y-2">
+          <label className="flex items-center gap-2 text-sm font-semibold text-gray-700">
+            <input
+              type="checkbox"
+              checked={loadedSettings.lot_versioning ?? false}
+              onChange={(e) => setLoadedSettings({ ...loadedSettings, lot_versioning: e.target.checked })}
+              disabled={isBackupRunning}
+            />
+            ロットを再コピーする際、上書きするファイルを世代フォルダに残す
+          </label>
+        </div>
+
+        {/* ロットごとに残す世代数 */}
+        <div className="space-y-2">
+          <label className="block text-sm font-semibold text-gray-700">
+            ロットごとに残す世代数
+          </label>
+          <input
+            type="number"
+            min="1"
+            value={loadedSettings.max_lot_versions ?? 5}
+            onChange={(e) => setLoadedSettings({ ...loadedSettings, max_lot_versions: parseInt(e.target.value) || 1 })}
+            disabled={isBackupRunning || !(loadedSettings.lot_versioning ?? false)}
+            placeholder="5"
+            className="w-full px-4 py-2 border border-gray-300 rounded-lg focus:ring-2 focus:ring-blue-500 focus:border-blue-500 outline-none transition-all text-gray-900 placeholder-gray-400 disabled:bg-gray-100 disabled:cursor-not-allowed"
+          />
+        </div>
+
+        {/* 生産時間 */}
+        <div className="space-y-2">
+          <label className="block text-sm font-semibold text-gray-700">
+            生産時間(転送速度の上限の切り替え)
+          </label>
+          <div className="flex items-center gap-2">
+            <input
+              type="time"
+              value={loadedSettings.production_start_time ?? "08:00"}
+              onChange={(e) => setLoadedSettings({ ...loadedSettings, production_start_time: e.target.value })}
+              disabled={isBackupRunning}
+              className="w-full px-4 py-2 border border-gray-300 rounded-lg focus:ring-2 focus:ring-blue-500 focus:border-blue-500 outline-none transition-all text-gray-900 placeholder-gray-400 disabled:bg-gray-100 disabled:cursor-not-allowed"
+            />
+            <span className="text-gray-700">〜</span>
+            <input
+              type="time"
+              value={loadedSettings.production_end_time ?? "20:00"}
+              onChange={(e) => setLoadedSettings({ ...loadedSettings, production_end_time: e.target.value })}
+              disabled={isBackupRunning}
+              className="w-full px-4 py-2 border border-gray-300 rounded-lg focus:ring-2 focus:ring-blue-500 focus:border-blue-500 outline-none transition-all text-gray-900 placeholder-gray-400 disabled:bg-gray-100 disabled:cursor-not-allowed"
+            />
+          </div>
+        </div>
+
+        {/* 生産時間内の転送速度の上限 */}
+        <div className="space-y-2">
+          <label className="block text-sm font-semibold text-gray-700">
+            生産時間内の全体の転送速度の上限(Mbps、0で無制限)
+          </label>
+          <input
+            type="number"
+            value={loadedSettings.bandwidth_limit_mbps ?? 0}
+            onChange={(e) => setLoadedSettings({ ...loadedSettings, bandwidth_limit_mbps: parseInt(e.target.value) || 0 })}
+            disabled={isBackupRunning}
+            placeholder="0"
+            className="w-full px-4 py-2 border border-gray-300 rounded-lg focus:ring-2 focus:ring-blue-500 focus:border-blue-500 outline-none transition-all text-gray-900 placeholder-gray-400 disabled:bg-gray-100 disabled:cursor-not-allowed"
+          />
+        </div>
+
+        {/* 生産時間外の転送速度の上限 */}
+        <div className="space-y-2">
+          <label className="block text-sm font-semibold text-gray-700">
+            生産時間外の全体の転送速度の上限(Mbps、0で無制限)
+          </label>
+          <input
+            type="number"
+            value={loadedSettings.off_hours_bandwidth_limit_mbps ?? 0}
+            onChange={(e) => setLoadedSettings({ ...loadedSettings, off_hours_bandwidth_limit_mbps: parseInt(e.target.value) || 0 })}
+            disabled={isBackupRunning}
+            placeholder="0"
+            className="w-full px-4 py-2 border border-gray-300 rounded-lg focus:ring-2 focus:ring-blue-500 focus:border-blue-500 outline-none transition-all text-gray-900 placeholder-gray-400 disabled:bg-gray-100 disabled:cursor-not-allowed"
+          />
+        </div>
+
+        {/* 転送速度の自動調整 */}
+        <div className="space-y-2">
+          <label className="flex items-center gap-2 text-sm font-semibold text-gray-700">
+            <input
+              type="checkbox"
+              checked={loadedSettings.adaptive_throttle ?? false}
+              onChange={(e) => setLoadedSettings({ ...loadedSettings, adaptive_throttle: e.target.checked })}
+              disabled={isBackupRunning}
+            />
+            検査機器の読み込みが遅くなったら転送速度を自動で下げる
+          </label>
+          <p className="text-xs text-gray-500">
+            有効にするとSMB共有・ローカルの検査機器もOSのコピー機能を使わず、読み込みを調整しながらコピーします
+          </p>
+        </div>
+
         {/* 保存ボタン */}
         <div className="pt-4">
           <button
@@ -9,6 +9,7 @@ import { useNASContext } from "../contexts/NASContext";
 import AddInspDialog from "./AddInspDialog";
 import AddNasDialog from "./AddNasDialog";
 import BackupHisotry from "./BackupHisotry";
+import DuplicateLots from "./DuplicateLots";
 
 export default function StackCard() {
     const [loading, setLoading] = useState(true);
@@ -36,6 +37,7 @@ export default function StackCard() {
                     total_space: config.total_space,        //NASの全容量
                     used_space: config.used_space,    //NASの現在の容量
                     free_space: config.free_space,          //NASの現在の空容量
+                    backend: config.backend,                //コピー先の種類（パスワード・シークレットキーは含まれない）
                     lastReceived: "-",
                     data: null,
                 }));
@@ -50,6 +52,10 @@ export default function StackCard() {
                     surface_result_path: config.surface_result_path,
                     back_result_path: config.back_result_path,
                     is_backup: config.is_backup,            //転送実施するかどうか
+                    is_continuous: config.is_continuous,    //連続バックアップを実施するかどうか
+                    bandwidth_limit_mbps: config.bandwidth_limit_mbps, //転送速度の上限(Mbps)
+                    file_filter: config.file_filter,        //バックアップするファイルの絞り込み
+                    source: config.source,                  //コピー元へのアクセス方法（パスワードは含まれない）
                     lastBackuped: "-",
                 }));
                 setInspList(InspFormattedData);
@@ -257,6 +263,7 @@ export default function StackCard() {
                         )
                         }
                         </div>
+                        {nasList.length > 1 ? <DuplicateLots /> : null}
                     </>
                 ) : tab === "INSP" ? (
                     <>
@@ -1,5 +1,6 @@
 import { createContext, useContext, useState, useEffect } from 'react';
 import { listen } from '@tauri-apps/api/event';
+import { invoke } from '@tauri-apps/api/core';
 
 /**
  * NASリストを管理するContext
@@ -37,11 +38,34 @@ export const NASProvider = ({ children }) => {
   const [backupProgress, setBackupProgress] = useState(null)
   const [lastBackupDate, setLastBackupDate] = useState(null)
 
+  // バックエンドに保存されたバックアップ履歴を読み込む（最新20件）
+  const loadHistory = async () => {
+    try {
+      const page = await invoke('get_backup_history', { query: { page: 0, page_size: 20 } });
+      // 画面側は古い順のリストとして扱う
+      const histories = [...page.entries].reverse().map((entry) => ({
+        "complete": entry.status !== 'failed',
+        "success": entry.status === 'success',
+        "start_date": entry.start_time,
+        "end_date": entry.end_time,
+        "total_files": entry.result ? entry.result.total_files : 0,
+        "copied_files": entry.result ? entry.result.copied_files : 0,
+        "failed_files": entry.result ? entry.result.failed_files : 0,
+        "total_size_bytes": entry.result ? entry.result.total_size_bytes : 0,
+        "duration_secs": entry.result ? entry.result.duration_secs : 0,
+        "errors": entry.errors,
+        "devices": entry.result ? entry.result.devices : [],
+      }));
+      setHistoryList(histories);
+    } catch (error) {
+      console.error('バックアップ履歴の読み込みに失敗しました:', error);
+    }
+  };
+
   // すべてのイベントリスナーを統合
   useEffect(() => {
     let unlistenStarted, unlistenProgress, unlistenCompleted, unlistenFailed
-    let unlistenMessage, unlistenNasStatus
-    let currentBackupStartDate = ""; // クロージャ問題を回避するためのローカル変数
+    let unlistenMessage, unlistenNasStatus, unlistenInspStatus, unlistenStale
 
     const setupListeners = async () => {
       try {
@@ -90,11 +114,34 @@ export const NASProvider = ({ children }) => {
           );
         });
 
+        // insp-status-updatedリスナー
+        // 10sに1回受信
+        unlistenInspStatus = await listen('insp-status-updated', (event) => {
+          const insp_configs = event.payload;
+
+          // insp_configsの接続状態・バックアップ状態でinspListを更新
+          setInspList((prev) =>
+            prev.map((insp) => {
+              const updatedConfig = insp_configs.find(config => config.id === insp.id);
+              if (updatedConfig) {
+                return {
+                  ...insp,
+                  is_connected: updatedConfig.is_connected,
+                  unreadable_paths: updatedConfig.unreadable_paths,
+                  last_successful_backup: updatedConfig.last_successful_backup,
+                  category_backups: updatedConfig.category_backups,
+                  is_backup_stale: updatedConfig.is_backup_stale,
+                };
+              }
+              return insp;
+            })
+          );
+        });
+
         // バックアップ関連のリスナー
         // バックアップ開始
         unlistenStarted = await listen('backup-started', (event) => {
           console.log('Backup started:', event.payload)
-          currentBackupStartDate = event.payload; // ローカル変数に保存
           setIsBackupRunning(true)
           setBackupProgress(null)
         })
@@ -111,51 +158,26 @@ export const NASProvider = ({ children }) => {
           setIsBackupRunning(false)
           setBackupProgress(null)
 
-          //バックアップ履歴を残す（最大20件）
-          setHistoryList((prev) => {
-            const newHistory = {
-              "complete": true,
-              "start_date": currentBackupStartDate, // ローカル変数を使用
-              "end_date":event.payload[1],
-              "success": event.payload[0].success,
-              "total_files": event.payload[0].total_files,
-              "copied_files": event.payload[0].copied_files,
-              "failed_files": event.payload[0].failed_files,
-              "total_size_bytes": event.payload[0].total_size_bytes,
-              "duration_secs": event.payload[0].duration_secs,
-              "errors": event.payload[0].errors,
-            };
-            const updatedList = [...prev, newHistory];
-            // 20件を超える場合は最初の要素を削除
-            return updatedList.length > 20 ? updatedList.slice(1) : updatedList;
-          })
+          //バックエンドに保存された履歴を再読み込み
+          loadHistory()
 
           setLastBackupDate(new Date().toISOString())
         })
 
+        // バックアップ未成功の警告
+        unlistenStale = await listen('backup-stale-alert', (event) => {
+          const names = event.payload.map((insp) => insp.name).join(', ')
+          console.warn('Backup stale:', event.payload)
+          alert(`次の検査機器のバックアップがしばらく成功していません: ${names}`)
+        })
+
         // バックアップ失敗
         unlistenFailed = await listen('backup-failed', (event) => {
           console.error('Backup failed:', event.payload)
           setIsBackupRunning(false)
           setBackupProgress(null)
-          //バックアップ履歴を残す（最大20件）
-          setHistoryList((prev) => {
-            const newHistory = {
-              "complete": false,
-              "success": false,
-              "start_date": currentBackupStartDate, // ローカル変数を使用
-              "end_date":event.payload[1],
-              "total_files": 0,
-              "copied_files": 0,
-              "failed_files": 0,
-              "total_size_bytes": 0,
-              "duration_secs": 0,
-              "errors": event.payload[0],
-            };
-            const updatedList = [...prev, newHistory];
-            // 20件を超える場合は最初の要素を削除
-            return updatedList.length > 20 ? updatedList.slice(1) : updatedList;
-          })
+          //バックエンドに保存された履歴を再読み込み
+          loadHistory()
 
           alert(`バックアップに失敗しました: ${event.payload}`)
         })
@@ -165,15 +187,18 @@ export const NASProvider = ({ children }) => {
     }
 
     setupListeners()
+    loadHistory()
 
     return () => {
       // クリーンアップ
       if (unlistenMessage) unlistenMessage()
       if (unlistenNasStatus) unlistenNasStatus()
+      if (unlistenInspStatus) unlistenInspStatus()
       if (unlistenStarted) unlistenStarted()
       if (unlistenProgress) unlistenProgress()
       if (unlistenCompleted) unlistenCompleted()
       if (unlistenFailed) unlistenFailed()
+      if (unlistenStale) unlistenStale()
     }
   }, [])
 